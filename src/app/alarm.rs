@@ -0,0 +1,100 @@
+//! Sensor alarms.
+//!
+//! Alarm is active while the last reading of the sensor is rejected. Acknowledged alarm
+//! isn't displayed in the alarm color and it doesn't keep the display on until a new
+//! rejection arrives (rejected reading after an accepted one).
+use chrono::{DateTime, Utc};
+use state::{Location, Reading, Status};
+use std::collections::HashMap;
+
+/// Sensor alarms & their acknowledgement.
+#[derive(Default)]
+pub struct Alarms {
+    /// Timestamps of the acknowledged rejected readings.
+    acknowledged: HashMap<Location, DateTime<Utc>>,
+}
+
+impl Alarms {
+    /// Check if the alarm is active and not acknowledged.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - sensor location
+    /// * `reading` - last reading of the sensor
+    pub fn active(&self, location: Location, reading: Option<Reading>) -> bool {
+        reading.map_or(false, |r| r.status == Status::Rejected) && !self.acknowledged.contains_key(&location)
+    }
+
+    /// Acknowledge the alarm, returns `false` if there's no active alarm.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - sensor location
+    /// * `reading` - last reading of the sensor
+    pub fn acknowledge(&mut self, location: Location, reading: Option<Reading>) -> bool {
+        match reading {
+            Some(reading) if self.active(location, Some(reading)) => {
+                self.acknowledged.insert(location, reading.timestamp);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Forget acknowledgements of the alarms which ended, i.e. there's an accepted
+    /// reading since the acknowledged one.
+    ///
+    /// # Arguments
+    ///
+    /// * `readings_since` - readings of the sensor since the given time
+    pub fn update<F>(&mut self, readings_since: F)
+    where
+        F: Fn(Location, DateTime<Utc>) -> Vec<Reading>,
+    {
+        self.acknowledged.retain(|location, acknowledged| {
+            readings_since(*location, *acknowledged)
+                .iter()
+                .all(|r| r.status == Status::Rejected)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Alarms;
+    use chrono::{TimeZone, Utc};
+    use state::{Location, Reading, Status};
+    use w1::thermometer::Temperature;
+
+    fn reading(second: u32, status: Status) -> Reading {
+        Reading {
+            timestamp: Utc.ymd(2018, 7, 1).and_hms(12, 0, second),
+            raw: Temperature::new(85_000),
+            filtered: Temperature::new(21_000),
+            status,
+        }
+    }
+
+    #[test]
+    fn test_acknowledge() {
+        let mut alarms = Alarms::default();
+        let rejected = reading(0, Status::Rejected);
+        assert!(alarms.active(Location::Inside, Some(rejected)));
+        assert!(!alarms.active(Location::Inside, Some(reading(0, Status::Ok))));
+        assert!(!alarms.active(Location::Inside, None));
+
+        assert!(!alarms.acknowledge(Location::Outside, Some(reading(0, Status::Ok))));
+        assert!(alarms.acknowledge(Location::Inside, Some(rejected)));
+        assert!(!alarms.active(Location::Inside, Some(rejected)));
+        assert!(alarms.active(Location::Outside, Some(rejected)));
+        assert!(!alarms.acknowledge(Location::Inside, Some(rejected)));
+
+        // Rejections continue, alarm stays acknowledged
+        alarms.update(|_, _| vec![rejected, reading(1, Status::Rejected)]);
+        assert!(!alarms.active(Location::Inside, Some(reading(1, Status::Rejected))));
+
+        // Accepted reading ends the acknowledged alarm, next rejection is a new alarm
+        alarms.update(|_, _| vec![rejected, reading(2, Status::Ok), reading(3, Status::Rejected)]);
+        assert!(alarms.active(Location::Inside, Some(reading(3, Status::Rejected))));
+    }
+}
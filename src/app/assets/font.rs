@@ -1,7 +1,9 @@
-use super::glyphs;
+use super::{bundled_glyphs, glyphs};
+use error::Error;
 use piston_window::text;
 use piston_window::types::{Color, FontSize};
 use piston_window::{Context, DrawState, G2dTexture, GfxFactory, Glyphs, Graphics, Position, Transformed};
+use std::path::Path;
 
 /// Glyphs wrapper to simplify text rendering.
 pub struct Font {
@@ -18,20 +20,30 @@ impl Font {
         Font { glyphs }
     }
 
-    /// Create new `Font` from asset file name.
+    /// Create new `Font` from file.
     ///
     /// # Arguments
     ///
-    /// * `name` - asset file name with extension
+    /// * `path` - font file path
     /// * `factory` - Gfx backend factory
     ///
     /// # Note
     ///
-    /// Function panics if asset is not found or can't be loaded.
-    ///
     /// TTF fonts work. OTF and other fonts untested.
-    pub fn asset(name: &str, factory: GfxFactory) -> Font {
-        Font::new(glyphs(name, factory, None))
+    pub fn from_path<P>(path: P, factory: GfxFactory) -> Result<Font, Error>
+    where
+        P: AsRef<Path>,
+    {
+        glyphs(path.as_ref(), factory, None).map(Font::new)
+    }
+
+    /// Create new `Font` from the bundled default font.
+    ///
+    /// # Arguments
+    ///
+    /// * `factory` - Gfx backend factory
+    pub fn bundled(factory: GfxFactory) -> Result<Font, Error> {
+        bundled_glyphs(factory).map(Font::new)
     }
 
     /// Render text at given position.
@@ -1,7 +1,9 @@
-use super::texture;
+use super::{bundled_logo, texture};
+use error::Error;
 use piston_window::types::Rectangle;
 use piston_window::Image as PistonImage;
-use piston_window::{Context, DrawState, G2dTexture, GfxFactory, Graphics, ImageSize, Position};
+use piston_window::{Context, DrawState, G2dTexture, GfxFactory, Graphics, ImageSize};
+use std::path::Path;
 
 /// Texture wrapper to simplify drawing.
 pub struct Image {
@@ -28,43 +30,26 @@ impl Image {
         self.texture.get_height()
     }
 
-    /// Create new `Image` from asset file name.
+    /// Create new `Image` from file.
     ///
     /// # Arguments
     ///
-    /// * `name` - asset file name with extension
+    /// * `path` - image file path
     /// * `factory` - Gfx backend factory
-    ///
-    /// # Note
-    ///
-    /// Function panics if asset is not found or can't be loaded.
-    pub fn asset(name: &str, factory: &mut GfxFactory) -> Image {
-        Image::new(texture(name, factory, None))
+    pub fn from_path<P>(path: P, factory: &mut GfxFactory) -> Result<Image, Error>
+    where
+        P: AsRef<Path>,
+    {
+        texture(path.as_ref(), factory, None).map(Image::new)
     }
 
-    /// Draw self at given point.
+    /// Create new `Image` from the bundled default logo.
     ///
     /// # Arguments
     ///
-    /// * `position` - top / left position
-    /// * `c` - drawing context
-    /// * `g` - backend graphics
-    pub fn draw_at<P, G>(&self, position: P, c: &Context, g: &mut G)
-    where
-        P: Into<Position>,
-        G: Graphics<Texture = G2dTexture>,
-    {
-        let p = position.into();
-        self.draw_rect(
-            [
-                f64::from(p.x),
-                f64::from(p.y),
-                f64::from(self.texture.get_width()),
-                f64::from(self.texture.get_height()),
-            ],
-            c,
-            g,
-        );
+    /// * `factory` - Gfx backend factory
+    pub fn bundled_logo(factory: &mut GfxFactory) -> Result<Image, Error> {
+        bundled_logo(factory).map(Image::new)
     }
 
     /// Draw self in the given rectangle.
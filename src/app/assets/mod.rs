@@ -1,4 +1,7 @@
 //! Asset loading functions.
+//!
+//! Assets are loaded from the assets folder. Default font and logo are bundled
+//! (embedded) in the binary and used if the assets folder or an asset is missing.
 
 mod font;
 mod image;
@@ -6,61 +9,145 @@ mod image;
 pub use self::font::Font;
 pub use self::image::Image;
 
-use find_folder;
+use error::Error;
+use find_folder::Search;
+use image as img;
 use piston_window::{Flip, G2dTexture, GfxFactory, Glyphs, Texture, TextureSettings};
-use std::ffi::OsString;
 use std::path::{Path, PathBuf};
 
-lazy_static! {
-    /// Assets folder. All assets must be located directly in this folder (in the root).
-    static ref ASSETS_FOLDER: PathBuf = find_folder::Search::ParentsThenKids(3, 3).for_folder("assets").unwrap();
+/// Default font file name.
+pub const FONT_NAME: &str = "FiraMono-Regular.ttf";
 
-    /// Default `TextureSettings` in case `None` is provided as an argument for the `texture()` function.
-    static ref DEFAULT_TEXTURE_SETTINGS: TextureSettings = TextureSettings::new();
+/// Default logo file name.
+pub const LOGO_NAME: &str = "rust-logo.png";
+
+/// Bundled default font.
+const BUNDLED_FONT: &[u8] = include_bytes!("../../../assets/FiraMono-Regular.ttf");
+
+/// Bundled default logo.
+const BUNDLED_LOGO: &[u8] = include_bytes!("../../../assets/rust-logo.png");
+
+/// Find assets folder.
+///
+/// # Arguments
+///
+/// * `folder` - configured assets folder or `None` to search for the `assets` folder
+///   in the current folder, its parents and kids
+pub fn find_folder(folder: Option<&str>) -> Result<PathBuf, Error> {
+    match folder {
+        Some(folder) => {
+            let path = PathBuf::from(folder);
+            if path.is_dir() {
+                Ok(path)
+            } else {
+                Err(Error::Other(format!("Assets folder {} doesn't exist", folder)))
+            }
+        }
+        None => Search::ParentsThenKids(3, 3)
+            .for_folder("assets")
+            .map_err(|e| Error::Other(format!("Unable to find assets folder: {}", e))),
+    }
 }
 
-/// Create asset path.
+/// Asset paths to try in order.
 ///
 /// # Arguments
 ///
-/// * `name` - asset file name with extension
-fn asset_path(name: &str) -> OsString {
-    let mut path = ASSETS_FOLDER.clone();
-    path.push(Path::new(name));
-    path.into_os_string()
+/// * `custom` - custom asset path (theme, ...), preferred
+/// * `folder` - assets folder
+/// * `name` - asset file name inside the assets folder
+pub fn candidates(custom: Option<&Path>, folder: Option<&Path>, name: &str) -> Vec<PathBuf> {
+    custom
+        .map(Path::to_path_buf)
+        .into_iter()
+        .chain(folder.map(|folder| folder.join(name)))
+        .collect()
 }
 
 /// Load texture from file.
 ///
 /// # Arguments
 ///
-/// * `name` - asset file name with extension
+/// * `path` - image file path
 /// * `factory` -  Gfx backend factory
 /// * `settings` - `TextureSettings` or `None` if default settings should be used
-///
-/// # Note
-///
-/// Function panics if asset is not found or can't be loaded.
-pub fn texture(name: &str, factory: &mut GfxFactory, settings: Option<&TextureSettings>) -> G2dTexture {
+pub fn texture(path: &Path, factory: &mut GfxFactory, settings: Option<TextureSettings>) -> Result<G2dTexture, Error> {
     Texture::from_path(
         factory,
-        &asset_path(name),
+        path,
         Flip::None,
-        settings.unwrap_or(&DEFAULT_TEXTURE_SETTINGS),
-    ).unwrap()
+        &settings.unwrap_or_else(TextureSettings::new),
+    )
+    .map_err(|e| Error::Other(e).context(format!("Unable to load image {}", path.display())))
+}
+
+/// Load bundled logo texture.
+///
+/// # Arguments
+///
+/// * `factory` -  Gfx backend factory
+pub fn bundled_logo(factory: &mut GfxFactory) -> Result<G2dTexture, Error> {
+    let image = img::load_from_memory(BUNDLED_LOGO)
+        .map_err(|e| Error::Other(format!("Unable to decode bundled logo: {}", e)))?
+        .to_rgba();
+
+    Texture::from_image(factory, &image, &TextureSettings::new())
+        .map_err(|e| Error::Other(format!("Unable to create bundled logo texture: {:?}", e)))
 }
 
 /// Load glyphs from file.
 ///
 /// # Arguments
 ///
-/// * `name` - asset file name with extension
+/// * `path` - TTF font file path
 /// * `factory` - Gfx backend factory
 /// * `settings` - `TextureSettings` or `None` if default settings should be used
+pub fn glyphs(path: &Path, factory: GfxFactory, settings: Option<TextureSettings>) -> Result<Glyphs, Error> {
+    Glyphs::new(path, factory, settings.unwrap_or_else(TextureSettings::new))
+        .map_err(|e| Error::from(e).context(format!("Unable to load font {}", path.display())))
+}
+
+/// Load bundled font glyphs.
 ///
-/// # Note
+/// # Arguments
 ///
-/// Function panics if asset is not found or can't be loaded.
-pub fn glyphs(name: &str, factory: GfxFactory, settings: Option<TextureSettings>) -> Glyphs {
-    Glyphs::new(asset_path(name), factory, settings.unwrap_or_else(TextureSettings::new)).unwrap()
+/// * `factory` - Gfx backend factory
+pub fn bundled_glyphs(factory: GfxFactory) -> Result<Glyphs, Error> {
+    Glyphs::from_bytes(BUNDLED_FONT, factory, TextureSettings::new())
+        .map_err(|_| Error::Other("Unable to load bundled font".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{candidates, find_folder, img, BUNDLED_LOGO, FONT_NAME};
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_candidates() {
+        let folder = Path::new("/opt/thermometer/assets");
+
+        assert_eq!(
+            candidates(Some(Path::new("/etc/font.ttf")), Some(folder), FONT_NAME),
+            vec![
+                PathBuf::from("/etc/font.ttf"),
+                PathBuf::from("/opt/thermometer/assets/FiraMono-Regular.ttf"),
+            ]
+        );
+        assert_eq!(
+            candidates(None, Some(folder), FONT_NAME),
+            vec![PathBuf::from("/opt/thermometer/assets/FiraMono-Regular.ttf")]
+        );
+        assert!(candidates(None, None, FONT_NAME).is_empty());
+    }
+
+    #[test]
+    fn test_find_folder() {
+        assert!(find_folder(Some("/nonexistent/assets")).is_err());
+        assert!(find_folder(Some(env!("CARGO_MANIFEST_DIR"))).is_ok());
+    }
+
+    #[test]
+    fn test_bundled_logo() {
+        assert!(img::load_from_memory(BUNDLED_LOGO).is_ok());
+    }
 }
@@ -0,0 +1,195 @@
+//! Display backlight.
+//!
+//! Official Raspberry Pi display backlight is controlled with the `bl_power` (`0` on,
+//! `1` off) and `brightness` (`0` - `255`) files in `/sys/class/backlight/rpi_backlight`.
+//!
+//! Brightness follows the day / night schedule. Display is blanked in the blank period
+//! and after the idle timeout, touch wakes it. Alarm keeps the display on.
+use super::theme::NightTime;
+use chrono::NaiveTime;
+use error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Display stays on for this period after a touch in the blank period if there's no
+/// idle timeout (s).
+const WAKE_PERIOD: u64 = 30;
+
+/// Backlight power.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Power {
+    /// Backlight on with the brightness.
+    On(u8),
+    /// Display blanked.
+    Off,
+}
+
+/// Backlight schedule.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Schedule {
+    /// Day brightness.
+    pub day_brightness: u8,
+    /// Night brightness.
+    pub night_brightness: u8,
+    /// Night time.
+    pub night: NightTime,
+    /// Display is blanked in this period unless touched.
+    pub blank: Option<NightTime>,
+    /// Display is blanked after this period without touch.
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Schedule {
+    /// Backlight power.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - local time
+    /// * `idle` - time since the last touch
+    /// * `alarm` - `true` if any temperature requires attention
+    pub fn power(&self, time: NaiveTime, idle: Duration, alarm: bool) -> Power {
+        let woken = idle < self.idle_timeout.unwrap_or_else(|| Duration::from_secs(WAKE_PERIOD));
+        let blank = self.blank.map_or(false, |b| b.contains(time)) && !woken;
+        let idle = self.idle_timeout.map_or(false, |t| idle >= t);
+
+        if (blank || idle) && !alarm {
+            Power::Off
+        } else if self.night.contains(time) {
+            Power::On(self.night_brightness)
+        } else {
+            Power::On(self.day_brightness)
+        }
+    }
+}
+
+/// Backlight device.
+pub struct Backlight {
+    path: PathBuf,
+    power: Option<Power>,
+}
+
+impl Backlight {
+    /// Create new `Backlight`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - backlight folder (e.g. `/sys/class/backlight/rpi_backlight`)
+    pub fn new<P>(path: P) -> Backlight
+    where
+        P: Into<PathBuf>,
+    {
+        Backlight {
+            path: path.into(),
+            power: None,
+        }
+    }
+
+    /// Last set power, `None` if it was not set yet.
+    pub fn power(&self) -> Option<Power> {
+        self.power
+    }
+
+    /// Set backlight power, files are written only if the power changes.
+    ///
+    /// # Arguments
+    ///
+    /// * `power` - backlight power
+    pub fn set(&mut self, power: Power) -> Result<(), Error> {
+        if self.power == Some(power) {
+            return Ok(());
+        }
+
+        match power {
+            Power::On(brightness) => {
+                self.write("brightness", &brightness.to_string())?;
+                self.write("bl_power", "0")?;
+            }
+            Power::Off => self.write("bl_power", "1")?,
+        };
+
+        self.power = Some(power);
+        Ok(())
+    }
+
+    /// Write value to the backlight file.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - file name
+    /// * `value` - value
+    fn write(&self, name: &str, value: &str) -> Result<(), Error> {
+        let path = self.path.join(name);
+        fs::write(&path, value).map_err(|e| Error::from(e).context(format!("Unable to write {}", path.display())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backlight, Power, Schedule};
+    use app::theme::{parse_time_of_day, NightTime};
+    use std::env;
+    use std::fs;
+    use std::time::Duration;
+
+    fn schedule(blank: bool, idle_timeout: Option<u64>) -> Schedule {
+        Schedule {
+            day_brightness: 255,
+            night_brightness: 64,
+            night: NightTime {
+                start: parse_time_of_day("21:00").unwrap(),
+                end: parse_time_of_day("07:00").unwrap(),
+            },
+            blank: if blank {
+                Some(NightTime {
+                    start: parse_time_of_day("23:00").unwrap(),
+                    end: parse_time_of_day("06:00").unwrap(),
+                })
+            } else {
+                None
+            },
+            idle_timeout: idle_timeout.map(Duration::from_secs),
+        }
+    }
+
+    #[test]
+    fn test_schedule() {
+        let noon = parse_time_of_day("12:00").unwrap();
+        let evening = parse_time_of_day("22:00").unwrap();
+        let midnight = parse_time_of_day("00:00").unwrap();
+        let long = Duration::from_secs(3_600);
+        let short = Duration::from_secs(10);
+
+        let schedule = schedule(true, None);
+        assert_eq!(schedule.power(noon, long, false), Power::On(255));
+        assert_eq!(schedule.power(evening, long, false), Power::On(64));
+        assert_eq!(schedule.power(midnight, long, false), Power::Off);
+        // Touch wakes & alarm overrides blanking
+        assert_eq!(schedule.power(midnight, short, false), Power::On(64));
+        assert_eq!(schedule.power(midnight, long, true), Power::On(64));
+
+        let schedule = self::schedule(false, Some(60));
+        assert_eq!(schedule.power(noon, short, false), Power::On(255));
+        assert_eq!(schedule.power(noon, long, false), Power::Off);
+        assert_eq!(schedule.power(noon, long, true), Power::On(255));
+    }
+
+    #[test]
+    fn test_backlight() {
+        let folder = env::temp_dir().join("thermometer-backlight-test");
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+
+        let mut backlight = Backlight::new(&folder);
+        backlight.set(Power::On(128)).unwrap();
+        assert_eq!(fs::read_to_string(folder.join("brightness")).unwrap(), "128");
+        assert_eq!(fs::read_to_string(folder.join("bl_power")).unwrap(), "0");
+
+        backlight.set(Power::Off).unwrap();
+        assert_eq!(fs::read_to_string(folder.join("bl_power")).unwrap(), "1");
+        assert_eq!(backlight.power(), Some(Power::Off));
+
+        fs::remove_dir_all(&folder).unwrap();
+        assert!(Backlight::new(&folder).set(Power::Off).is_err());
+    }
+}
@@ -0,0 +1,278 @@
+//! Sensor labels.
+//!
+//! Label definition format is `LOCATION=NAME[,short:SHORT][,icon:ICON][,room:ROOM]`,
+//! for example `inside=Living room,short:Living,icon:home,room:Ground floor`. Sensors
+//! without a label are displayed with their default names.
+use super::locale::{Locale, Text};
+use error::Error;
+use state::Location;
+use std::str::FromStr;
+
+/// Sensor icon.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Icon {
+    /// House.
+    Home,
+    /// Tree (garden, outdoor).
+    Tree,
+    /// Snowflake (fridge, freezer).
+    Snowflake,
+    /// Flame (boiler, fireplace).
+    Flame,
+}
+
+impl AsRef<str> for Icon {
+    fn as_ref(&self) -> &str {
+        match self {
+            Icon::Home => "home",
+            Icon::Tree => "tree",
+            Icon::Snowflake => "snowflake",
+            Icon::Flame => "flame",
+        }
+    }
+}
+
+impl FromStr for Icon {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "home" => Ok(Icon::Home),
+            "tree" => Ok(Icon::Tree),
+            "snowflake" => Ok(Icon::Snowflake),
+            "flame" => Ok(Icon::Flame),
+            _ => Err(Error::from(format!("Invalid icon: {}", s))),
+        }
+    }
+}
+
+/// Default sensor icon.
+///
+/// # Arguments
+///
+/// * `location` - sensor location
+fn default_icon(location: Location) -> Option<Icon> {
+    match location {
+        Location::Inside => Some(Icon::Home),
+        Location::Outside => Some(Icon::Tree),
+        Location::Named(_) => None,
+    }
+}
+
+/// Sensor label.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Label {
+    /// Labeled sensor.
+    pub location: Location,
+    /// Display name.
+    pub name: String,
+    /// Short name used where the space is limited.
+    pub short_name: String,
+    /// Icon displayed next to the name.
+    pub icon: Option<Icon>,
+    /// Room or location of the sensor.
+    pub room: Option<String>,
+}
+
+impl Label {
+    /// Default label of the sensor.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - sensor location
+    /// * `locale` - locale of the default name
+    pub fn default_for(location: Location, locale: Locale) -> Label {
+        let name = match location {
+            Location::Inside => locale.text(Text::Inside),
+            Location::Outside => locale.text(Text::Outside),
+            Location::Named(name) => name,
+        };
+
+        Label {
+            location,
+            name: name.to_string(),
+            short_name: name.to_string(),
+            icon: default_icon(location),
+            room: None,
+        }
+    }
+}
+
+impl FromStr for Label {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut definition = s.splitn(2, '=');
+        let location = definition.next().unwrap_or("").trim().parse::<Location>()?;
+        let mut parts = definition
+            .next()
+            .ok_or_else(|| Error::from(format!("Invalid label: {}", s)))?
+            .split(',');
+
+        let name = parts.next().unwrap_or("").trim();
+        if name.is_empty() {
+            return Err(Error::from(format!("Missing label name: {}", s)));
+        }
+
+        let mut label = Label {
+            location,
+            name: name.to_string(),
+            short_name: name.to_string(),
+            icon: default_icon(location),
+            room: None,
+        };
+
+        for option in parts {
+            let mut option = option.trim().splitn(2, ':');
+
+            match (option.next(), option.next().map(str::trim)) {
+                (Some("short"), Some(short)) if !short.is_empty() => label.short_name = short.to_string(),
+                (Some("icon"), Some(icon)) => label.icon = Some(icon.parse()?),
+                (Some("room"), Some(room)) if !room.is_empty() => label.room = Some(room.to_string()),
+                _ => return Err(Error::from(format!("Invalid label option: {}", s))),
+            }
+        }
+
+        Ok(label)
+    }
+}
+
+/// Parse semicolon separated labels.
+///
+/// # Arguments
+///
+/// * `s` - labels
+pub fn parse_labels(s: &str) -> Result<Vec<Label>, Error> {
+    let mut labels: Vec<Label> = vec![];
+
+    for definition in s.split(';').filter(|d| !d.trim().is_empty()) {
+        let label = definition.parse::<Label>()?;
+
+        if labels.iter().any(|l| l.location == label.location) {
+            return Err(Error::from(format!("Duplicate label: {}", label.location.as_ref())));
+        }
+
+        labels.push(label);
+    }
+
+    Ok(labels)
+}
+
+/// Parse comma separated sensors order.
+///
+/// # Arguments
+///
+/// * `s` - sensors
+pub fn parse_order(s: &str) -> Result<Vec<Location>, Error> {
+    let mut order: Vec<Location> = vec![];
+
+    for location in s.split(',').filter(|l| !l.trim().is_empty()) {
+        let location = location.trim().parse::<Location>()?;
+
+        if order.contains(&location) {
+            return Err(Error::from(format!("Duplicate sensor in order: {}", location.as_ref())));
+        }
+
+        order.push(location);
+    }
+
+    Ok(order)
+}
+
+/// Check that labeled & ordered sensors exist.
+///
+/// # Arguments
+///
+/// * `labels` - labels
+/// * `order` - sensors order
+/// * `sensors` - all sensors, virtual sensors included
+pub fn check_sensors(labels: &[Label], order: &[Location], sensors: &[Location]) -> Result<(), Error> {
+    let locations = labels.iter().map(|l| &l.location).chain(order.iter());
+
+    for location in locations {
+        if !sensors.contains(location) {
+            return Err(Error::from(format!("Unknown sensor {}", location.as_ref())));
+        }
+    }
+
+    Ok(())
+}
+
+/// Sensors in the given order, sensors missing in the order follow in the original order.
+///
+/// # Arguments
+///
+/// * `sensors` - sensors
+/// * `order` - sensors order
+pub fn ordered(sensors: &[Location], order: &[Location]) -> Vec<Location> {
+    let mut ordered: Vec<Location> = order.iter().filter(|l| sensors.contains(l)).cloned().collect();
+    ordered.extend(sensors.iter().filter(|l| !order.contains(l)));
+    ordered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_sensors, ordered, parse_labels, parse_order, Icon, Label};
+    use state::Location;
+
+    #[test]
+    fn test_parse_label() {
+        let label = "inside=Living room,short:Living,icon:home,room:Ground floor"
+            .parse::<Label>()
+            .unwrap();
+        assert_eq!(
+            label,
+            Label {
+                location: Location::Inside,
+                name: "Living room".to_string(),
+                short_name: "Living".to_string(),
+                icon: Some(Icon::Home),
+                room: Some("Ground floor".to_string()),
+            }
+        );
+
+        let label = "outside=Garden".parse::<Label>().unwrap();
+        assert_eq!(label.short_name, "Garden");
+        assert_eq!(label.icon, Some(Icon::Tree));
+        assert_eq!("garage=Garage".parse::<Label>().unwrap().icon, None);
+
+        assert!("inside".parse::<Label>().is_err());
+        assert!("inside=".parse::<Label>().is_err());
+        assert!("inside=Living,icon:sofa".parse::<Label>().is_err());
+        assert!("inside=Living,color:red".parse::<Label>().is_err());
+    }
+
+    #[test]
+    fn test_parse_labels_and_order() {
+        assert_eq!(parse_labels("inside=Living; outside=Garden").unwrap().len(), 2);
+        assert!(parse_labels("inside=Living;inside=Kitchen").is_err());
+
+        assert_eq!(
+            parse_order("outside, inside").unwrap(),
+            vec![Location::Outside, Location::Inside]
+        );
+        assert!(parse_order("outside,outside").is_err());
+    }
+
+    #[test]
+    fn test_check_sensors() {
+        let sensors = [Location::Inside, Location::Outside];
+        let labels = parse_labels("inside=Living").unwrap();
+        assert!(check_sensors(&labels, &[Location::Outside], &sensors).is_ok());
+
+        let garage = "garage".parse::<Location>().unwrap();
+        assert!(check_sensors(&labels, &[garage], &sensors).is_err());
+        assert!(check_sensors(&parse_labels("garage=Garage").unwrap(), &[], &sensors).is_err());
+    }
+
+    #[test]
+    fn test_ordered() {
+        let garage = "garage".parse::<Location>().unwrap();
+        let sensors = [Location::Inside, Location::Outside, garage];
+        assert_eq!(
+            ordered(&sensors, &[garage, Location::Outside]),
+            vec![garage, Location::Outside, Location::Inside]
+        );
+        assert_eq!(ordered(&sensors, &[]), sensors.to_vec());
+    }
+}
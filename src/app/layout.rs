@@ -0,0 +1,348 @@
+//! Layout scaled from the window size.
+//!
+//! All positions and font sizes are designed for the 800x480 reference screen
+//! (official Raspberry Pi 7" touchscreen) and scaled to the actual window size.
+use error::Error;
+use piston_window::types::{FontSize, Rectangle};
+use piston_window::{Context, Size, Transformed};
+use std::str::FromStr;
+
+/// Reference screen width.
+const REFERENCE_WIDTH: f64 = 800.0;
+
+/// Reference screen height.
+const REFERENCE_HEIGHT: f64 = 480.0;
+
+/// Top (time) & bottom (date, logo) bar height.
+const BAR_HEIGHT: f64 = 40.0;
+
+/// Screen edges padding.
+const PADDING: f64 = 15.0;
+
+/// Monospaced font glyph width relative to the font size.
+const GLYPH_WIDTH: f64 = 0.6;
+
+/// Smallest readable font size.
+const MIN_FONT_SIZE: FontSize = 8;
+
+/// Layout mode.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum LayoutMode {
+    /// Sensor title & temperature with details, graph or stats.
+    Standard,
+    /// Temperature only, as big as possible (one sensor per page).
+    BigNumber,
+}
+
+impl AsRef<str> for LayoutMode {
+    fn as_ref(&self) -> &str {
+        match self {
+            LayoutMode::Standard => "standard",
+            LayoutMode::BigNumber => "big-number",
+        }
+    }
+}
+
+impl FromStr for LayoutMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "standard" => Ok(LayoutMode::Standard),
+            "big-number" => Ok(LayoutMode::BigNumber),
+            _ => Err(Error::from(format!("Invalid layout mode: {}", s))),
+        }
+    }
+}
+
+/// Display rotation (clockwise).
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Rotation {
+    Deg0,
+    Deg90,
+    Deg180,
+    Deg270,
+}
+
+impl AsRef<str> for Rotation {
+    fn as_ref(&self) -> &str {
+        match self {
+            Rotation::Deg0 => "0",
+            Rotation::Deg90 => "90",
+            Rotation::Deg180 => "180",
+            Rotation::Deg270 => "270",
+        }
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "0" => Ok(Rotation::Deg0),
+            "90" => Ok(Rotation::Deg90),
+            "180" => Ok(Rotation::Deg180),
+            "270" => Ok(Rotation::Deg270),
+            _ => Err(Error::from(format!("Invalid display rotation: {}", s))),
+        }
+    }
+}
+
+/// Layout options.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct LayoutOptions {
+    /// Layout mode.
+    pub mode: LayoutMode,
+    /// Display rotation.
+    pub rotation: Rotation,
+    /// Additional scale applied to font sizes.
+    pub scale: f64,
+}
+
+/// Layout for the given window size.
+pub struct Layout {
+    window_size: Size,
+    width: f64,
+    height: f64,
+    scale: f64,
+    font_scale: f64,
+    options: LayoutOptions,
+}
+
+impl Layout {
+    /// Create new `Layout`.
+    ///
+    /// # Arguments
+    ///
+    /// * `window_size` - window size
+    /// * `options` - layout options
+    pub fn new(window_size: Size, options: LayoutOptions) -> Layout {
+        let (width, height) = match options.rotation {
+            Rotation::Deg0 | Rotation::Deg180 => (f64::from(window_size.width), f64::from(window_size.height)),
+            Rotation::Deg90 | Rotation::Deg270 => (f64::from(window_size.height), f64::from(window_size.width)),
+        };
+
+        // Portrait layout is scaled as the reference screen rotated
+        let (reference_width, reference_height) = if height > width {
+            (REFERENCE_HEIGHT, REFERENCE_WIDTH)
+        } else {
+            (REFERENCE_WIDTH, REFERENCE_HEIGHT)
+        };
+        let scale = (width / reference_width).min(height / reference_height);
+
+        Layout {
+            window_size,
+            width,
+            height,
+            scale,
+            font_scale: scale * options.scale,
+            options,
+        }
+    }
+
+    /// Layout mode.
+    pub fn mode(&self) -> LayoutMode {
+        self.options.mode
+    }
+
+    /// Scale value designed for the reference screen.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - reference screen value
+    pub fn scaled(&self, value: f64) -> f64 {
+        value * self.scale
+    }
+
+    /// Scale font size designed for the reference screen.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - reference screen font size
+    pub fn font_size(&self, size: FontSize) -> FontSize {
+        ((f64::from(size) * self.font_scale).round() as FontSize).max(MIN_FONT_SIZE)
+    }
+
+    /// Approximate text width.
+    ///
+    /// Assumes monospaced font with glyph width ~0.6 of the font size.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - text
+    /// * `size` - font size
+    pub fn text_width(&self, text: &str, size: FontSize) -> f64 {
+        text.chars().count() as f64 * f64::from(size) * GLYPH_WIDTH
+    }
+
+    /// Largest font size for a text to fit into the area.
+    ///
+    /// Assumes monospaced font with glyph width ~0.6 of the font size.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - text
+    /// * `area` - area
+    pub fn fit_font_size(&self, text: &str, area: Rectangle) -> FontSize {
+        let chars = text.chars().count().max(1) as f64;
+        let size = (area[2] / (chars * GLYPH_WIDTH)).min(area[3] * 0.8);
+        (size.floor() as FontSize).max(MIN_FONT_SIZE)
+    }
+
+    /// Padding from the screen edges.
+    pub fn padding(&self) -> f64 {
+        self.scaled(PADDING)
+    }
+
+    /// Time baseline position.
+    pub fn time_position(&self) -> [i32; 2] {
+        [self.padding() as i32, self.scaled(22.0) as i32]
+    }
+
+    /// Date baseline position.
+    pub fn date_position(&self) -> [i32; 2] {
+        [self.padding() as i32, (self.height - self.scaled(20.0)) as i32]
+    }
+
+    /// Weather forecast area in the top bar (right side).
+    pub fn weather_area(&self) -> Rectangle {
+        let width = self.width / 2.0 - self.padding();
+        [self.width / 2.0, 0.0, width, self.scaled(BAR_HEIGHT)]
+    }
+
+    /// Logo rectangle, logo is in the bottom right corner.
+    ///
+    /// # Arguments
+    ///
+    /// * `width` - logo width (reference screen)
+    /// * `height` - logo height (reference screen)
+    pub fn logo_rect(&self, width: f64, height: f64) -> Rectangle {
+        let (width, height) = (self.scaled(width), self.scaled(height));
+        [
+            self.width - width - self.padding(),
+            self.height - height - self.padding(),
+            width,
+            height,
+        ]
+    }
+
+    /// Areas of the sensors (x, y, width, height), sensors are stacked vertically.
+    ///
+    /// # Arguments
+    ///
+    /// * `count` - number of sensors
+    pub fn sensor_areas(&self, count: usize) -> Vec<Rectangle> {
+        if count == 0 {
+            return vec![];
+        }
+
+        let bar_height = self.scaled(BAR_HEIGHT);
+        let width = self.width - 2.0 * self.padding();
+        let height = (self.height - 2.0 * bar_height) / count as f64;
+
+        (0..count)
+            .map(|i| [self.padding(), bar_height + i as f64 * height, width, height])
+            .collect()
+    }
+
+    /// Drawing context with the display rotation applied.
+    ///
+    /// # Arguments
+    ///
+    /// * `c` - window context
+    pub fn transform(&self, c: Context) -> Context {
+        let (w, h) = (f64::from(self.window_size.width), f64::from(self.window_size.height));
+
+        match self.options.rotation {
+            Rotation::Deg0 => c,
+            Rotation::Deg90 => c.trans(w, 0.0).rot_deg(90.0),
+            Rotation::Deg180 => c.trans(w, h).rot_deg(180.0),
+            Rotation::Deg270 => c.trans(0.0, h).rot_deg(270.0),
+        }
+    }
+
+    /// Convert window position (mouse, touch) to the layout position.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - window position
+    pub fn position(&self, position: [f64; 2]) -> [f64; 2] {
+        let (w, h) = (f64::from(self.window_size.width), f64::from(self.window_size.height));
+        let [x, y] = position;
+
+        match self.options.rotation {
+            Rotation::Deg0 => [x, y],
+            Rotation::Deg90 => [y, w - x],
+            Rotation::Deg180 => [w - x, h - y],
+            Rotation::Deg270 => [h - y, x],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Layout, LayoutMode, LayoutOptions, Rotation};
+    use piston_window::Size;
+
+    fn layout(width: u32, height: u32, rotation: Rotation) -> Layout {
+        Layout::new(
+            Size { width, height },
+            LayoutOptions {
+                mode: LayoutMode::Standard,
+                rotation,
+                scale: 1.0,
+            },
+        )
+    }
+
+    #[test]
+    fn test_reference_screen() {
+        let layout = layout(800, 480, Rotation::Deg0);
+
+        assert_eq!(layout.font_size(14), 14);
+        assert_eq!(layout.time_position(), [15, 22]);
+        assert_eq!(layout.date_position(), [15, 460]);
+        assert_eq!(layout.weather_area(), [400.0, 0.0, 385.0, 40.0]);
+        assert_eq!(layout.text_width("25 °C", 20), 60.0);
+        assert_eq!(
+            layout.sensor_areas(2),
+            vec![[15.0, 40.0, 770.0, 200.0], [15.0, 240.0, 770.0, 200.0]]
+        );
+        assert!(layout.sensor_areas(0).is_empty());
+    }
+
+    #[test]
+    fn test_scaling() {
+        assert_eq!(layout(480, 320, Rotation::Deg0).font_size(14), 8);
+        assert_eq!(layout(1920, 1080, Rotation::Deg0).font_size(14), 32);
+        assert_eq!(layout(1024, 600, Rotation::Deg0).font_size(20), 25);
+    }
+
+    #[test]
+    fn test_portrait_rotation() {
+        let layout = layout(800, 480, Rotation::Deg90);
+
+        assert_eq!((layout.width, layout.height), (480.0, 800.0));
+        assert_eq!(layout.font_size(14), 14);
+        assert_eq!(layout.position([800.0, 0.0]), [0.0, 0.0]);
+        assert_eq!(layout.position([700.0, 10.0]), [10.0, 100.0]);
+    }
+
+    #[test]
+    fn test_position_rotation() {
+        assert_eq!(
+            layout(800, 480, Rotation::Deg180).position([790.0, 470.0]),
+            [10.0, 10.0]
+        );
+        assert_eq!(layout(800, 480, Rotation::Deg270).position([10.0, 470.0]), [10.0, 10.0]);
+    }
+
+    #[test]
+    fn test_fit_font_size() {
+        let layout = layout(800, 480, Rotation::Deg0);
+
+        assert_eq!(layout.fit_font_size("21.5 °C", [0.0, 0.0, 770.0, 400.0]), 183);
+        assert_eq!(layout.fit_font_size("21.5 °C", [0.0, 0.0, 770.0, 100.0]), 80);
+    }
+}
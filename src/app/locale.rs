@@ -0,0 +1,253 @@
+//! UI localisation.
+//!
+//! Locale selects the translation catalogue of UI texts, time & date format, decimal
+//! separator and default temperature units.
+use chrono::{NaiveDate, NaiveTime};
+use error::Error;
+use std::str::FromStr;
+use w1::thermometer::Units;
+
+/// Locale.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Locale {
+    /// English, ISO date & 24-hour time.
+    English,
+    /// English (United States).
+    EnglishUs,
+    /// Czech (Czech Republic).
+    Czech,
+    /// German (Germany).
+    German,
+}
+
+/// UI text.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Text {
+    /// Inside sensor name.
+    Inside,
+    /// Outside sensor name.
+    Outside,
+    /// Missing temperature.
+    NotAvailable,
+    /// No readings yet.
+    NoReadings,
+    /// Raw temperature.
+    Raw,
+    /// Reading status.
+    Status,
+    /// Ok status.
+    StatusOk,
+    /// Rejected status.
+    StatusRejected,
+    /// Reading time.
+    Updated,
+    /// Temperature trend.
+    Trend,
+    /// Lowest temperature.
+    Min,
+    /// Highest temperature.
+    Max,
+    /// Average temperature.
+    Average,
+    /// Number of readings.
+    Readings,
+    /// Readings since time.
+    Since,
+    /// System clock is not synchronised.
+    ClockNotSynced,
+}
+
+impl Locale {
+    /// Translated text.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - text to translate
+    pub fn text(self, text: Text) -> &'static str {
+        match self {
+            Locale::English | Locale::EnglishUs => english(text),
+            Locale::Czech => czech(text),
+            Locale::German => german(text),
+        }
+    }
+
+    /// Default temperature units.
+    pub fn units(self) -> Units {
+        match self {
+            Locale::EnglishUs => Units::Fahrenheit,
+            _ => Units::Celsius,
+        }
+    }
+
+    /// Time formatted as `String` (12 or 24-hour format).
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - time
+    pub fn time(self, time: NaiveTime) -> String {
+        match self {
+            Locale::EnglishUs => time.format("%I:%M:%S %p").to_string(),
+            _ => time.format("%H:%M:%S").to_string(),
+        }
+    }
+
+    /// Date formatted as `String`.
+    ///
+    /// # Arguments
+    ///
+    /// * `date` - date
+    pub fn date(self, date: NaiveDate) -> String {
+        match self {
+            Locale::English => date.format("%Y-%m-%d").to_string(),
+            Locale::EnglishUs => date.format("%m/%d/%Y").to_string(),
+            Locale::Czech => date.format("%-d. %-m. %Y").to_string(),
+            Locale::German => date.format("%d.%m.%Y").to_string(),
+        }
+    }
+
+    /// Number formatted with the locale decimal separator.
+    ///
+    /// # Arguments
+    ///
+    /// * `formatted` - formatted number(s) with `.` decimal separator
+    pub fn decimal<S>(self, formatted: S) -> String
+    where
+        S: Into<String>,
+    {
+        match self {
+            Locale::English | Locale::EnglishUs => formatted.into(),
+            Locale::Czech | Locale::German => formatted.into().replace('.', ","),
+        }
+    }
+}
+
+impl AsRef<str> for Locale {
+    fn as_ref(&self) -> &str {
+        match self {
+            Locale::English => "en",
+            Locale::EnglishUs => "en-US",
+            Locale::Czech => "cs-CZ",
+            Locale::German => "de-DE",
+        }
+    }
+}
+
+impl FromStr for Locale {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.replace('_', "-").to_lowercase().as_str() {
+            "en" => Ok(Locale::English),
+            "en-us" => Ok(Locale::EnglishUs),
+            "cs" | "cs-cz" => Ok(Locale::Czech),
+            "de" | "de-de" => Ok(Locale::German),
+            _ => Err(Error::from(format!("Invalid locale: {}", s))),
+        }
+    }
+}
+
+/// English catalogue.
+fn english(text: Text) -> &'static str {
+    match text {
+        Text::Inside => "Inside",
+        Text::Outside => "Outside",
+        Text::NotAvailable => "N/A",
+        Text::NoReadings => "No readings yet",
+        Text::Raw => "Raw",
+        Text::Status => "Status",
+        Text::StatusOk => "ok",
+        Text::StatusRejected => "rejected",
+        Text::Updated => "Updated",
+        Text::Trend => "Trend",
+        Text::Min => "Min",
+        Text::Max => "Max",
+        Text::Average => "Average",
+        Text::Readings => "Readings",
+        Text::Since => "since",
+        Text::ClockNotSynced => "Clock not synced",
+    }
+}
+
+/// Czech catalogue.
+fn czech(text: Text) -> &'static str {
+    match text {
+        Text::Inside => "Uvnitř",
+        Text::Outside => "Venku",
+        Text::NotAvailable => "N/A",
+        Text::NoReadings => "Zatím bez měření",
+        Text::Raw => "Naměřeno",
+        Text::Status => "Stav",
+        Text::StatusOk => "ok",
+        Text::StatusRejected => "odmítnuto",
+        Text::Updated => "Aktualizováno",
+        Text::Trend => "Trend",
+        Text::Min => "Min",
+        Text::Max => "Max",
+        Text::Average => "Průměr",
+        Text::Readings => "Měření",
+        Text::Since => "od",
+        Text::ClockNotSynced => "Čas nesynchronizován",
+    }
+}
+
+/// German catalogue.
+fn german(text: Text) -> &'static str {
+    match text {
+        Text::Inside => "Innen",
+        Text::Outside => "Außen",
+        Text::NotAvailable => "k. A.",
+        Text::NoReadings => "Noch keine Messwerte",
+        Text::Raw => "Roh",
+        Text::Status => "Status",
+        Text::StatusOk => "ok",
+        Text::StatusRejected => "verworfen",
+        Text::Updated => "Aktualisiert",
+        Text::Trend => "Trend",
+        Text::Min => "Min",
+        Text::Max => "Max",
+        Text::Average => "Mittel",
+        Text::Readings => "Messwerte",
+        Text::Since => "seit",
+        Text::ClockNotSynced => "Uhr nicht synchron",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Locale, Text};
+    use chrono::{NaiveDate, NaiveTime};
+    use w1::thermometer::Units;
+
+    #[test]
+    fn test_parse_locale() {
+        assert_eq!("en".parse::<Locale>().unwrap(), Locale::English);
+        assert_eq!("en_US".parse::<Locale>().unwrap(), Locale::EnglishUs);
+        assert_eq!("cs".parse::<Locale>().unwrap(), Locale::Czech);
+        assert_eq!("de-DE".parse::<Locale>().unwrap(), Locale::German);
+        assert!("fr-FR".parse::<Locale>().is_err());
+    }
+
+    #[test]
+    fn test_formats() {
+        let date = NaiveDate::from_ymd(2018, 7, 1);
+        let time = NaiveTime::from_hms(14, 5, 9);
+
+        assert_eq!(Locale::English.date(date), "2018-07-01");
+        assert_eq!(Locale::EnglishUs.date(date), "07/01/2018");
+        assert_eq!(Locale::Czech.date(date), "1. 7. 2018");
+        assert_eq!(Locale::German.date(date), "01.07.2018");
+
+        assert_eq!(Locale::English.time(time), "14:05:09");
+        assert_eq!(Locale::EnglishUs.time(time), "02:05:09 PM");
+
+        assert_eq!(Locale::German.decimal("-2.5 °C"), "-2,5 °C");
+        assert_eq!(Locale::EnglishUs.decimal("-2.5 °F"), "-2.5 °F");
+    }
+
+    #[test]
+    fn test_units_and_texts() {
+        assert_eq!(Locale::EnglishUs.units(), Units::Fahrenheit);
+        assert_eq!(Locale::Czech.units(), Units::Celsius);
+        assert_eq!(Locale::German.text(Text::Outside), "Außen");
+    }
+}
@@ -1,48 +1,193 @@
+mod alarm;
 mod assets;
+pub mod backlight;
+pub mod label;
+pub mod layout;
+pub mod locale;
 mod model;
+mod navigation;
+pub mod theme;
 mod view;
 
-use self::model::Model;
+use self::alarm::Alarms;
+use self::assets::{Font, Image};
+use self::backlight::{Backlight, Power};
+use self::layout::{LayoutMode, LayoutOptions};
+use self::locale::{Locale, Text};
+use self::model::{Model, SensorModel, WeatherModel};
+use self::navigation::{Action, Gesture, Navigation, SensorView};
+use self::theme::Theme;
 use self::view::View;
-use chrono::{Local, SecondsFormat};
+use chrono::{Duration, Utc};
 use config;
+use error::Error;
 use piston_window::{
-    Event, EventLoop, OpenGL, PistonWindow, RenderEvent, UpdateArgs, UpdateEvent, Window, WindowSettings,
+    Button, Event, EventLoop, GfxFactory, MouseButton, MouseCursorEvent, OpenGL, PistonWindow, PressEvent,
+    ReleaseEvent, RenderEvent, ResizeEvent, Size, Touch, TouchEvent, UpdateArgs, UpdateEvent, Window, WindowSettings,
 };
-use state::{SharedState, State};
-
-/// Convert `State` into view `Model` with current date & time.
-impl From<State> for Model {
-    fn from(state: State) -> Model {
-        let inside_temperature = format!(
-            "Inside temperature {}",
-            state
-                .inside_temperature
-                .map(|t| t.to_string(config::CONFIG.temperature_units()))
-                .unwrap_or_else(|| "N/A".to_string())
-        );
+use shutdown;
+use slog::Logger;
+use state::{Location, Reading, SharedState, State, Status};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use trend::{self, Trend};
+use w1::thermometer::{Temperature, Units};
 
-        let outside_temperature = format!(
-            "Outside temperature {}",
-            state
-                .outside_temperature
-                .map(|t| t.to_string(config::CONFIG.temperature_units()))
-                .unwrap_or_else(|| "N/A".to_string())
-        );
+/// Max number of graph points, history is downsampled.
+const GRAPH_POINTS: usize = 400;
 
-        let formatted = Local::now().to_rfc3339_opts(SecondsFormat::Secs, false);
+/// Update events per second, updates only check if the state or the clock second changed.
+const UPDATES_PER_SECOND: u64 = 4;
 
-        let date: &str = &formatted[..10]; // YYYY-MM-DD
-        let time: &str = &formatted[11..19]; // HH:MM:SS
+/// State version & clock second (seconds since the Unix epoch), work is done only when
+/// they change.
+#[derive(PartialEq, Debug, Copy, Clone)]
+struct Tick {
+    version: usize,
+    second: i64,
+}
 
-        Model::new(inside_temperature, outside_temperature, time, date)
+impl Tick {
+    /// Current tick of the state.
+    ///
+    /// # Arguments
+    ///
+    /// * `shared_state` - shared state
+    /// * `second` - clock second
+    fn new(shared_state: &SharedState, second: i64) -> Tick {
+        Tick {
+            version: shared_state.version(),
+            second,
+        }
     }
+
+    /// Replace the last tick, returns `true` if it changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `last` - last tick or `None` if there's none
+    fn advance(self, last: &mut Option<Tick>) -> bool {
+        let changed = *last != Some(self);
+        *last = Some(self);
+        changed
+    }
+}
+
+/// Rate of change formatted as `String`.
+///
+/// # Arguments
+///
+/// * `rate` - rate of change (degrees celsius per hour)
+/// * `units` - temperature units
+/// * `precision` - number of decimal places
+fn format_rate(rate: f64, units: Units, precision: usize) -> String {
+    format!("{:+.*} {}/h", precision, units.delta(rate), units.symbol())
+}
+
+/// Detail view lines.
+///
+/// # Arguments
+///
+/// * `reading` - last reading
+/// * `rate` - formatted rate of change
+/// * `format` - temperature formatter
+/// * `locale` - UI locale
+fn detail_lines<F>(reading: Option<Reading>, rate: Option<String>, format: F, locale: Locale) -> Vec<String>
+where
+    F: Fn(Temperature) -> String,
+{
+    match reading {
+        Some(reading) => {
+            let status = match reading.status {
+                Status::Ok => Text::StatusOk,
+                Status::Rejected => Text::StatusRejected,
+            };
+            let mut lines = vec![
+                format!("{} {}", locale.text(Text::Raw), format(reading.raw)),
+                format!("{} {}", locale.text(Text::Status), locale.text(status)),
+                format!(
+                    "{} {}",
+                    locale.text(Text::Updated),
+                    locale.time(config::CONFIG.timezone().localize(reading.timestamp).time())
+                ),
+            ];
+            if let Some(rate) = rate {
+                lines.push(format!("{} {}", locale.text(Text::Trend), rate));
+            }
+            lines
+        }
+        None => vec![locale.text(Text::NoReadings).to_string()],
+    }
+}
+
+/// Stats view lines.
+///
+/// # Arguments
+///
+/// * `history` - readings, oldest first
+/// * `format` - temperature formatter
+/// * `locale` - UI locale
+fn stats_lines<F>(history: &[Reading], format: F, locale: Locale) -> Vec<String>
+where
+    F: Fn(Temperature) -> String,
+{
+    let first = match history.first() {
+        Some(first) => first,
+        None => return vec![locale.text(Text::NoReadings).to_string()],
+    };
+
+    let values: Vec<i64> = history.iter().map(|r| r.filtered.value()).collect();
+    let min = values.iter().min().cloned().unwrap_or_default();
+    let max = values.iter().max().cloned().unwrap_or_default();
+    let average = values.iter().sum::<i64>() / values.len() as i64;
+
+    vec![
+        format!("{} {}", locale.text(Text::Min), format(Temperature::new(min))),
+        format!("{} {}", locale.text(Text::Max), format(Temperature::new(max))),
+        format!("{} {}", locale.text(Text::Average), format(Temperature::new(average))),
+        format!(
+            "{} {} {} {}",
+            locale.text(Text::Readings),
+            values.len(),
+            locale.text(Text::Since),
+            locale.time(config::CONFIG.timezone().localize(first.timestamp).time())
+        ),
+    ]
+}
+
+/// Graph values, history is downsampled to `GRAPH_POINTS` values.
+///
+/// # Arguments
+///
+/// * `history` - readings, oldest first
+/// * `units` - temperature units
+fn graph_values(history: &[Reading], units: Units) -> Vec<f64> {
+    let step = ((history.len() + GRAPH_POINTS - 1) / GRAPH_POINTS).max(1);
+    history
+        .iter()
+        .enumerate()
+        .filter(|&(i, _)| i % step == 0)
+        .map(|(_, r)| r.filtered.in_units(units))
+        .collect()
 }
 
 /// Main application (UI).
 struct App {
     shared_state: SharedState,
     view: View,
+    theme: Theme,
+    navigation: Navigation,
+    cursor: [f64; 2],
+    alarms: Alarms,
+    backlight: Option<Backlight>,
+    last_input: Instant,
+    /// Tick of the alarms & backlight update.
+    checked: Option<Tick>,
+    /// Tick of the displayed model, `None` if the model has to be created again.
+    displayed: Option<Tick>,
+    /// Model changed since the last render.
+    redraw: bool,
+    logger: Logger,
 }
 
 impl App {
@@ -52,17 +197,231 @@ impl App {
     ///
     /// * `shared_state` - shared state
     /// * `view` - main view
-    fn new(shared_state: SharedState, view: View) -> App {
-        App { shared_state, view }
+    /// * `theme` - theme
+    /// * `backlight` - display backlight or `None` if it's not controlled
+    /// * `logger` - logger
+    fn new(shared_state: SharedState, view: View, theme: Theme, backlight: Option<Backlight>, logger: Logger) -> App {
+        App {
+            shared_state,
+            view,
+            theme,
+            navigation: Navigation::new(config::CONFIG.layout() != LayoutMode::BigNumber, locations()),
+            cursor: [0.0, 0.0],
+            alarms: Alarms::default(),
+            backlight,
+            last_input: Instant::now(),
+            checked: None,
+            displayed: None,
+            redraw: false,
+            logger,
+        }
+    }
+
+    /// Display is blanked.
+    fn blanked(&self) -> bool {
+        self.backlight.as_ref().and_then(|b| b.power()) == Some(Power::Off)
+    }
+
+    /// Create the model & redraw on the next update even if the state didn't change.
+    fn invalidate(&mut self) {
+        self.displayed = None;
+    }
+
+    /// Register user input, blanked display is woken.
+    ///
+    /// Returns `true` if the display was blanked and the input should be ignored.
+    fn input(&mut self) -> bool {
+        self.last_input = Instant::now();
+        self.invalidate();
+
+        let blanked = self.blanked();
+        if blanked {
+            self.update_backlight();
+        }
+        blanked
+    }
+
+    /// Set backlight power according to the schedule, backlight is not controlled
+    /// anymore if it fails.
+    fn update_backlight(&mut self) {
+        let power = {
+            let state = self.shared_state.state();
+            let alarm = locations()
+                .into_iter()
+                .any(|l| self.alarms.active(l, state.temperature(l)));
+            config::CONFIG.backlight_schedule().power(
+                config::CONFIG.timezone().now().time(),
+                self.last_input.elapsed(),
+                alarm,
+            )
+        };
+
+        let result = match self.backlight {
+            Some(ref mut backlight) => backlight.set(power),
+            None => return,
+        };
+        if let Err(e) = result {
+            error!(self.logger, "Failed to set backlight, backlight is not controlled anymore"; "error" => %e);
+            self.backlight = None;
+        }
+    }
+
+    /// Forget acknowledgements of the alarms which ended.
+    fn update_alarms(&mut self) {
+        let state = self.shared_state.state();
+        let shared_state = &self.shared_state;
+
+        // History is held back while the clock is not synchronised, add the last reading
+        self.alarms.update(|location, since| {
+            let mut readings = shared_state.history_since(location, since);
+            readings.extend(state.temperature(location));
+            readings
+        });
+    }
+
+    /// Acknowledge active alarms.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - sensor location or `None` for all sensors
+    fn acknowledge_alarms(&mut self, location: Option<Location>) {
+        let state = self.shared_state.state();
+        let mut acknowledged = vec![];
+
+        for location in location.map_or_else(locations, |l| vec![l]) {
+            if self.alarms.acknowledge(location, state.temperature(location)) {
+                acknowledged.push(location.as_ref().to_string());
+            }
+        }
+
+        if acknowledged.is_empty() {
+            debug!(self.logger, "No active alarm to acknowledge");
+        } else {
+            info!(self.logger, "Alarm acknowledged"; "sensors" => acknowledged.join(","));
+            self.invalidate();
+        }
+    }
+
+    /// Handle button press.
+    ///
+    /// # Arguments
+    ///
+    /// * `button` - pressed button
+    fn press(&mut self, button: Button) {
+        if self.input() {
+            return;
+        }
+
+        match button {
+            Button::Keyboard(key) => {
+                if let Some(action) = self.navigation.key(key) {
+                    self.handle(action);
+                }
+            }
+            Button::Mouse(MouseButton::Left) => self.navigation.press(self.cursor, Instant::now()),
+            _ => {}
+        };
+    }
+
+    /// Handle button release.
+    ///
+    /// # Arguments
+    ///
+    /// * `button` - released button
+    /// * `size` - window size
+    fn release(&mut self, button: Button, size: Size) {
+        if let Button::Mouse(MouseButton::Left) = button {
+            self.pointer_released(size);
+        }
+    }
+
+    /// Handle touch, touch is handled in the same way as the left mouse button.
+    ///
+    /// # Arguments
+    ///
+    /// * `touch` - touch phase
+    /// * `position` - touch position normalized to 0..1
+    /// * `size` - window size
+    fn touch(&mut self, touch: Touch, position: [f64; 2], size: Size) {
+        let position = [
+            position[0] * f64::from(size.width),
+            position[1] * f64::from(size.height),
+        ];
+
+        if touch == Touch::Start && self.input() {
+            return;
+        }
+
+        match touch {
+            Touch::Start => self.navigation.press(position, Instant::now()),
+            Touch::End => self.pointer_released(size),
+            Touch::Cancel => {
+                self.navigation.release(Instant::now());
+            }
+            Touch::Move => {}
+        };
+    }
+
+    /// Pointer (mouse or touch) released, gesture is handled by the navigation.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - window size
+    fn pointer_released(&mut self, size: Size) {
+        self.invalidate();
+        let gesture = match self.navigation.release(Instant::now()) {
+            Some(gesture) => gesture,
+            None => return,
+        };
+
+        let position = match gesture {
+            Gesture::Tap(position) | Gesture::LongPress(position) => position,
+        };
+        let sensor = self.view.sensor_at(size, position);
+        let alarm = sensor.map_or(false, |l| {
+            self.alarms.active(l, self.shared_state.state().temperature(l))
+        });
+
+        if let Some(action) = self.navigation.gesture(gesture, sensor, alarm) {
+            self.handle(action);
+        }
     }
 
-    /// Render / draw application.
+    /// Handle navigation action.
+    ///
+    /// # Arguments
+    ///
+    /// * `action` - action to handle
+    fn handle(&mut self, action: Action) {
+        match action {
+            Action::SwitchUnits => self.switch_units(),
+            Action::AcknowledgeAlarm(location) => self.acknowledge_alarms(location),
+        };
+    }
+
+    /// Switch to the next temperature units.
+    fn switch_units(&mut self) {
+        let units = self.shared_state.state().settings.units.next();
+
+        if let Err(e) = self.shared_state.set_units(units) {
+            error!(self.logger, "Failed to save settings"; "error" => %e);
+        }
+        info!(self.logger, "Temperature units switched"; "units" => units.as_ref());
+    }
+
+    /// Render / draw application, nothing is drawn if the model didn't change.
+    ///
+    /// Buffers are swapped only after drawing, so the last frame stays on the screen.
     ///
     /// # Arguments
     ///
     /// * `window` - window
     /// * `e` - event
     fn render(&mut self, window: &mut PistonWindow, e: &Event) {
+        if !self.redraw {
+            return;
+        }
+
         // Do not use draw_size() here, because it returns 1600x960 in case of retina displays,
         // 800x480 on Rpi. size() always returns 800x480.
         let size = window.size();
@@ -70,17 +429,167 @@ impl App {
             // Just call draw on our view, it will render itself
             self.view.draw(size, c, g);
         });
+        window.swap_buffers();
+        self.redraw = false;
     }
 
     /// Update application state.
     ///
+    /// Alarms & backlight are updated only if the state or the clock second changed,
+    /// model is created only if they changed since it was displayed and the display is
+    /// not blanked. Nothing else is done on the update.
+    ///
     /// # Arguments
     ///
     /// * `_args` - update arguments (not used)
     #[cfg_attr(feature = "cargo-clippy", allow(trivially_copy_pass_by_ref))]
     fn update(&mut self, _args: &UpdateArgs) {
-        self.view.set_model(self.shared_state.state());
+        let tick = Tick::new(&self.shared_state, Utc::now().timestamp());
+        if tick.advance(&mut self.checked) {
+            self.update_alarms();
+            self.update_backlight();
+        }
+        if self.blanked() || !tick.advance(&mut self.displayed) {
+            return;
+        }
+
+        let model = self.model();
+        self.view.set_model(model);
+        self.redraw = true;
     }
+
+    /// Create view `Model` for the current page with current date & time.
+    fn model(&self) -> Model {
+        let state = self.shared_state.state();
+
+        let sensors = self
+            .navigation
+            .locations()
+            .into_iter()
+            .map(|location| self.sensor_model(location, &state))
+            .collect();
+
+        let now = config::CONFIG.timezone().now();
+        let locale = config::CONFIG.locale();
+        let date = if state.clock_synchronized {
+            locale.date(now.naive_local().date())
+        } else {
+            locale.text(Text::ClockNotSynced).to_string()
+        };
+        let time = locale.time(now.time());
+
+        let palette = self
+            .theme
+            .palette(config::CONFIG.theme_mode(), config::CONFIG.night_time(), now.time());
+
+        let units = state.settings.units;
+        // Forecast is updated periodically, it can be outdated just after midnight
+        let weather = state.forecast.filter(|f| f.date == now.naive_local().date()).map(|f| {
+            WeatherModel::new(
+                format!("{} / {}", f.low.to_string(units, 0), f.high.to_string(units, 0)),
+                f.condition,
+            )
+        });
+
+        Model::new(sensors, time, date, weather, palette, state.clock_synchronized)
+    }
+
+    /// Create sensor model.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - sensor location
+    /// * `state` - state snapshot
+    fn sensor_model(&self, location: Location, state: &State) -> SensorModel {
+        let precision = config::CONFIG.sensor_precision(location);
+        let reading = state.temperature(location);
+        let units = state.settings.units;
+        // Difference is a temperature delta, not an absolute temperature
+        let difference = config::CONFIG
+            .virtual_sensors()
+            .iter()
+            .any(|s| s.location == location && s.expression.is_difference());
+        let locale = config::CONFIG.locale();
+        let format = |t: Temperature| {
+            locale.decimal(if difference {
+                format!("{:+.*} {}", precision, units.delta(t.celsius()), units.symbol())
+            } else {
+                t.to_string(units, precision)
+            })
+        };
+
+        let temperature = reading
+            .map(|r| format(r.filtered))
+            .unwrap_or_else(|| locale.text(Text::NotAvailable).to_string());
+        let alarm = self.alarms.active(location, reading);
+
+        let since = Utc::now() - Duration::minutes(config::CONFIG.trend_window() as i64);
+        let rate = trend::rate(&self.shared_state.history_since(location, since));
+        let trend = rate.map(|rate| Trend::from_rate(rate, config::CONFIG.trend_threshold()));
+
+        let view = self.navigation.view(location);
+        // History is copied only if it's displayed
+        let (lines, graph) = match view {
+            SensorView::Detail => (
+                detail_lines(
+                    reading,
+                    rate.map(|r| locale.decimal(format_rate(r, units, precision))),
+                    format,
+                    locale,
+                ),
+                vec![],
+            ),
+            SensorView::Graph => (vec![], graph_values(&self.shared_state.history(location), units)),
+            SensorView::Stats => (
+                stats_lines(&self.shared_state.history(location), format, locale),
+                vec![],
+            ),
+        };
+
+        SensorModel::new(
+            config::CONFIG.sensor_label(location),
+            temperature,
+            alarm,
+            trend,
+            view,
+            lines,
+            graph,
+        )
+    }
+}
+
+/// Sensors, additional sensors & virtual sensors in the configured navigation order.
+fn locations() -> Vec<Location> {
+    let mut locations = vec![Location::Inside, Location::Outside];
+    locations.extend(config::CONFIG.extra_sensors().iter().map(|s| s.location));
+    locations.extend(config::CONFIG.virtual_sensors().iter().map(|s| s.location));
+    label::ordered(&locations, config::CONFIG.sensor_order())
+}
+
+/// Load asset from the first path which can be loaded, bundled asset is the last resort.
+///
+/// # Arguments
+///
+/// * `paths` - asset paths to try in order
+/// * `factory` - Gfx backend factory
+/// * `load` - loads asset from the path
+/// * `bundled` - loads bundled asset
+/// * `logger` - logger
+fn load_asset<T, L, B>(paths: Vec<PathBuf>, factory: &mut GfxFactory, load: L, bundled: B, logger: &Logger) -> Option<T>
+where
+    L: Fn(&Path, &mut GfxFactory) -> Result<T, Error>,
+    B: FnOnce(&mut GfxFactory) -> Result<T, Error>,
+{
+    for path in paths {
+        match load(&path, factory) {
+            Ok(asset) => return Some(asset),
+            Err(e) => warn!(logger, "Failed to load asset"; "error" => %e),
+        };
+    }
+
+    bundled(factory)
+        .map_err(|e| error!(logger, "Failed to load bundled asset"; "error" => %e))
+        .ok()
 }
 
 /// Run main application (UI).
@@ -88,7 +597,8 @@ impl App {
 /// # Arguments
 ///
 /// * `shared_state` - shared state
-pub fn run(shared_state: SharedState) {
+/// * `logger` - logger
+pub fn run(shared_state: SharedState, logger: Logger) {
     let open_gl = OpenGL::V3_2;
 
     let mut window: PistonWindow = WindowSettings::new(config::PKG_NAME, [800, 480])
@@ -99,17 +609,206 @@ pub fn run(shared_state: SharedState) {
         .unwrap();
 
     window.set_max_fps(config::CONFIG.max_fps());
+    // Update events drive the clock & the state checks, so the event loop can't be lazy
+    window.set_lazy(false);
+    window.set_ups(UPDATES_PER_SECOND);
+    // Buffers are swapped by the app, only when something was drawn
+    window.set_swap_buffers(false);
+
+    let theme = match config::CONFIG.theme_file() {
+        Some(path) => Theme::load(path).unwrap_or_else(|e| {
+            error!(logger, "Failed to load theme, using default theme"; "error" => %e);
+            Theme::default()
+        }),
+        None => Theme::default(),
+    };
 
-    let view = View::new(&mut window.factory);
-    let mut app = App::new(shared_state, view);
+    let layout_options = LayoutOptions {
+        mode: config::CONFIG.layout(),
+        rotation: config::CONFIG.display_rotation(),
+        scale: config::CONFIG.ui_scale() * theme.font_scale,
+    };
+
+    let assets_folder = assets::find_folder(config::CONFIG.assets_folder())
+        .map_err(|e| warn!(logger, "Assets folder not available, using bundled assets"; "error" => %e))
+        .ok();
+
+    let logo = load_asset(
+        assets::candidates(
+            theme.logo.as_ref().map(PathBuf::as_path),
+            assets_folder.as_ref().map(PathBuf::as_path),
+            assets::LOGO_NAME,
+        ),
+        &mut window.factory,
+        |path, factory| Image::from_path(path, factory),
+        Image::bundled_logo,
+        &logger,
+    );
+    let font = load_asset(
+        assets::candidates(
+            theme.font.as_ref().map(PathBuf::as_path),
+            assets_folder.as_ref().map(PathBuf::as_path),
+            assets::FONT_NAME,
+        ),
+        &mut window.factory,
+        |path, factory| Font::from_path(path, factory.clone()),
+        |factory| Font::bundled(factory.clone()),
+        &logger,
+    );
+
+    let (logo, font) = match (logo, font) {
+        (Some(logo), Some(font)) => (logo, font),
+        _ => {
+            crit!(logger, "Unable to load bundled assets");
+            return;
+        }
+    };
+
+    let backlight_path = Path::new(config::CONFIG.backlight_path());
+    let backlight = if backlight_path.is_dir() {
+        info!(logger, "Controlling display backlight"; "path" => %backlight_path.display());
+        Some(Backlight::new(backlight_path))
+    } else {
+        info!(logger, "Display backlight not available"; "path" => %backlight_path.display());
+        None
+    };
+
+    let view = View::new(logo, font, layout_options);
+    let mut app = App::new(shared_state, view, theme, backlight, logger);
 
     while let Some(e) = window.next() {
+        // Return, so the caller can write buffered readings before the exit
+        if shutdown::terminated() {
+            info!(app.logger, "Termination signal received, closing UI");
+            break;
+        }
+
         if e.render_args().is_some() {
             app.render(&mut window, &e);
         }
 
+        if e.resize_args().is_some() {
+            app.invalidate();
+        }
+
+        if let Some(c) = e.mouse_cursor_args() {
+            app.cursor = c;
+        }
+
+        if let Some(b) = e.press_args() {
+            app.press(b);
+        }
+
+        if let Some(b) = e.release_args() {
+            app.release(b, window.size());
+        }
+
+        if let Some(t) = e.touch_args() {
+            app.touch(t.touch, t.position(), window.size());
+        }
+
         if let Some(u) = e.update_args() {
             app.update(&u);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::locale::Locale;
+    use super::{detail_lines, format_rate, graph_values, stats_lines, Tick};
+    use chrono::Utc;
+    use settings::Settings;
+    use state::{Location, Reading, SharedState, Status};
+    use w1::thermometer::{Temperature, Units};
+
+    fn history(values: &[i64]) -> Vec<Reading> {
+        values
+            .iter()
+            .map(|v| Reading {
+                timestamp: Utc::now(),
+                raw: Temperature::new(*v),
+                filtered: Temperature::new(*v),
+                status: Status::Ok,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_graph_values_downsampled() {
+        let values: Vec<i64> = (0..1_000).collect();
+        let graph = graph_values(&history(&values), Units::Celsius);
+
+        assert_eq!(graph.len(), 334);
+        assert_eq!(graph[1], 0.003);
+        assert!(graph_values(&[], Units::Celsius).is_empty());
+    }
+
+    #[test]
+    fn test_detail_lines() {
+        let reading = history(&[21_000])[0];
+        let format = |t: Temperature| t.to_string(Units::Celsius, 1);
+
+        let lines = detail_lines(
+            Some(reading),
+            Some(format_rate(-0.42, Units::Fahrenheit, 1)),
+            format,
+            Locale::English,
+        );
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[3], "Trend -0.8 °F/h");
+
+        assert_eq!(detail_lines(Some(reading), None, format, Locale::English).len(), 3);
+        assert_eq!(
+            detail_lines(None, None, format, Locale::English),
+            vec!["No readings yet"]
+        );
+
+        let lines = detail_lines(Some(reading), None, format, Locale::Czech);
+        assert_eq!(lines[1], "Stav ok");
+        assert!(lines[2].starts_with("Aktualizováno "));
+    }
+
+    #[test]
+    fn test_stats_lines() {
+        let lines = stats_lines(
+            &history(&[20_000, 22_000, 27_000]),
+            |t| t.to_string(Units::Celsius, 1),
+            Locale::English,
+        );
+
+        assert_eq!(&lines[..3], &["Min 20.0 °C", "Max 27.0 °C", "Average 23.0 °C"]);
+        assert!(lines[3].starts_with("Readings 3 since "));
+        assert_eq!(
+            stats_lines(&[], |t| t.to_string(Units::Celsius, 1), Locale::English),
+            vec!["No readings yet"]
+        );
+    }
+
+    #[test]
+    fn test_tick() {
+        let state = SharedState::new(10, Settings::new(Units::Celsius), None, vec![]);
+        let second = 1_530_446_400;
+        let mut checked = None;
+        assert!(Tick::new(&state, second).advance(&mut checked));
+
+        // Nothing changed, no work is done on the update
+        assert!(!Tick::new(&state, second).advance(&mut checked));
+        assert!(!Tick::new(&state, second).advance(&mut checked));
+
+        state
+            .set_temperature(
+                Location::Inside,
+                Reading {
+                    timestamp: Utc::now(),
+                    raw: Temperature::new(21_000),
+                    filtered: Temperature::new(21_000),
+                    status: Status::Ok,
+                },
+            )
+            .unwrap();
+        assert!(Tick::new(&state, second).advance(&mut checked));
+        assert!(!Tick::new(&state, second).advance(&mut checked));
+        assert!(Tick::new(&state, second + 1).advance(&mut checked));
+    }
+}
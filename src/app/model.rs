@@ -1,9 +1,155 @@
+use super::label::{Icon, Label};
+use super::navigation::SensorView;
+use super::theme::Palette;
+use state::Location;
+use trend::Trend;
+use weather::Condition;
+
+/// Sensor model for view containing ready to render / draw values.
+pub struct SensorModel {
+    label: Label,
+    temperature: String,
+    alarm: bool,
+    trend: Option<Trend>,
+    view: SensorView,
+    lines: Vec<String>,
+    graph: Vec<f64>,
+}
+
+impl SensorModel {
+    /// Create new `SensorModel`.
+    ///
+    /// # Arguments
+    ///
+    /// * `label` - sensor label
+    /// * `temperature` - formatted temperature
+    /// * `alarm` - `true` if the temperature requires attention
+    /// * `trend` - temperature trend or `None` if there are not enough readings
+    /// * `view` - sensor view
+    /// * `lines` - formatted detail or stats lines
+    /// * `graph` - graph values, oldest first (empty if the view is not a graph)
+    #[allow(clippy::too_many_arguments)]
+    pub fn new<S>(
+        label: Label,
+        temperature: S,
+        alarm: bool,
+        trend: Option<Trend>,
+        view: SensorView,
+        lines: Vec<String>,
+        graph: Vec<f64>,
+    ) -> SensorModel
+    where
+        S: Into<String>,
+    {
+        SensorModel {
+            label,
+            temperature: temperature.into(),
+            alarm,
+            trend,
+            view,
+            lines,
+            graph,
+        }
+    }
+
+    /// Sensor location.
+    pub fn location(&self) -> Location {
+        self.label.location
+    }
+
+    /// Sensor name.
+    pub fn name(&self) -> &str {
+        &self.label.name
+    }
+
+    /// Sensor short name.
+    pub fn short_name(&self) -> &str {
+        &self.label.short_name
+    }
+
+    /// Sensor icon.
+    pub fn icon(&self) -> Option<Icon> {
+        self.label.icon
+    }
+
+    /// Room or location of the sensor.
+    pub fn room(&self) -> Option<&str> {
+        self.label.room.as_ref().map(String::as_str)
+    }
+
+    /// Current temperature.
+    pub fn temperature(&self) -> &str {
+        &self.temperature
+    }
+
+    /// Temperature requires attention.
+    pub fn alarm(&self) -> bool {
+        self.alarm
+    }
+
+    /// Temperature trend.
+    pub fn trend(&self) -> Option<Trend> {
+        self.trend
+    }
+
+    /// Sensor view.
+    pub fn view(&self) -> SensorView {
+        self.view
+    }
+
+    /// Detail or stats lines.
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Graph values, oldest first.
+    pub fn graph(&self) -> &[f64] {
+        &self.graph
+    }
+}
+
+/// Weather forecast model for view.
+pub struct WeatherModel {
+    text: String,
+    condition: Condition,
+}
+
+impl WeatherModel {
+    /// Create new `WeatherModel`.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - formatted high & low temperatures
+    /// * `condition` - weather condition
+    pub fn new<S>(text: S, condition: Condition) -> WeatherModel
+    where
+        S: Into<String>,
+    {
+        WeatherModel {
+            text: text.into(),
+            condition,
+        }
+    }
+
+    /// Formatted high & low temperatures.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Weather condition.
+    pub fn condition(&self) -> Condition {
+        self.condition
+    }
+}
+
 /// Model for view containing ready to render / draw values.
 pub struct Model {
-    inside_temperature: String,
-    outside_temperature: String,
+    sensors: Vec<SensorModel>,
     time: String,
     date: String,
+    weather: Option<WeatherModel>,
+    palette: Palette,
+    clock_synchronized: bool,
 }
 
 impl Model {
@@ -11,33 +157,37 @@ impl Model {
     ///
     /// # Arguments
     ///
-    /// * `inside_temperature` - formatted inside temperature
-    /// * `outside_temperature` - formatted outside temperature
+    /// * `sensors` - sensors displayed on the current page
     /// * `time` - formatted time
     /// * `date` - formatted date
-    pub fn new<S1, S2, S3, S4>(inside_temperature: S1, outside_temperature: S2, time: S3, date: S4) -> Model
+    /// * `weather` - today's weather forecast
+    /// * `palette` - current theme palette
+    /// * `clock_synchronized` - system clock is synchronised
+    pub fn new<S1, S2>(
+        sensors: Vec<SensorModel>,
+        time: S1,
+        date: S2,
+        weather: Option<WeatherModel>,
+        palette: Palette,
+        clock_synchronized: bool,
+    ) -> Model
     where
         S1: Into<String>,
         S2: Into<String>,
-        S3: Into<String>,
-        S4: Into<String>,
     {
         Model {
-            inside_temperature: inside_temperature.into(),
-            outside_temperature: outside_temperature.into(),
+            sensors,
             time: time.into(),
             date: date.into(),
+            weather,
+            palette,
+            clock_synchronized,
         }
     }
 
-    /// Inside temperature.
-    pub fn inside_temperature(&self) -> &str {
-        &self.inside_temperature
-    }
-
-    /// Outside temperature.
-    pub fn outside_temperature(&self) -> &str {
-        &self.outside_temperature
+    /// Sensors displayed on the current page.
+    pub fn sensors(&self) -> &[SensorModel] {
+        &self.sensors
     }
 
     /// Current time.
@@ -45,8 +195,23 @@ impl Model {
         &self.time
     }
 
-    /// Current date.
+    /// Current date (or clock not synchronised text).
     pub fn date(&self) -> &str {
         &self.date
     }
+
+    /// Today's weather forecast.
+    pub fn weather(&self) -> Option<&WeatherModel> {
+        self.weather.as_ref()
+    }
+
+    /// Current theme palette.
+    pub fn palette(&self) -> Palette {
+        self.palette
+    }
+
+    /// System clock is synchronised, time & date can be trusted.
+    pub fn clock_synchronized(&self) -> bool {
+        self.clock_synchronized
+    }
 }
@@ -0,0 +1,309 @@
+//! Navigation (page) model on top of the view `Model`.
+//!
+//! Overview page shows all sensors, sensor pages show one sensor only. Each sensor
+//! is displayed in one of the `SensorView`s, tap on the sensor cycles them. Long press
+//! on the sensor switches units or acknowledges its alarm.
+use piston_window::Key;
+use state::Location;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// Press longer than this is a long press (ms).
+const LONG_PRESS_DURATION: u64 = 800;
+
+/// Sensor view.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum SensorView {
+    /// Current temperature details (raw value, status, last update).
+    Detail,
+    /// Temperature history graph.
+    Graph,
+    /// Temperature history statistics (min, max, average).
+    Stats,
+}
+
+impl SensorView {
+    /// Next view (detail -> graph -> stats -> detail).
+    pub fn next(self) -> SensorView {
+        match self {
+            SensorView::Detail => SensorView::Graph,
+            SensorView::Graph => SensorView::Stats,
+            SensorView::Stats => SensorView::Detail,
+        }
+    }
+}
+
+/// Page.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Page {
+    /// All sensors.
+    Overview,
+    /// Single sensor.
+    Sensor(Location),
+}
+
+/// Pointer (mouse or touch) gesture.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Gesture {
+    /// Short press & release at the position.
+    Tap([f64; 2]),
+    /// Long press & release at the position.
+    LongPress([f64; 2]),
+}
+
+/// Action which must be handled outside of the navigation.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Action {
+    /// Switch to the next temperature units.
+    SwitchUnits,
+    /// Acknowledge active alarm of the sensor or of all sensors (`None`).
+    AcknowledgeAlarm(Option<Location>),
+}
+
+/// Navigation state.
+pub struct Navigation {
+    locations: Vec<Location>,
+    pages: Vec<Page>,
+    page: Page,
+    views: HashMap<Location, SensorView>,
+    press: Option<(Instant, [f64; 2])>,
+}
+
+impl Navigation {
+    /// Create new `Navigation` with the first page and detail views.
+    ///
+    /// # Arguments
+    ///
+    /// * `overview` - `false` if the overview page should be skipped (one sensor per page only)
+    /// * `locations` - sensors in the navigation order (at least one)
+    pub fn new(overview: bool, locations: Vec<Location>) -> Navigation {
+        let mut pages: Vec<Page> = locations.iter().map(|l| Page::Sensor(*l)).collect();
+        if overview {
+            pages.insert(0, Page::Overview);
+        }
+
+        Navigation {
+            locations,
+            page: pages[0],
+            pages,
+            views: HashMap::new(),
+            press: None,
+        }
+    }
+
+    /// Current page.
+    pub fn page(&self) -> Page {
+        self.page
+    }
+
+    /// Sensors displayed on the current page.
+    pub fn locations(&self) -> Vec<Location> {
+        match self.page() {
+            Page::Overview => self.locations.clone(),
+            Page::Sensor(location) => vec![location],
+        }
+    }
+
+    /// Sensor view.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - sensor location
+    pub fn view(&self, location: Location) -> SensorView {
+        self.views.get(&location).cloned().unwrap_or(SensorView::Detail)
+    }
+
+    /// Cycle sensor view.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - sensor location
+    pub fn next_view(&mut self, location: Location) {
+        let view = self.view(location).next();
+        self.views.insert(location, view);
+    }
+
+    /// Current page index.
+    fn index(&self) -> usize {
+        self.pages.iter().position(|p| *p == self.page).unwrap()
+    }
+
+    /// Switch to the next page.
+    pub fn next_page(&mut self) {
+        self.page = self.pages[(self.index() + 1) % self.pages.len()];
+    }
+
+    /// Switch to the previous page.
+    pub fn previous_page(&mut self) {
+        self.page = self.pages[(self.index() + self.pages.len() - 1) % self.pages.len()];
+    }
+
+    /// Handle key press.
+    ///
+    /// Left / right arrows switch pages, tab cycles views of displayed sensors,
+    /// `U` switches units.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - pressed key
+    pub fn key(&mut self, key: Key) -> Option<Action> {
+        match key {
+            Key::Left => self.previous_page(),
+            Key::Right => self.next_page(),
+            Key::Tab => {
+                for location in self.locations() {
+                    self.next_view(location);
+                }
+            }
+            Key::U => return Some(Action::SwitchUnits),
+            _ => {}
+        };
+        None
+    }
+
+    /// Handle pointer gesture.
+    ///
+    /// Tap on a sensor cycles its view. Long press on a sensor acknowledges its alarm if
+    /// it's active, otherwise it switches units. Long press elsewhere acknowledges alarms
+    /// of all sensors.
+    ///
+    /// # Arguments
+    ///
+    /// * `gesture` - pointer gesture
+    /// * `sensor` - sensor at the gesture position
+    /// * `alarm` - `true` if the sensor alarm is active
+    pub fn gesture(&mut self, gesture: Gesture, sensor: Option<Location>, alarm: bool) -> Option<Action> {
+        match (gesture, sensor) {
+            (Gesture::Tap(_), Some(location)) => {
+                self.next_view(location);
+                None
+            }
+            (Gesture::Tap(_), None) => None,
+            (Gesture::LongPress(_), Some(location)) if alarm => Some(Action::AcknowledgeAlarm(Some(location))),
+            (Gesture::LongPress(_), Some(_)) => Some(Action::SwitchUnits),
+            (Gesture::LongPress(_), None) => Some(Action::AcknowledgeAlarm(None)),
+        }
+    }
+
+    /// Pointer pressed.
+    ///
+    /// # Arguments
+    ///
+    /// * `position` - pointer position
+    /// * `now` - press time
+    pub fn press(&mut self, position: [f64; 2], now: Instant) {
+        self.press = Some((now, position));
+    }
+
+    /// Pointer released, returns `None` if there was no press.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - release time
+    pub fn release(&mut self, now: Instant) -> Option<Gesture> {
+        self.press.take().map(|(pressed, position)| {
+            if now > pressed && now.duration_since(pressed) >= Duration::from_millis(LONG_PRESS_DURATION) {
+                Gesture::LongPress(position)
+            } else {
+                Gesture::Tap(position)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Action, Gesture, Navigation, Page, SensorView};
+    use piston_window::Key;
+    use state::Location;
+    use std::time::{Duration, Instant};
+
+    fn new_navigation(overview: bool) -> Navigation {
+        Navigation::new(overview, vec![Location::Inside, Location::Outside])
+    }
+
+    #[test]
+    fn test_pages() {
+        let mut navigation = new_navigation(true);
+        assert_eq!(navigation.page(), Page::Overview);
+
+        navigation.key(Key::Right);
+        assert_eq!(navigation.page(), Page::Sensor(Location::Inside));
+
+        navigation.key(Key::Left);
+        navigation.key(Key::Left);
+        assert_eq!(navigation.page(), Page::Sensor(Location::Outside));
+        assert_eq!(navigation.locations(), vec![Location::Outside]);
+
+        let mut navigation = new_navigation(false);
+        assert_eq!(navigation.page(), Page::Sensor(Location::Inside));
+        navigation.key(Key::Right);
+        navigation.key(Key::Right);
+        assert_eq!(navigation.page(), Page::Sensor(Location::Inside));
+
+        let delta = "delta".parse::<Location>().unwrap();
+        let mut navigation = Navigation::new(true, vec![Location::Inside, Location::Outside, delta]);
+        assert_eq!(navigation.locations().len(), 3);
+        navigation.key(Key::Left);
+        assert_eq!(navigation.page(), Page::Sensor(delta));
+    }
+
+    #[test]
+    fn test_views() {
+        let mut navigation = new_navigation(true);
+        navigation.next_view(Location::Inside);
+        assert_eq!(navigation.view(Location::Inside), SensorView::Graph);
+        assert_eq!(navigation.view(Location::Outside), SensorView::Detail);
+
+        navigation.key(Key::Tab);
+        assert_eq!(navigation.view(Location::Inside), SensorView::Stats);
+        assert_eq!(navigation.view(Location::Outside), SensorView::Graph);
+
+        assert_eq!(navigation.key(Key::U), Some(Action::SwitchUnits));
+    }
+
+    #[test]
+    fn test_gestures() {
+        let mut navigation = new_navigation(true);
+        let now = Instant::now();
+        assert_eq!(navigation.release(now), None);
+
+        navigation.press([10.0, 20.0], now);
+        assert_eq!(
+            navigation.release(now + Duration::from_millis(200)),
+            Some(Gesture::Tap([10.0, 20.0]))
+        );
+
+        navigation.press([10.0, 20.0], now);
+        assert_eq!(
+            navigation.release(now + Duration::from_secs(1)),
+            Some(Gesture::LongPress([10.0, 20.0]))
+        );
+    }
+
+    #[test]
+    fn test_gesture_actions() {
+        let mut navigation = new_navigation(true);
+        let tap = Gesture::Tap([10.0, 20.0]);
+        let long_press = Gesture::LongPress([10.0, 20.0]);
+
+        assert_eq!(navigation.gesture(tap, Some(Location::Outside), true), None);
+        assert_eq!(navigation.view(Location::Outside), SensorView::Graph);
+        assert_eq!(navigation.gesture(tap, None, false), None);
+
+        // Long press on the temperature switches units unless the sensor alarm is active
+        assert_eq!(
+            navigation.gesture(long_press, Some(Location::Inside), false),
+            Some(Action::SwitchUnits)
+        );
+        assert_eq!(
+            navigation.gesture(long_press, Some(Location::Inside), true),
+            Some(Action::AcknowledgeAlarm(Some(Location::Inside)))
+        );
+        assert_eq!(
+            navigation.gesture(long_press, None, false),
+            Some(Action::AcknowledgeAlarm(None))
+        );
+        assert_eq!(navigation.view(Location::Inside), SensorView::Detail);
+    }
+}
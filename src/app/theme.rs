@@ -0,0 +1,305 @@
+//! Themes (colors, font, logo) and automatic night mode.
+//!
+//! Theme is loaded from a JSON file, all fields are optional:
+//!
+//! ```json
+//! {
+//!   "light": { "background": "#ffffff", "foreground": "#000000", "secondary": "#555555",
+//!              "graph": "#d25a10", "alarm": "#d01010" },
+//!   "dark": { "background": "#000000", "foreground": "#ffffff" },
+//!   "font": "/etc/thermometer/Branding.ttf",
+//!   "font_scale": 1.2,
+//!   "logo": "/etc/thermometer/logo.png"
+//! }
+//! ```
+use chrono::NaiveTime;
+use error::Error;
+use piston_window::types::Color;
+use serde::de::{Deserialize, Deserializer, Error as DeError};
+use serde_json;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Theme colors.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Palette {
+    /// Background color.
+    #[serde(deserialize_with = "deserialize_color")]
+    pub background: Color,
+    /// Primary text color (time, date, temperatures).
+    #[serde(deserialize_with = "deserialize_color")]
+    pub foreground: Color,
+    /// Secondary text color (sensor details, titles).
+    #[serde(deserialize_with = "deserialize_color")]
+    pub secondary: Color,
+    /// Graph line color.
+    #[serde(deserialize_with = "deserialize_color")]
+    pub graph: Color,
+    /// Color of values requiring attention (rejected readings, ...).
+    #[serde(deserialize_with = "deserialize_color")]
+    pub alarm: Color,
+}
+
+/// Dark palette, white text on the black background.
+pub const DARK: Palette = Palette {
+    background: [0.0, 0.0, 0.0, 1.0],
+    foreground: [1.0, 1.0, 1.0, 1.0],
+    secondary: [0.7, 0.7, 0.7, 1.0],
+    graph: [0.95, 0.55, 0.2, 1.0],
+    alarm: [0.95, 0.25, 0.2, 1.0],
+};
+
+/// Light palette, black text on the white background.
+pub const LIGHT: Palette = Palette {
+    background: [1.0, 1.0, 1.0, 1.0],
+    foreground: [0.0, 0.0, 0.0, 1.0],
+    secondary: [0.33, 0.33, 0.33, 1.0],
+    graph: [0.82, 0.35, 0.06, 1.0],
+    alarm: [0.82, 0.06, 0.06, 1.0],
+};
+
+impl Default for Palette {
+    fn default() -> Palette {
+        DARK
+    }
+}
+
+/// Parse `#rrggbb` or `#rrggbbaa` color.
+///
+/// # Arguments
+///
+/// * `s` - color to parse
+pub fn parse_color(s: &str) -> Result<Color, Error> {
+    let invalid = || Error::Parse(format!("invalid color {}, expected #rrggbb or #rrggbbaa", s));
+
+    if !s.starts_with('#') {
+        return Err(invalid());
+    }
+
+    let hex = &s[1..];
+    if (hex.len() != 6 && hex.len() != 8) || !hex.is_ascii() {
+        return Err(invalid());
+    }
+
+    let mut color = [1.0; 4];
+    for (i, component) in color.iter_mut().enumerate().take(hex.len() / 2) {
+        let value = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+        *component = f32::from(value) / 255.0;
+    }
+    Ok(color)
+}
+
+fn deserialize_color<'de, D>(deserializer: D) -> Result<Color, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    parse_color(&s).map_err(D::Error::custom)
+}
+
+/// Theme definition.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// Palette used during the day.
+    pub light: Palette,
+    /// Palette used at night.
+    pub dark: Palette,
+    /// TTF font file path, bundled font is used if not set.
+    pub font: Option<PathBuf>,
+    /// Font size scale.
+    pub font_scale: f64,
+    /// Logo image file path, bundled logo is used if not set.
+    pub logo: Option<PathBuf>,
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme {
+            light: LIGHT,
+            dark: DARK,
+            font: None,
+            font_scale: 1.0,
+            logo: None,
+        }
+    }
+}
+
+impl Theme {
+    /// Load theme from the JSON file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - theme file path
+    pub fn load<P>(path: P) -> Result<Theme, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let file =
+            File::open(path).map_err(|e| Error::from(e).context(format!("Unable to open {}", path.display())))?;
+
+        let theme: Theme = serde_json::from_reader(BufReader::new(file))
+            .map_err(|e| Error::Parse(format!("invalid theme {}: {}", path.display(), e)))?;
+
+        if theme.font_scale < 0.25 || theme.font_scale > 4.0 {
+            return Err(Error::Parse(format!(
+                "invalid theme {}: font_scale must be between 0.25 and 4.0",
+                path.display()
+            )));
+        }
+
+        Ok(theme)
+    }
+
+    /// Palette for the given mode and local time.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - theme mode
+    /// * `night` - night time range
+    /// * `time` - local time
+    pub fn palette(&self, mode: ThemeMode, night: NightTime, time: NaiveTime) -> Palette {
+        match mode {
+            ThemeMode::Dark => self.dark,
+            ThemeMode::Light => self.light,
+            ThemeMode::Auto if night.contains(time) => self.dark,
+            ThemeMode::Auto => self.light,
+        }
+    }
+}
+
+/// Theme mode.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum ThemeMode {
+    /// Dark palette all the time.
+    Dark,
+    /// Light palette all the time.
+    Light,
+    /// Light palette during the day, dark palette at night.
+    Auto,
+}
+
+impl AsRef<str> for ThemeMode {
+    fn as_ref(&self) -> &str {
+        match self {
+            ThemeMode::Dark => "dark",
+            ThemeMode::Light => "light",
+            ThemeMode::Auto => "auto",
+        }
+    }
+}
+
+impl FromStr for ThemeMode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dark" => Ok(ThemeMode::Dark),
+            "light" => Ok(ThemeMode::Light),
+            "auto" => Ok(ThemeMode::Auto),
+            _ => Err(Error::from(format!("Invalid theme mode: {}", s))),
+        }
+    }
+}
+
+/// Night time range (local time), can span midnight.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct NightTime {
+    /// Night starts at.
+    pub start: NaiveTime,
+    /// Night ends (day starts) at.
+    pub end: NaiveTime,
+}
+
+impl NightTime {
+    /// Check if the time is at night.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - local time
+    pub fn contains(self, time: NaiveTime) -> bool {
+        if self.start <= self.end {
+            time >= self.start && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        }
+    }
+}
+
+/// Parse `HH:MM` time.
+///
+/// # Arguments
+///
+/// * `s` - time to parse
+pub fn parse_time_of_day(s: &str) -> Result<NaiveTime, Error> {
+    NaiveTime::parse_from_str(s, "%H:%M").map_err(|_| Error::Parse(format!("invalid time {}, expected HH:MM", s)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_color, parse_time_of_day, NightTime, Theme, ThemeMode, DARK, LIGHT};
+    use serde_json;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#ffffff").unwrap(), [1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(parse_color("#ff000000").unwrap(), [1.0, 0.0, 0.0, 0.0]);
+        assert!(parse_color("ffffff").is_err());
+        assert!(parse_color("#fff").is_err());
+        assert!(parse_color("#gggggg").is_err());
+    }
+
+    #[test]
+    fn test_deserialize_theme() {
+        let theme: Theme = serde_json::from_str(r##"{"dark": {"foreground": "#00ff00"}, "font_scale": 1.5}"##).unwrap();
+
+        assert_eq!(theme.dark.foreground, [0.0, 1.0, 0.0, 1.0]);
+        assert_eq!(theme.dark.background, DARK.background);
+        assert_eq!(theme.light, LIGHT);
+        assert_eq!(theme.font_scale, 1.5);
+        assert_eq!(theme.font, None);
+
+        assert!(serde_json::from_str::<Theme>(r##"{"dark": {"foreground": "green"}}"##).is_err());
+    }
+
+    #[test]
+    fn test_night_mode() {
+        let theme = Theme::default();
+        let night = NightTime {
+            start: parse_time_of_day("21:00").unwrap(),
+            end: parse_time_of_day("07:00").unwrap(),
+        };
+
+        assert_eq!(
+            theme.palette(ThemeMode::Auto, night, parse_time_of_day("23:30").unwrap()),
+            DARK
+        );
+        assert_eq!(
+            theme.palette(ThemeMode::Auto, night, parse_time_of_day("06:59").unwrap()),
+            DARK
+        );
+        assert_eq!(
+            theme.palette(ThemeMode::Auto, night, parse_time_of_day("07:00").unwrap()),
+            LIGHT
+        );
+        assert_eq!(
+            theme.palette(ThemeMode::Light, night, parse_time_of_day("23:30").unwrap()),
+            LIGHT
+        );
+        assert_eq!(
+            theme.palette(ThemeMode::Dark, night, parse_time_of_day("12:00").unwrap()),
+            DARK
+        );
+
+        let night = NightTime {
+            start: parse_time_of_day("01:00").unwrap(),
+            end: parse_time_of_day("05:00").unwrap(),
+        };
+        assert!(night.contains(parse_time_of_day("02:00").unwrap()));
+        assert!(!night.contains(parse_time_of_day("23:00").unwrap()));
+    }
+}
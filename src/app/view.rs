@@ -1,11 +1,21 @@
 use super::assets::{Font, Image};
-use super::model::Model;
-use piston_window::{clear, color, Context, G2d, GfxFactory, Position, Size};
+use super::label::Icon;
+use super::layout::{Layout, LayoutMode, LayoutOptions};
+use super::model::{Model, SensorModel, WeatherModel};
+use super::navigation::SensorView;
+use super::theme::Palette;
+use piston_window::types::{Color, Rectangle};
+use piston_window::{clear, ellipse, line, polygon, Context, G2d, Size};
+use state::Location;
+use std::f64::{INFINITY, NEG_INFINITY};
+use trend::Trend;
+use weather::Condition;
 
 /// Thermometer view.
 pub struct View {
-    rust_logo: Image,
-    mono_regular_font: Font,
+    logo: Image,
+    font: Font,
+    layout_options: LayoutOptions,
     model: Option<Model>,
 }
 
@@ -14,18 +24,14 @@ impl View {
     ///
     /// # Arguments
     ///
-    /// * `factory` - graphics factory
-    ///
-    /// # Notes
-    ///
-    /// Panics if assets can not be loaded.
-    pub fn new(factory: &mut GfxFactory) -> View {
-        let rust_logo = Image::asset("rust-logo.png", factory);
-        let mono_regular_font = Font::asset("FiraMono-Regular.ttf", factory.clone());
-
+    /// * `logo` - logo
+    /// * `font` - font
+    /// * `layout_options` - layout options
+    pub fn new(logo: Image, font: Font, layout_options: LayoutOptions) -> View {
         View {
-            rust_logo,
-            mono_regular_font,
+            logo,
+            font,
+            layout_options,
             model: None,
         }
     }
@@ -42,57 +48,495 @@ impl View {
         self.model = Some(model.into());
     }
 
-    fn rust_logo_position(&self, size: Size) -> Position {
-        [
-            (size.width - self.rust_logo.width() - 15) as i32,
-            (size.height - self.rust_logo.height() - 15) as i32,
-        ].into()
-    }
-
-    fn time_position(&self, _size: Size) -> Position {
-        [15, 22].into()
-    }
-
-    fn date_position(&self, size: Size) -> Position {
-        [15, size.height as i32 - 20].into()
-    }
-
-    fn inside_temperature_position(&self, _size: Size) -> Position {
-        [250, 200].into()
-    }
+    /// Sensor at the given position.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - window size
+    /// * `position` - position in the window
+    pub fn sensor_at(&self, size: Size, position: [f64; 2]) -> Option<Location> {
+        let model = self.model.as_ref()?;
+        let layout = Layout::new(size, self.layout_options);
+        let [x, y] = layout.position(position);
 
-    fn outside_temperature_position(&self, _size: Size) -> Position {
-        [250, 250].into()
+        layout
+            .sensor_areas(model.sensors().len())
+            .into_iter()
+            .zip(model.sensors())
+            .find(|(area, _)| x >= area[0] && x < area[0] + area[2] && y >= area[1] && y < area[1] + area[3])
+            .map(|(_, sensor)| sensor.location())
     }
 
     /// Render view.
     ///
     /// # Arguments
     ///
+    /// * `size` - window size
     /// * `c` - context
     /// * `g` - graphics
     pub fn draw(&mut self, size: Size, c: Context, g: &mut G2d) {
-        clear(color::BLACK, g);
+        let palette = self.model.as_ref().map(|m| m.palette()).unwrap_or_default();
+        clear(palette.background, g);
 
-        let logo_position = self.rust_logo_position(size);
-        self.rust_logo.draw_at(logo_position, &c, g);
+        let layout = Layout::new(size, self.layout_options);
+        let c = layout.transform(c);
+
+        let logo_rect = layout.logo_rect(f64::from(self.logo.width()), f64::from(self.logo.height()));
+        self.logo.draw_rect(logo_rect, &c, g);
 
         if let Some(ref model) = self.model {
-            let position = self.time_position(size);
-            self.mono_regular_font
-                .draw_at(position, model.time(), 14, color::WHITE, &c, g);
+            let font_size = layout.font_size(14);
+            let clock_color = if model.clock_synchronized() {
+                palette.foreground
+            } else {
+                palette.alarm
+            };
+
+            self.font
+                .draw_at(layout.time_position(), model.time(), font_size, clock_color, &c, g);
 
-            let position = self.date_position(size);
-            self.mono_regular_font
-                .draw_at(position, model.date(), 14, color::WHITE, &c, g);
+            self.font
+                .draw_at(layout.date_position(), model.date(), font_size, clock_color, &c, g);
 
-            let position = self.inside_temperature_position(size);
-            self.mono_regular_font
-                .draw_at(position, model.inside_temperature(), 14, color::WHITE, &c, g);
+            if let Some(weather) = model.weather() {
+                draw_weather(&mut self.font, &layout, &palette, weather, &c, g);
+            }
 
-            let position = self.outside_temperature_position(size);
-            self.mono_regular_font
-                .draw_at(position, model.outside_temperature(), 14, color::WHITE, &c, g);
+            for (area, sensor) in layout
+                .sensor_areas(model.sensors().len())
+                .into_iter()
+                .zip(model.sensors())
+            {
+                match layout.mode() {
+                    LayoutMode::Standard => draw_sensor(&mut self.font, &layout, &palette, area, sensor, &c, g),
+                    LayoutMode::BigNumber => draw_big_number(&mut self.font, &layout, &palette, area, sensor, &c, g),
+                };
+            }
         }
     }
 }
+
+/// Temperature color.
+///
+/// # Arguments
+///
+/// * `palette` - theme palette
+/// * `sensor` - sensor model
+fn temperature_color(palette: &Palette, sensor: &SensorModel) -> Color {
+    if sensor.alarm() {
+        palette.alarm
+    } else {
+        palette.foreground
+    }
+}
+
+/// Render sensor.
+///
+/// # Arguments
+///
+/// * `font` - font
+/// * `layout` - layout
+/// * `palette` - theme palette
+/// * `area` - sensor area
+/// * `sensor` - sensor model
+/// * `c` - context
+/// * `g` - graphics
+fn draw_sensor(
+    font: &mut Font,
+    layout: &Layout,
+    palette: &Palette,
+    area: Rectangle,
+    sensor: &SensorModel,
+    c: &Context,
+    g: &mut G2d,
+) {
+    let header = format!("{} {}", sensor.short_name(), sensor.temperature());
+    let header_size = layout.font_size(20);
+    let baseline = area[1] + layout.scaled(24.0);
+    let left = area[0] + draw_label_icon(sensor.icon(), area[0], baseline, f64::from(header_size), palette, c, g);
+    font.draw_at(
+        [left as i32, baseline as i32],
+        &header,
+        header_size,
+        temperature_color(palette, sensor),
+        c,
+        g,
+    );
+
+    if let Some(trend) = sensor.trend() {
+        let x = left + layout.text_width(&header, header_size) + layout.scaled(8.0);
+        draw_trend(trend, x, baseline, f64::from(header_size), palette, c, g);
+    }
+
+    match sensor.view() {
+        SensorView::Detail | SensorView::Stats => {
+            let font_size = layout.font_size(14);
+            for (i, text) in sensor.lines().iter().enumerate() {
+                let y = area[1] + layout.scaled(50.0 + i as f64 * 20.0);
+                if y > area[1] + area[3] {
+                    break;
+                }
+                font.draw_at([area[0] as i32, y as i32], text, font_size, palette.secondary, c, g);
+            }
+        }
+        SensorView::Graph => {
+            let top = layout.scaled(36.0);
+            let graph_area = [area[0], area[1] + top, area[2], area[3] - top - layout.scaled(8.0)];
+            draw_graph(sensor.graph(), graph_area, layout.scaled(1.0), palette.graph, c, g);
+        }
+    };
+}
+
+/// Render sensor temperature as big as possible.
+///
+/// # Arguments
+///
+/// * `font` - font
+/// * `layout` - layout
+/// * `palette` - theme palette
+/// * `area` - sensor area
+/// * `sensor` - sensor model
+/// * `c` - context
+/// * `g` - graphics
+fn draw_big_number(
+    font: &mut Font,
+    layout: &Layout,
+    palette: &Palette,
+    area: Rectangle,
+    sensor: &SensorModel,
+    c: &Context,
+    g: &mut G2d,
+) {
+    let title_size = layout.font_size(20);
+    let baseline = area[1] + f64::from(title_size);
+    let left = area[0] + draw_label_icon(sensor.icon(), area[0], baseline, f64::from(title_size), palette, c, g);
+    let title = match sensor.room() {
+        Some(room) => format!("{} ({})", sensor.name(), room),
+        None => sensor.name().to_string(),
+    };
+    font.draw_at(
+        [left as i32, baseline as i32],
+        &title,
+        title_size,
+        palette.secondary,
+        c,
+        g,
+    );
+
+    let top = f64::from(title_size) * 1.5;
+    let number_area = [area[0], area[1] + top, area[2], area[3] - top];
+    // Space for the trend arrow (two characters)
+    let size = match sensor.trend() {
+        Some(_) => layout.fit_font_size(&format!("{}  ", sensor.temperature()), number_area),
+        None => layout.fit_font_size(sensor.temperature(), number_area),
+    };
+    // Baseline, vertically centered
+    let y = number_area[1] + (number_area[3] + f64::from(size) * 0.7) / 2.0;
+    font.draw_at(
+        [area[0] as i32, y as i32],
+        sensor.temperature(),
+        size,
+        temperature_color(palette, sensor),
+        c,
+        g,
+    );
+
+    if let Some(trend) = sensor.trend() {
+        let x = area[0] + layout.text_width(sensor.temperature(), size) + f64::from(size) * 0.3;
+        draw_trend(trend, x, y, f64::from(size), palette, c, g);
+    }
+}
+
+/// Render trend arrow.
+///
+/// # Arguments
+///
+/// * `trend` - temperature trend
+/// * `x` - arrow left position
+/// * `baseline` - text baseline
+/// * `size` - font size
+/// * `palette` - theme palette
+/// * `c` - context
+/// * `g` - graphics
+fn draw_trend(trend: Trend, x: f64, baseline: f64, size: f64, palette: &Palette, c: &Context, g: &mut G2d) {
+    // Arrow fits into the box of the digit height
+    let height = size * 0.7;
+    let (left, right) = (x, x + height);
+    let (top, bottom, middle) = (baseline - height, baseline, baseline - height / 2.0);
+
+    let (from, to) = match trend {
+        Trend::Rising => ([left, bottom], [right, top]),
+        Trend::Falling => ([left, top], [right, bottom]),
+        Trend::Steady => ([left, middle], [right, middle]),
+    };
+
+    // Arrow head, two lines rotated by +/- 150 degrees from the arrow direction
+    let angle = (to[1] - from[1]).atan2(to[0] - from[0]);
+    let head = height * 0.4;
+    let radius = (size / 20.0).max(0.5);
+
+    line(
+        palette.secondary,
+        radius,
+        [from[0], from[1], to[0], to[1]],
+        c.transform,
+        g,
+    );
+    for delta in [150f64, -150f64].iter() {
+        let a = angle + delta.to_radians();
+        line(
+            palette.secondary,
+            radius,
+            [to[0], to[1], to[0] + head * a.cos(), to[1] + head * a.sin()],
+            c.transform,
+            g,
+        );
+    }
+}
+
+/// Render sensor icon in front of the label.
+///
+/// Returns horizontal space taken by the icon (`0.0` if there's no icon).
+///
+/// # Arguments
+///
+/// * `icon` - sensor icon
+/// * `x` - icon left position
+/// * `baseline` - label baseline
+/// * `size` - label font size
+/// * `palette` - theme palette
+/// * `c` - context
+/// * `g` - graphics
+fn draw_label_icon(
+    icon: Option<Icon>,
+    x: f64,
+    baseline: f64,
+    size: f64,
+    palette: &Palette,
+    c: &Context,
+    g: &mut G2d,
+) -> f64 {
+    let icon = match icon {
+        Some(icon) => icon,
+        None => return 0.0,
+    };
+
+    // Icon fits into the box of the digit height
+    let h = size * 0.7;
+    let (y, w) = (baseline - h, h);
+    let radius = (size / 20.0).max(0.5);
+
+    match icon {
+        Icon::Home => {
+            polygon(
+                palette.secondary,
+                &[[x, y + h * 0.45], [x + w * 0.5, y], [x + w, y + h * 0.45]],
+                c.transform,
+                g,
+            );
+            polygon(
+                palette.secondary,
+                &[
+                    [x + w * 0.15, y + h * 0.45],
+                    [x + w * 0.85, y + h * 0.45],
+                    [x + w * 0.85, y + h],
+                    [x + w * 0.15, y + h],
+                ],
+                c.transform,
+                g,
+            );
+        }
+        Icon::Tree => {
+            ellipse(palette.graph, [x + w * 0.1, y, w * 0.8, h * 0.7], c.transform, g);
+            line(
+                palette.secondary,
+                radius * 1.5,
+                [x + w * 0.5, y + h * 0.6, x + w * 0.5, y + h],
+                c.transform,
+                g,
+            );
+        }
+        Icon::Snowflake => {
+            let (cx, cy) = (x + w / 2.0, y + h / 2.0);
+            for i in 0..3 {
+                let a = (60.0 * f64::from(i)).to_radians();
+                let (dx, dy) = (a.cos() * w / 2.0, a.sin() * h / 2.0);
+                line(
+                    palette.graph,
+                    radius,
+                    [cx - dx, cy - dy, cx + dx, cy + dy],
+                    c.transform,
+                    g,
+                );
+            }
+        }
+        Icon::Flame => polygon(
+            palette.alarm,
+            &[
+                [x + w * 0.5, y],
+                [x + w * 0.85, y + h * 0.6],
+                [x + w * 0.7, y + h],
+                [x + w * 0.3, y + h],
+                [x + w * 0.15, y + h * 0.6],
+            ],
+            c.transform,
+            g,
+        ),
+    };
+
+    w + size * 0.3
+}
+
+/// Render weather forecast (icon, low & high temperatures) in the top bar.
+///
+/// # Arguments
+///
+/// * `font` - font
+/// * `layout` - layout
+/// * `palette` - theme palette
+/// * `weather` - weather model
+/// * `c` - context
+/// * `g` - graphics
+fn draw_weather(font: &mut Font, layout: &Layout, palette: &Palette, weather: &WeatherModel, c: &Context, g: &mut G2d) {
+    let area = layout.weather_area();
+    let font_size = layout.font_size(14);
+
+    // Right aligned text with the icon on the left side
+    let x = area[0] + area[2] - layout.text_width(weather.text(), font_size);
+    font.draw_at(
+        [x as i32, layout.time_position()[1]],
+        weather.text(),
+        font_size,
+        palette.foreground,
+        c,
+        g,
+    );
+
+    let icon_size = area[3] * 0.6;
+    let icon = [
+        x - icon_size - layout.scaled(8.0),
+        area[1] + (area[3] - icon_size) / 2.0,
+        icon_size,
+        icon_size,
+    ];
+    draw_weather_icon(weather.condition(), icon, palette, c, g);
+}
+
+/// Render weather condition icon.
+///
+/// # Arguments
+///
+/// * `condition` - weather condition
+/// * `rect` - icon rectangle
+/// * `palette` - theme palette
+/// * `c` - context
+/// * `g` - graphics
+fn draw_weather_icon(condition: Condition, rect: Rectangle, palette: &Palette, c: &Context, g: &mut G2d) {
+    let [x, y, w, h] = rect;
+    // Cloud in the upper part, precipitation below it
+    let upper = [x, y, w, h * 0.65];
+    let radius = (w / 30.0).max(0.5);
+
+    match condition {
+        Condition::Clear => ellipse(
+            palette.graph,
+            [x + w * 0.1, y + h * 0.1, w * 0.8, h * 0.8],
+            c.transform,
+            g,
+        ),
+        Condition::PartlyCloudy => {
+            ellipse(palette.graph, [x + w * 0.35, y, w * 0.6, h * 0.6], c.transform, g);
+            draw_cloud(palette.secondary, [x, y + h * 0.3, w * 0.8, h * 0.6], c, g);
+        }
+        Condition::Cloudy => draw_cloud(palette.secondary, [x, y + h * 0.15, w, h * 0.7], c, g),
+        Condition::Fog => {
+            for i in 1..4 {
+                let line_y = y + h * 0.25 * f64::from(i);
+                line(
+                    palette.secondary,
+                    radius * 2.0,
+                    [x, line_y, x + w, line_y],
+                    c.transform,
+                    g,
+                );
+            }
+        }
+        Condition::Drizzle | Condition::Rain => {
+            draw_cloud(palette.secondary, upper, c, g);
+            let length = if condition == Condition::Rain { 0.3 } else { 0.15 };
+            for i in 0..3 {
+                let drop_x = x + w * (0.25 + 0.25 * f64::from(i));
+                let drop = [drop_x, y + h * 0.7, drop_x - w * 0.08, y + h * (0.7 + length)];
+                line(palette.foreground, radius, drop, c.transform, g);
+            }
+        }
+        Condition::Snow => {
+            draw_cloud(palette.secondary, upper, c, g);
+            let flake = w * 0.12;
+            for i in 0..3 {
+                let flake_x = x + w * (0.2 + 0.25 * f64::from(i));
+                ellipse(
+                    palette.foreground,
+                    [flake_x, y + h * 0.78, flake, flake],
+                    c.transform,
+                    g,
+                );
+            }
+        }
+        Condition::Thunderstorm => {
+            draw_cloud(palette.secondary, upper, c, g);
+            let bolt = [
+                [x + w * 0.55, y + h * 0.6, x + w * 0.4, y + h * 0.8],
+                [x + w * 0.4, y + h * 0.8, x + w * 0.55, y + h * 0.8],
+                [x + w * 0.55, y + h * 0.8, x + w * 0.4, y + h],
+            ];
+            for segment in bolt.iter() {
+                line(palette.alarm, radius * 1.5, *segment, c.transform, g);
+            }
+        }
+    };
+}
+
+/// Render cloud (three overlapping ellipses).
+///
+/// # Arguments
+///
+/// * `color` - cloud color
+/// * `rect` - cloud rectangle
+/// * `c` - context
+/// * `g` - graphics
+fn draw_cloud(color: Color, rect: Rectangle, c: &Context, g: &mut G2d) {
+    let [x, y, w, h] = rect;
+    ellipse(color, [x, y + h * 0.4, w * 0.55, h * 0.6], c.transform, g);
+    ellipse(color, [x + w * 0.2, y, w * 0.55, h * 0.8], c.transform, g);
+    ellipse(color, [x + w * 0.45, y + h * 0.3, w * 0.55, h * 0.7], c.transform, g);
+}
+
+/// Render graph.
+///
+/// # Arguments
+///
+/// * `values` - graph values, oldest first
+/// * `area` - graph area
+/// * `radius` - line radius
+/// * `color` - line color
+/// * `c` - context
+/// * `g` - graphics
+fn draw_graph(values: &[f64], area: Rectangle, radius: f64, color: Color, c: &Context, g: &mut G2d) {
+    if values.len() < 2 || area[3] <= 0.0 {
+        return;
+    }
+
+    let min = values.iter().cloned().fold(INFINITY, f64::min);
+    let max = values.iter().cloned().fold(NEG_INFINITY, f64::max);
+    // Flat line in the middle if all values are the same
+    let range = if max - min > 0.0 { max - min } else { 1.0 };
+    let step = area[2] / (values.len() - 1) as f64;
+
+    let point = |i: usize, v: f64| [area[0] + i as f64 * step, area[1] + area[3] * (1.0 - (v - min) / range)];
+
+    for i in 1..values.len() {
+        let (from, to) = (point(i - 1, values[i - 1]), point(i, values[i]));
+        line(color, radius, [from[0], from[1], to[0], to[1]], c.transform, g);
+    }
+}
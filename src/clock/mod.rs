@@ -0,0 +1,194 @@
+//! Timezone & system clock synchronisation.
+//!
+//! Raspberry Pi has no real time clock, time starts in the past (1970 or the last
+//! shutdown time) until it's synchronised over the network. Readings taken before
+//! the clock is synchronised have wrong timestamps.
+mod zoneinfo;
+
+pub use self::zoneinfo::Zone;
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDate, Offset, TimeZone, Utc};
+use error::Error;
+use std::str::FromStr;
+
+/// Clock showing an earlier year is not synchronised.
+pub const MIN_YEAR: i32 = 2018;
+
+/// Timezone.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Timezone {
+    /// System timezone (`TZ` environment variable or `/etc/localtime`).
+    System,
+    /// IANA timezone from the system timezone database, e.g. `Europe/Prague`.
+    Named(&'static Zone),
+}
+
+impl Timezone {
+    /// Time in this timezone.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - time
+    pub fn localize(self, time: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            Timezone::System => {
+                let local = time.with_timezone(&Local);
+                local.with_timezone(&local.offset().fix())
+            }
+            Timezone::Named(zone) => time.with_timezone(&FixedOffset::east(zone.offset(time.timestamp()))),
+        }
+    }
+
+    /// Current time in this timezone.
+    pub fn now(self) -> DateTime<FixedOffset> {
+        self.localize(Utc::now())
+    }
+
+    /// Midnight at the start of the date in this timezone.
+    ///
+    /// # Arguments
+    ///
+    /// * `date` - date
+    pub fn midnight(self, date: NaiveDate) -> Option<DateTime<Utc>> {
+        let midnight = date.and_hms(0, 0, 0);
+
+        match self {
+            Timezone::System => Local
+                .from_local_datetime(&midnight)
+                .earliest()
+                .map(|t| t.with_timezone(&Utc)),
+            Timezone::Named(zone) => zone.to_utc(midnight.timestamp()).map(|t| Utc.timestamp(t, 0)),
+        }
+    }
+}
+
+impl AsRef<str> for Timezone {
+    fn as_ref(&self) -> &str {
+        match self {
+            Timezone::System => "system",
+            Timezone::Named(zone) => zone.name(),
+        }
+    }
+}
+
+impl FromStr for Timezone {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "system" => Ok(Timezone::System),
+            // Zones live for the whole run, they are leaked like interned sensor names
+            _ => Zone::load(s).map(|zone| Timezone::Named(Box::leak(Box::new(zone)))),
+        }
+    }
+}
+
+/// Clock synchronisation check.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum SyncCheck {
+    /// Kernel clock state (`adjtimex`) & year sanity check.
+    Kernel,
+    /// Year sanity check only.
+    Year,
+    /// Clock is always considered synchronised.
+    Off,
+}
+
+impl SyncCheck {
+    /// Check if the clock is synchronised.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - current time
+    pub fn is_synchronized(self, now: DateTime<Utc>) -> bool {
+        match self {
+            SyncCheck::Kernel => now.year() >= MIN_YEAR && kernel_synchronized().unwrap_or(true),
+            SyncCheck::Year => now.year() >= MIN_YEAR,
+            SyncCheck::Off => true,
+        }
+    }
+}
+
+impl AsRef<str> for SyncCheck {
+    fn as_ref(&self) -> &str {
+        match self {
+            SyncCheck::Kernel => "kernel",
+            SyncCheck::Year => "year",
+            SyncCheck::Off => "off",
+        }
+    }
+}
+
+impl FromStr for SyncCheck {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "kernel" => Ok(SyncCheck::Kernel),
+            "year" => Ok(SyncCheck::Year),
+            "off" => Ok(SyncCheck::Off),
+            _ => Err(Error::from(format!("Invalid clock sync check: {}", s))),
+        }
+    }
+}
+
+/// Kernel clock state, `None` if it can't be determined.
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn kernel_synchronized() -> Option<bool> {
+    use libc;
+    use std::mem;
+
+    // `modes` = 0 only reads the clock state
+    let mut timex: libc::timex = unsafe { mem::zeroed() };
+    let state = unsafe { libc::adjtimex(&mut timex) };
+
+    if state < 0 {
+        None
+    } else {
+        Some(state != libc::TIME_ERROR)
+    }
+}
+
+/// Kernel clock state, `None` if it can't be determined.
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+fn kernel_synchronized() -> Option<bool> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SyncCheck, Timezone, Zone};
+    use chrono::{NaiveDate, TimeZone, Timelike, Utc};
+
+    /// Timezone from the bundled TZif file, tests don't depend on the system database.
+    fn fixture(name: &str, data: &[u8]) -> Timezone {
+        Timezone::Named(Box::leak(Box::new(Zone::parse(name, data).unwrap())))
+    }
+
+    #[test]
+    fn test_timezone() {
+        let prague = fixture("Europe/Prague", include_bytes!("fixtures/Europe/Prague"));
+        assert_eq!(prague.as_ref(), "Europe/Prague");
+        assert_eq!("system".parse::<Timezone>().unwrap(), Timezone::System);
+        assert!("../Europe/Prague".parse::<Timezone>().is_err());
+
+        // Summer & winter time
+        assert_eq!(prague.localize(Utc.ymd(2018, 7, 1).and_hms(12, 0, 0)).hour(), 14);
+        assert_eq!(prague.localize(Utc.ymd(2018, 1, 1).and_hms(12, 0, 0)).hour(), 13);
+
+        let new_york = fixture("America/New_York", include_bytes!("fixtures/America/New_York"));
+        assert_eq!(
+            new_york.midnight(NaiveDate::from_ymd(2018, 7, 1)).unwrap(),
+            Utc.ymd(2018, 7, 1).and_hms(4, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_sync_check() {
+        let unsynced = Utc.ymd(1970, 1, 1).and_hms(0, 0, 42);
+        assert!(!SyncCheck::Year.is_synchronized(unsynced));
+        assert!(!SyncCheck::Kernel.is_synchronized(unsynced));
+        assert!(SyncCheck::Off.is_synchronized(unsynced));
+        assert!(SyncCheck::Year.is_synchronized(Utc.ymd(2018, 7, 1).and_hms(12, 0, 0)));
+        assert!("ntp".parse::<SyncCheck>().is_err());
+    }
+}
@@ -0,0 +1,380 @@
+//! IANA timezone database.
+//!
+//! Timezones are loaded from the compiled TZif files (RFC 8536) of the system timezone
+//! database in `/usr/share/zoneinfo` (or the `TZDIR` folder), version 2+ files are
+//! required. Times after the last transition in the file follow the POSIX TZ rule in the
+//! file footer, only the `Mm.w.d` rules used by the database are supported, e.g.
+//! `CET-1CEST,M3.5.0,M10.5.0/3`. The last offset is used if the rule is not supported.
+use chrono::{Datelike, NaiveDate};
+use error::Error;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+/// Default timezone database folder.
+const ZONEINFO_FOLDER: &str = "/usr/share/zoneinfo";
+
+/// Transition time of POSIX TZ rules if it's not specified (02:00:00).
+const DEFAULT_RULE_TIME: i64 = 7_200;
+
+/// Split the timezone abbreviation (`CET` or `<+0330>`) from the start of the string,
+/// returns the rest.
+fn skip_name(s: &str) -> Option<&str> {
+    let length = if s.starts_with('<') {
+        s.find('>')? + 1
+    } else {
+        s.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(s.len())
+    };
+
+    if length < 3 {
+        None
+    } else {
+        Some(&s[length..])
+    }
+}
+
+/// Split time `[+|-]hh[:mm[:ss]]` in seconds from the start of the string, returns
+/// the time & the rest.
+fn split_time(s: &str) -> Option<(i64, &str)> {
+    let length = s
+        .char_indices()
+        .find(|&(i, c)| !(c.is_ascii_digit() || c == ':' || (i == 0 && (c == '+' || c == '-'))))
+        .map_or(s.len(), |(i, _)| i);
+    let (time, rest) = s.split_at(length);
+    let (sign, time) = match time.chars().next() {
+        Some('-') => (-1, &time[1..]),
+        Some('+') => (1, &time[1..]),
+        _ => (1, time),
+    };
+
+    let parts: Vec<&str> = time.split(':').collect();
+    if parts.len() > 3 {
+        return None;
+    }
+    let mut seconds = 0;
+    for (part, unit) in parts.iter().zip(&[3_600, 60, 1]) {
+        seconds += part.parse::<i64>().ok()? * unit;
+    }
+    Some((sign * seconds, rest))
+}
+
+/// Day `d` (0 Sunday) of week `w` (5 last) of month `m` (`Mm.w.d`) in which the DST
+/// starts or ends.
+#[derive(PartialEq, Debug, Copy, Clone)]
+struct RuleDay {
+    month: u32,
+    week: u32,
+    weekday: u32,
+}
+
+impl RuleDay {
+    /// Parse `Mm.w.d[/time]` rule day & time.
+    fn parse(s: &str) -> Option<(RuleDay, i64)> {
+        let (day, time) = match s.find('/') {
+            Some(i) => match split_time(&s[i + 1..])? {
+                (time, "") => (&s[..i], time),
+                _ => return None,
+            },
+            None => (s, DEFAULT_RULE_TIME),
+        };
+        if !day.starts_with('M') {
+            return None;
+        }
+
+        let numbers = day[1..]
+            .split('.')
+            .map(|n| n.parse::<u32>().ok())
+            .collect::<Option<Vec<u32>>>()?;
+        match numbers[..] {
+            [month, week, weekday] if month >= 1 && month <= 12 && week >= 1 && week <= 5 && weekday <= 6 => {
+                Some((RuleDay { month, week, weekday }, time))
+            }
+            _ => None,
+        }
+    }
+
+    /// Date in the given year.
+    fn date(self, year: i32) -> Option<NaiveDate> {
+        let first = NaiveDate::from_ymd_opt(year, self.month, 1)?;
+        let day = 1 + (self.weekday + 7 - first.weekday().num_days_from_sunday()) % 7 + (self.week - 1) * 7;
+        // Week 5 is the last week, it can be the 4th one
+        NaiveDate::from_ymd_opt(year, self.month, day).or_else(|| NaiveDate::from_ymd_opt(year, self.month, day - 7))
+    }
+}
+
+/// Daylight saving time of the POSIX TZ rule.
+#[derive(PartialEq, Debug, Copy, Clone)]
+struct Dst {
+    /// Offset east of UTC (s).
+    offset: i32,
+    /// DST starts on this day at the given local standard time (s).
+    start: (RuleDay, i64),
+    /// DST ends on this day at the given local daylight saving time (s).
+    end: (RuleDay, i64),
+}
+
+/// POSIX TZ rule.
+#[derive(PartialEq, Debug, Copy, Clone)]
+struct Rule {
+    /// Standard time offset east of UTC (s).
+    offset: i32,
+    /// Daylight saving time or `None` if it's not observed.
+    dst: Option<Dst>,
+}
+
+impl Rule {
+    /// Parse the rule, `None` if it's invalid or not supported.
+    fn parse(s: &str) -> Option<Rule> {
+        let parts: Vec<&str> = s.split(',').collect();
+
+        // POSIX offsets are positive west of UTC
+        let (offset, rest) = split_time(skip_name(parts[0])?)?;
+        let offset = -offset as i32;
+        if rest.is_empty() {
+            return if parts.len() == 1 {
+                Some(Rule { offset, dst: None })
+            } else {
+                None
+            };
+        }
+
+        let rest = skip_name(rest)?;
+        let dst_offset = if rest.is_empty() {
+            offset + 3_600
+        } else {
+            match split_time(rest)? {
+                (dst_offset, "") => -dst_offset as i32,
+                _ => return None,
+            }
+        };
+        if parts.len() != 3 {
+            return None;
+        }
+
+        let dst = Dst {
+            offset: dst_offset,
+            start: RuleDay::parse(parts[1])?,
+            end: RuleDay::parse(parts[2])?,
+        };
+        Some(Rule { offset, dst: Some(dst) })
+    }
+
+    /// Offset east of UTC (s) at the given time (seconds since the Unix epoch).
+    fn offset(&self, time: i64) -> i32 {
+        let dst = match self.dst {
+            Some(dst) => dst,
+            None => return self.offset,
+        };
+
+        let days = (time + i64::from(self.offset)) / 86_400 + 719_163;
+        let year = match NaiveDate::from_num_days_from_ce_opt(days as i32) {
+            Some(date) => date.year(),
+            None => return self.offset,
+        };
+        let transition = |(day, time): (RuleDay, i64), offset: i32| {
+            day.date(year)
+                .map(|d| d.and_hms(0, 0, 0).timestamp() + time - i64::from(offset))
+        };
+
+        match (transition(dst.start, self.offset), transition(dst.end, dst.offset)) {
+            // Northern hemisphere, DST in the middle of the year
+            (Some(start), Some(end)) if start < end && time >= start && time < end => dst.offset,
+            // Southern hemisphere, DST at the start & end of the year
+            (Some(start), Some(end)) if start >= end && (time < end || time >= start) => dst.offset,
+            _ => self.offset,
+        }
+    }
+}
+
+/// Big endian signed integer of the given size (4 or 8 bytes).
+fn read_int(data: &[u8], size: usize) -> i64 {
+    let value = data[..size].iter().fold(0u64, |v, b| v << 8 | u64::from(*b));
+    if size == 4 {
+        i64::from(value as u32 as i32)
+    } else {
+        value as i64
+    }
+}
+
+/// Timezone loaded from the timezone database.
+#[derive(PartialEq, Debug)]
+pub struct Zone {
+    name: String,
+    /// Transition times (seconds since the Unix epoch) with the offset used since then,
+    /// the first one is the offset used before any transition.
+    transitions: Vec<(i64, i32)>,
+    /// Rule used after the last transition.
+    rule: Option<Rule>,
+}
+
+impl Zone {
+    /// Load timezone from the timezone database.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - IANA timezone name (e.g. `Europe/Prague`)
+    pub fn load(name: &str) -> Result<Zone, Error> {
+        let valid = !name.is_empty()
+            && !name.starts_with('/')
+            && name
+                .split('/')
+                .all(|p| !p.is_empty() && p != "." && p != ".." && p.chars().all(|c| c.is_ascii_graphic()));
+        if !valid {
+            return Err(Error::from(format!("Invalid timezone: {}", name)));
+        }
+
+        let folder = env::var_os("TZDIR")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(ZONEINFO_FOLDER));
+        let path = folder.join(name);
+        let data = fs::read(&path)
+            .map_err(|e| Error::from(e).context(format!("Unable to load timezone {} from {}", name, path.display())))?;
+
+        Zone::parse(name, &data).ok_or_else(|| Error::Parse(format!("invalid timezone file {}", path.display())))
+    }
+
+    /// Parse TZif file, `None` if it's invalid.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - timezone name
+    /// * `data` - TZif file content
+    pub fn parse(name: &str, data: &[u8]) -> Option<Zone> {
+        // Header counts & the data block length for the given time size
+        let header = |data: &[u8], time_size: usize| {
+            if data.len() < 44 || &data[..4] != b"TZif" || data[4] < b'2' {
+                return None;
+            }
+            let count: Vec<usize> = (0..6).map(|i| read_int(&data[20 + i * 4..], 4) as usize).collect();
+            let (isutcnt, isstdcnt, leapcnt, timecnt, typecnt, charcnt) =
+                (count[0], count[1], count[2], count[3], count[4], count[5]);
+            let length =
+                44 + timecnt * (time_size + 1) + typecnt * 6 + charcnt + leapcnt * (time_size + 4) + isstdcnt + isutcnt;
+            if typecnt == 0 || data.len() < length {
+                None
+            } else {
+                Some((timecnt, typecnt, length))
+            }
+        };
+
+        // Version 1 block with 32 bit times is followed by the header & block with 64 bit times
+        let (_, _, length) = header(data, 4)?;
+        let data = &data[length..];
+        let (timecnt, typecnt, length) = header(data, 8)?;
+
+        let indices = 44 + timecnt * 8;
+        let offset = |index: u8| read_int(&data[indices + timecnt + index as usize * 6..], 4) as i32;
+        let mut transitions = vec![(::std::i64::MIN, offset(0))];
+        for i in 0..timecnt {
+            if data[indices + i] as usize >= typecnt {
+                return None;
+            }
+            transitions.push((read_int(&data[44 + i * 8..], 8), offset(data[indices + i])));
+        }
+
+        let footer = String::from_utf8_lossy(&data[length..]);
+        Some(Zone {
+            name: name.to_string(),
+            transitions,
+            rule: Rule::parse(footer.trim_matches('\n')),
+        })
+    }
+
+    /// Timezone name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Offset east of UTC (s) at the given time.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - seconds since the Unix epoch
+    pub fn offset(&self, time: i64) -> i32 {
+        let index = match self.transitions.binary_search_by_key(&time, |t| t.0) {
+            Ok(index) => index,
+            Err(index) => index - 1,
+        };
+
+        match self.rule {
+            Some(rule) if index == self.transitions.len() - 1 => rule.offset(time),
+            _ => self.transitions[index].1,
+        }
+    }
+
+    /// Earliest time (seconds since the Unix epoch) at which the local time is the given
+    /// one, `None` if the local time is skipped (DST gap).
+    ///
+    /// # Arguments
+    ///
+    /// * `local` - local time as seconds since the Unix epoch
+    pub fn to_utc(&self, local: i64) -> Option<i64> {
+        // Offsets change at most once a day
+        [self.offset(local - 86_400), self.offset(local + 86_400)]
+            .iter()
+            .map(|offset| local - i64::from(*offset))
+            .filter(|time| i64::from(self.offset(*time)) == local - time)
+            .min()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Rule, Zone};
+    use chrono::{TimeZone, Utc};
+
+    fn time(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> i64 {
+        Utc.ymd(year, month, day).and_hms(hour, minute, 0).timestamp()
+    }
+
+    #[test]
+    fn test_rules() {
+        let prague = Rule::parse("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        assert_eq!(prague.offset(time(2030, 1, 1, 12, 0)), 3_600);
+        assert_eq!(prague.offset(time(2030, 7, 1, 12, 0)), 7_200);
+        // Last Sunday in March at 02:00 CET = 01:00 UTC
+        assert_eq!(prague.offset(time(2030, 3, 31, 0, 59)), 3_600);
+        assert_eq!(prague.offset(time(2030, 3, 31, 1, 0)), 7_200);
+
+        let sydney = Rule::parse("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        assert_eq!(sydney.offset(time(2030, 1, 1, 12, 0)), 39_600);
+        assert_eq!(sydney.offset(time(2030, 7, 1, 12, 0)), 36_000);
+
+        let nuuk = Rule::parse("<-02>2<-01>,M3.5.0/-1,M10.5.0/0").unwrap();
+        assert_eq!(nuuk.offset(time(2030, 7, 1, 12, 0)), -3_600);
+        assert_eq!(
+            Rule::parse("<+0330>-3:30").unwrap().offset(time(2030, 7, 1, 12, 0)),
+            12_600
+        );
+
+        assert_eq!(Rule::parse("C-1"), None);
+        assert_eq!(Rule::parse("CET-1CEST"), None);
+        assert_eq!(Rule::parse("CET-1CEST,M13.1.0,M10.5.0"), None);
+        assert_eq!(Rule::parse("XST3XDT,J60,300"), None);
+    }
+
+    #[test]
+    fn test_parse_zone() {
+        let data = include_bytes!("fixtures/Europe/Prague");
+        let zone = Zone::parse("Europe/Prague", data).unwrap();
+        assert_eq!(zone.name(), "Europe/Prague");
+
+        // Transitions in the file
+        assert_eq!(zone.offset(time(2018, 1, 1, 12, 0)), 3_600);
+        assert_eq!(zone.offset(time(2018, 3, 25, 0, 59)), 3_600);
+        assert_eq!(zone.offset(time(2018, 3, 25, 1, 0)), 7_200);
+        // Rule after the last transition
+        assert_eq!(zone.offset(time(2040, 1, 1, 12, 0)), 3_600);
+        assert_eq!(zone.offset(time(2040, 7, 1, 12, 0)), 7_200);
+        // Local mean time before the first transition
+        assert_eq!(zone.offset(time(1800, 1, 1, 12, 0)), 3_464);
+
+        // 02:30 local time doesn't exist, 02:30 in October exists twice
+        assert_eq!(zone.to_utc(time(2019, 3, 31, 2, 30)), None);
+        assert_eq!(zone.to_utc(time(2019, 10, 27, 2, 30)), Some(time(2019, 10, 27, 0, 30)));
+        assert_eq!(zone.to_utc(time(2040, 1, 1, 0, 0)), Some(time(2039, 12, 31, 23, 0)));
+
+        assert_eq!(Zone::parse("Europe/Prague", &data[..1_000]), None);
+        assert!(Zone::load("../etc/passwd").is_err());
+    }
+}
@@ -1,7 +1,25 @@
-use clap::{App, Arg};
+use app::backlight::Schedule as BacklightSchedule;
+use app::label::{check_sensors, parse_labels, parse_order, Label};
+use app::layout::{LayoutMode, Rotation as DisplayRotation};
+use app::locale::Locale;
+use app::theme::{parse_time_of_day, NightTime, ThemeMode};
+use chrono::{DateTime, Duration, Utc};
+use clap::{self, App, Arg, ArgMatches, SubCommand};
+use clock::{SyncCheck, Timezone};
+use derived::{check_operands, parse_virtual_sensors, VirtualSensor};
+use export::{parse_time, Format};
+use log::{Format as LogFormat, Rotation, SyslogAddress, Target as LogTarget, DEFAULT_JOURNALD_SOCKET};
+use processing::filter::{parse_filters, FilterKind};
+use processing::{parse_sensors, Overrun, Sensor, MAX_PRECISION};
+use rollup::{Resolution, Retention};
+use slog::Level;
+use state::Location;
 use std::fmt::Display;
 use std::str::FromStr;
-use w1::thermometer::Units;
+use std::time::Duration as StdDuration;
+use storage::Batch;
+use w1::thermometer::{self, Units};
+use weather::Source as WeatherSource;
 
 /// Package version (set at compile time).
 pub const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -9,6 +27,35 @@ pub const PKG_VERSION: &str = env!("CARGO_PKG_VERSION");
 /// Package name (set at compile time).
 pub const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 
+/// Number of decimal places displayed for additional & virtual sensors which don't set it.
+pub const DEFAULT_SENSOR_PRECISION: usize = 1;
+
+/// Number type accepted by `validate`.
+trait Number: FromStr + PartialOrd + Display {
+    /// Expected value description used in the error message.
+    const DESCRIPTION: &'static str;
+}
+
+impl Number for u8 {
+    const DESCRIPTION: &'static str = "a non-negative integer";
+}
+
+impl Number for u64 {
+    const DESCRIPTION: &'static str = "a non-negative integer";
+}
+
+impl Number for usize {
+    const DESCRIPTION: &'static str = "a non-negative integer";
+}
+
+impl Number for i64 {
+    const DESCRIPTION: &'static str = "an integer";
+}
+
+impl Number for f64 {
+    const DESCRIPTION: &'static str = "a number";
+}
+
 /// Return `()` in case provided argument is valid otherwise error message is returned.
 ///
 /// # Arguments
@@ -19,7 +66,7 @@ pub const PKG_NAME: &str = env!("CARGO_PKG_NAME");
 #[cfg_attr(feature = "cargo-clippy", allow(trivially_copy_pass_by_ref, needless_pass_by_value))]
 fn validate<T>(value: String, min: Option<T>, max: Option<T>) -> Result<(), String>
 where
-    T: FromStr + PartialOrd + Display,
+    T: Number,
 {
     match value.parse::<T>() {
         Ok(value) => {
@@ -36,7 +83,7 @@ where
 
             Ok(())
         }
-        Err(_) => Err("Invalid port number".to_string()),
+        Err(_) => Err(format!("Value must be {}: {}", T::DESCRIPTION, value)),
     }
 }
 
@@ -44,17 +91,218 @@ fn validate_max_fps(value: String) -> Result<(), String> {
     validate::<u64>(value, Some(2), Some(60))
 }
 
+fn validate_ui_scale(value: String) -> Result<(), String> {
+    validate::<f64>(value, Some(0.25), Some(4.0))
+}
+
+fn validate_time_of_day(value: String) -> Result<(), String> {
+    parse_time_of_day(&value).map(|_| ()).map_err(|e| e.to_string())
+}
+
 fn validate_temperature_interval(value: String) -> Result<(), String> {
     validate::<u64>(value, Some(500), Some(60_000))
 }
 
+fn validate_temperature_read_timeout(value: String) -> Result<(), String> {
+    validate::<u64>(value, Some(100), Some(60_000))
+}
+
+fn validate_precision(value: String) -> Result<(), String> {
+    validate::<usize>(value, Some(0), Some(MAX_PRECISION))
+}
+
+fn validate_history_size(value: String) -> Result<(), String> {
+    validate::<usize>(value, Some(1), Some(1_000_000))
+}
+
+fn validate_thermometer_device(value: String) -> Result<(), String> {
+    thermometer::validate(&value).map_err(|e| e.to_string())
+}
+
+fn validate_extra_sensors(value: String) -> Result<(), String> {
+    parse_sensors(&value).map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn validate_filters(value: String) -> Result<(), String> {
+    parse_filters(&value).map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn validate_log_file_max_size(value: String) -> Result<(), String> {
+    validate::<u64>(value, Some(1_024), None)
+}
+
+fn validate_log_file_count(value: String) -> Result<(), String> {
+    validate::<usize>(value, Some(0), Some(100))
+}
+
+fn validate_syslog_address(value: String) -> Result<(), String> {
+    value.parse::<SyslogAddress>().map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn validate_trend_window(value: String) -> Result<(), String> {
+    validate::<u64>(value, Some(1), Some(1_440))
+}
+
+fn validate_trend_threshold(value: String) -> Result<(), String> {
+    validate::<f64>(value, Some(0.0), Some(10.0))
+}
+
+fn validate_virtual_sensors(value: String) -> Result<(), String> {
+    parse_virtual_sensors(&value).map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn validate_location(value: String) -> Result<(), String> {
+    value.parse::<Location>().map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn validate_brightness(value: String) -> Result<(), String> {
+    validate::<u8>(value, Some(0), Some(255))
+}
+
+fn validate_idle_timeout(value: String) -> Result<(), String> {
+    validate::<u64>(value, Some(0), Some(86_400))
+}
+
+fn validate_timezone(value: String) -> Result<(), String> {
+    value.parse::<Timezone>().map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn validate_locale(value: String) -> Result<(), String> {
+    value.parse::<Locale>().map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn validate_sensor_labels(value: String) -> Result<(), String> {
+    parse_labels(&value).map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn validate_sensor_order(value: String) -> Result<(), String> {
+    parse_order(&value).map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn validate_weather_source(value: String) -> Result<(), String> {
+    value.parse::<WeatherSource>().map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn validate_weather_interval(value: String) -> Result<(), String> {
+    validate::<u64>(value, Some(60), Some(86_400))
+}
+
+fn validate_retention(value: String) -> Result<(), String> {
+    validate::<i64>(value, Some(1), Some(36_500))
+}
+
+fn validate_compaction_interval(value: String) -> Result<(), String> {
+    validate::<u64>(value, Some(60), Some(86_400))
+}
+
+fn validate_storage_flush_interval(value: String) -> Result<(), String> {
+    validate::<u64>(value, Some(1), Some(3_600))
+}
+
+fn validate_storage_flush_size(value: String) -> Result<(), String> {
+    validate::<usize>(value, Some(1), Some(100_000))
+}
+
+fn validate_time(value: String) -> Result<(), String> {
+    parse_time(&value, Timezone::System)
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Command to run.
+pub enum Command {
+    /// Read thermometers and display temperatures.
+    Run,
+    /// Export recorded readings to stdout.
+    Export {
+        /// Thermometer location or `None` for all locations.
+        location: Option<Location>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        /// Readings resolution or `None` if it should be picked for the range.
+        resolution: Option<Resolution>,
+        format: Format,
+    },
+    /// Import readings from exported CSV files.
+    Import { files: Vec<String> },
+}
+
+impl Command {
+    /// Create `Command` from matched arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `matches` - matched arguments
+    /// * `timezone` - timezone of the date only times
+    fn new(matches: &ArgMatches, timezone: Timezone) -> Command {
+        // It's ok to unwrap all values. If it crashes, it's programmer error in argument definition.
+        match matches.subcommand() {
+            ("export", Some(matches)) => Command::Export {
+                location: matches.value_of("SENSOR").map(|s| s.parse::<Location>().unwrap()),
+                from: matches.value_of("FROM").map(|s| parse_time(s, timezone).unwrap()),
+                to: matches.value_of("TO").map(|s| parse_time(s, timezone).unwrap()),
+                resolution: match matches.value_of("RESOLUTION").unwrap() {
+                    "auto" => None,
+                    s => Some(s.parse::<Resolution>().unwrap()),
+                },
+                format: matches.value_of("FORMAT").unwrap().parse::<Format>().unwrap(),
+            },
+            ("import", Some(matches)) => Command::Import {
+                files: matches.values_of("FILE").unwrap().map(|s| s.to_string()).collect(),
+            },
+            _ => Command::Run,
+        }
+    }
+}
+
 /// Application configuration.
 pub struct Config {
     inside_thermometer_device: String,
     outside_thermometer_device: String,
     temperature_units: Units,
+    locale: Locale,
+    timezone: Timezone,
+    clock_sync_check: SyncCheck,
     max_fps: u64,
+    layout: LayoutMode,
+    display_rotation: DisplayRotation,
+    ui_scale: f64,
+    assets_folder: Option<String>,
+    theme_file: Option<String>,
+    theme_mode: ThemeMode,
+    night_time: NightTime,
+    backlight_path: String,
+    backlight_schedule: BacklightSchedule,
     temperature_interval: u64,
+    temperature_overrun: Overrun,
+    temperature_read_timeout: u64,
+    inside_thermometer_filters: Vec<FilterKind>,
+    outside_thermometer_filters: Vec<FilterKind>,
+    inside_thermometer_precision: usize,
+    outside_thermometer_precision: usize,
+    history_size: usize,
+    trend_window: u64,
+    trend_threshold: f64,
+    extra_sensors: Vec<Sensor>,
+    virtual_sensors: Vec<VirtualSensor>,
+    sensor_labels: Vec<Label>,
+    sensor_order: Vec<Location>,
+    weather_source: Option<WeatherSource>,
+    weather_interval: u64,
+    data_folder: String,
+    retention: Retention,
+    compaction_interval: u64,
+    storage_batch: Batch,
+    log_level: Level,
+    log_target: LogTarget,
+    log_journald_socket: String,
+    log_syslog_address: SyslogAddress,
+    log_format: LogFormat,
+    log_file: Option<String>,
+    log_file_max_size: u64,
+    log_file_rotation: Rotation,
+    log_file_count: usize,
+    command: Command,
 }
 
 impl Config {
@@ -67,41 +315,213 @@ impl Config {
                 Arg::with_name("INSIDE_THERMOMETER")
                     .long("inside-thermometer")
                     .env("INSIDE_THERMOMETER")
-                    .help("Inside W1 thermometer device ID")
+                    .help("Inside W1 thermometer device ID, simulated:PROFILE or remote:URL")
                     .takes_value(true)
                     .required(true)
-                    .default_value("28-000009e8f6e7"),
+                    .default_value("28-000009e8f6e7")
+                    .validator(validate_thermometer_device),
             )
             .arg(
                 Arg::with_name("OUTSIDE_THERMOMETER")
                     .long("outside-thermometer")
                     .env("OUTSIDE_THERMOMETER")
-                    .help("Outside W1 thermometer device ID")
+                    .help("Outside W1 thermometer device ID, simulated:PROFILE or remote:URL")
                     .takes_value(true)
                     .required(true)
-                    .default_value("28-000009d4dffc"),
+                    .default_value("28-000009d4dffc")
+                    .validator(validate_thermometer_device),
             )
             .arg(
                 Arg::with_name("TEMPERATURE_UNITS")
                     .long("temperature-units")
                     .env("TEMPERATURE_UNITS")
-                    .help("Temperature units")
+                    .help(
+                        "Temperature units (initial value, units can be switched at runtime, locale units by default)",
+                    )
                     .takes_value(true)
-                    .required(true)
                     .possible_value(Units::Celsius.as_ref())
                     .possible_value(Units::Fahrenheit.as_ref())
-                    .default_value(Units::Celsius.as_ref()),
+                    .possible_value(Units::Kelvin.as_ref()),
+            )
+            .arg(
+                Arg::with_name("LOCALE")
+                    .long("locale")
+                    .env("LOCALE")
+                    .help("UI locale (en, en-US, cs-CZ or de-DE)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value(Locale::English.as_ref())
+                    .validator(validate_locale),
+            )
+            .arg(
+                Arg::with_name("TIMEZONE")
+                    .long("timezone")
+                    .env("TIMEZONE")
+                    .help("IANA timezone (e.g. Europe/Prague) or system")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value(Timezone::System.as_ref())
+                    .validator(validate_timezone),
+            )
+            .arg(
+                Arg::with_name("CLOCK_SYNC_CHECK")
+                    .long("clock-sync-check")
+                    .env("CLOCK_SYNC_CHECK")
+                    .help("System clock synchronisation check")
+                    .takes_value(true)
+                    .required(true)
+                    .possible_value(SyncCheck::Kernel.as_ref())
+                    .possible_value(SyncCheck::Year.as_ref())
+                    .possible_value(SyncCheck::Off.as_ref())
+                    .default_value(SyncCheck::Year.as_ref()),
             )
             .arg(
                 Arg::with_name("MAX_FPS")
                     .long("max-fps")
                     .env("MAX_FPS")
-                    .help("Max frames per second")
+                    .help("Max frames per second, UI is redrawn only when the readings or the clock change")
                     .takes_value(true)
                     .required(true)
                     .default_value("2")
                     .validator(validate_max_fps),
             )
+            .arg(
+                Arg::with_name("LAYOUT")
+                    .long("layout")
+                    .env("LAYOUT")
+                    .help("Layout mode (big-number shows one sensor temperature per page)")
+                    .takes_value(true)
+                    .required(true)
+                    .possible_value(LayoutMode::Standard.as_ref())
+                    .possible_value(LayoutMode::BigNumber.as_ref())
+                    .default_value(LayoutMode::Standard.as_ref()),
+            )
+            .arg(
+                Arg::with_name("DISPLAY_ROTATION")
+                    .long("display-rotation")
+                    .env("DISPLAY_ROTATION")
+                    .help("Display rotation in degrees (clockwise)")
+                    .takes_value(true)
+                    .required(true)
+                    .possible_value(DisplayRotation::Deg0.as_ref())
+                    .possible_value(DisplayRotation::Deg90.as_ref())
+                    .possible_value(DisplayRotation::Deg180.as_ref())
+                    .possible_value(DisplayRotation::Deg270.as_ref())
+                    .default_value(DisplayRotation::Deg0.as_ref()),
+            )
+            .arg(
+                Arg::with_name("UI_SCALE")
+                    .long("ui-scale")
+                    .env("UI_SCALE")
+                    .help("Additional font scale (applied on top of the window size scaling)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("1.0")
+                    .validator(validate_ui_scale),
+            )
+            .arg(
+                Arg::with_name("ASSETS_FOLDER")
+                    .long("assets-folder")
+                    .env("ASSETS_FOLDER")
+                    .help("Assets (font, logo) folder, searched for if not set, bundled assets are used if not found")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("THEME_FILE")
+                    .long("theme-file")
+                    .env("THEME_FILE")
+                    .help("Theme (colors, font, logo) JSON file path")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("THEME_MODE")
+                    .long("theme-mode")
+                    .env("THEME_MODE")
+                    .help("Theme mode (auto switches between light and dark by local time)")
+                    .takes_value(true)
+                    .required(true)
+                    .possible_value(ThemeMode::Dark.as_ref())
+                    .possible_value(ThemeMode::Light.as_ref())
+                    .possible_value(ThemeMode::Auto.as_ref())
+                    .default_value(ThemeMode::Dark.as_ref()),
+            )
+            .arg(
+                Arg::with_name("NIGHT_START")
+                    .long("night-start")
+                    .env("NIGHT_START")
+                    .help("Dark theme starts at (HH:MM, local time, auto theme mode only)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("21:00")
+                    .validator(validate_time_of_day),
+            )
+            .arg(
+                Arg::with_name("NIGHT_END")
+                    .long("night-end")
+                    .env("NIGHT_END")
+                    .help("Light theme starts at (HH:MM, local time, auto theme mode only)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("07:00")
+                    .validator(validate_time_of_day),
+            )
+            .arg(
+                Arg::with_name("BACKLIGHT_PATH")
+                    .long("backlight-path")
+                    .env("BACKLIGHT_PATH")
+                    .help("Display backlight folder, backlight is not controlled if it doesn't exist")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("/sys/class/backlight/rpi_backlight"),
+            )
+            .arg(
+                Arg::with_name("BACKLIGHT_DAY_BRIGHTNESS")
+                    .long("backlight-day-brightness")
+                    .env("BACKLIGHT_DAY_BRIGHTNESS")
+                    .help("Backlight brightness during the day (0 - 255)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("255")
+                    .validator(validate_brightness),
+            )
+            .arg(
+                Arg::with_name("BACKLIGHT_NIGHT_BRIGHTNESS")
+                    .long("backlight-night-brightness")
+                    .env("BACKLIGHT_NIGHT_BRIGHTNESS")
+                    .help("Backlight brightness at night (0 - 255, NIGHT_START - NIGHT_END)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("64")
+                    .validator(validate_brightness),
+            )
+            .arg(
+                Arg::with_name("BACKLIGHT_BLANK_START")
+                    .long("backlight-blank-start")
+                    .env("BACKLIGHT_BLANK_START")
+                    .help("Display is blanked from (HH:MM, local time), touch wakes it")
+                    .takes_value(true)
+                    .requires("BACKLIGHT_BLANK_END")
+                    .validator(validate_time_of_day),
+            )
+            .arg(
+                Arg::with_name("BACKLIGHT_BLANK_END")
+                    .long("backlight-blank-end")
+                    .env("BACKLIGHT_BLANK_END")
+                    .help("Display is blanked until (HH:MM, local time)")
+                    .takes_value(true)
+                    .requires("BACKLIGHT_BLANK_START")
+                    .validator(validate_time_of_day),
+            )
+            .arg(
+                Arg::with_name("BACKLIGHT_IDLE_TIMEOUT")
+                    .long("backlight-idle-timeout")
+                    .env("BACKLIGHT_IDLE_TIMEOUT")
+                    .help("Display is blanked after this period without touch (s, 0 - never)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("0")
+                    .validator(validate_idle_timeout),
+            )
             .arg(
                 Arg::with_name("TEMPERATURE_INTERVAL")
                     .long("temperature-interval")
@@ -112,25 +532,562 @@ impl Config {
                     .default_value("500")
                     .validator(validate_temperature_interval),
             )
+            .arg(
+                Arg::with_name("TEMPERATURE_OVERRUN")
+                    .long("temperature-overrun")
+                    .env("TEMPERATURE_OVERRUN")
+                    .help("Skip missed readings or take them immediately when reading takes longer than interval")
+                    .takes_value(true)
+                    .required(true)
+                    .possible_value(Overrun::Skip.as_ref())
+                    .possible_value(Overrun::Burst.as_ref())
+                    .default_value(Overrun::Skip.as_ref()),
+            )
+            .arg(
+                Arg::with_name("TEMPERATURE_READ_TIMEOUT")
+                    .long("temperature-read-timeout")
+                    .env("TEMPERATURE_READ_TIMEOUT")
+                    .help("Temperature read is abandoned if it doesn't finish within this timeout (ms), no new read starts until it finishes")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("2000")
+                    .validator(validate_temperature_read_timeout),
+            )
+            .arg(
+                Arg::with_name("INSIDE_THERMOMETER_FILTERS")
+                    .long("inside-thermometer-filters")
+                    .env("INSIDE_THERMOMETER_FILTERS")
+                    .help("Inside thermometer filters (none or comma separated median:N, ema:ALPHA, spike:MAX_RATE)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("spike:10")
+                    .validator(validate_filters),
+            )
+            .arg(
+                Arg::with_name("OUTSIDE_THERMOMETER_FILTERS")
+                    .long("outside-thermometer-filters")
+                    .env("OUTSIDE_THERMOMETER_FILTERS")
+                    .help("Outside thermometer filters (none or comma separated median:N, ema:ALPHA, spike:MAX_RATE)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("spike:10")
+                    .validator(validate_filters),
+            )
+            .arg(
+                Arg::with_name("INSIDE_THERMOMETER_PRECISION")
+                    .long("inside-thermometer-precision")
+                    .env("INSIDE_THERMOMETER_PRECISION")
+                    .help("Number of decimal places displayed for inside temperature")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("1")
+                    .validator(validate_precision),
+            )
+            .arg(
+                Arg::with_name("OUTSIDE_THERMOMETER_PRECISION")
+                    .long("outside-thermometer-precision")
+                    .env("OUTSIDE_THERMOMETER_PRECISION")
+                    .help("Number of decimal places displayed for outside temperature")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("1")
+                    .validator(validate_precision),
+            )
+            .arg(
+                Arg::with_name("HISTORY_SIZE")
+                    .long("history-size")
+                    .env("HISTORY_SIZE")
+                    .help("Number of readings kept in history per thermometer")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("7200")
+                    .validator(validate_history_size),
+            )
+            .arg(
+                Arg::with_name("TREND_WINDOW")
+                    .long("trend-window")
+                    .env("TREND_WINDOW")
+                    .help("Temperature trend is computed from readings in this window (minutes)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("30")
+                    .validator(validate_trend_window),
+            )
+            .arg(
+                Arg::with_name("TREND_THRESHOLD")
+                    .long("trend-threshold")
+                    .env("TREND_THRESHOLD")
+                    .help("Temperature is steady if it changes less than this (°C per hour)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("0.2")
+                    .validator(validate_trend_threshold),
+            )
+            .arg(
+                Arg::with_name("EXTRA_SENSORS")
+                    .long("extra-sensors")
+                    .env("EXTRA_SENSORS")
+                    .help(
+                        "Semicolon separated additional sensors (NAME[(PRECISION)] = W1 device ID, simulated:PROFILE or remote:URL [| FILTERS])",
+                    )
+                    .takes_value(true)
+                    .validator(validate_extra_sensors),
+            )
+            .arg(
+                Arg::with_name("VIRTUAL_SENSORS")
+                    .long("virtual-sensors")
+                    .env("VIRTUAL_SENSORS")
+                    .help("Semicolon separated virtual sensors (NAME[(PRECISION)] = a - b, avg(a, b, ...), min(...) or max(...))")
+                    .takes_value(true)
+                    .validator(validate_virtual_sensors),
+            )
+            .arg(
+                Arg::with_name("SENSOR_LABELS")
+                    .long("sensor-labels")
+                    .env("SENSOR_LABELS")
+                    .help("Semicolon separated sensor labels (LOCATION=NAME[,short:SHORT][,icon:ICON][,room:ROOM])")
+                    .takes_value(true)
+                    .validator(validate_sensor_labels),
+            )
+            .arg(
+                Arg::with_name("SENSOR_ORDER")
+                    .long("sensor-order")
+                    .env("SENSOR_ORDER")
+                    .help("Comma separated sensors in the display order, missing sensors follow")
+                    .takes_value(true)
+                    .validator(validate_sensor_order),
+            )
+            .arg(
+                Arg::with_name("WEATHER_SOURCE")
+                    .long("weather-source")
+                    .env("WEATHER_SOURCE")
+                    .help("Open-Meteo compatible forecast (http://... or file:PATH), forecast is disabled if not set")
+                    .takes_value(true)
+                    .validator(validate_weather_source),
+            )
+            .arg(
+                Arg::with_name("WEATHER_INTERVAL")
+                    .long("weather-interval")
+                    .env("WEATHER_INTERVAL")
+                    .help("Interval in which the weather forecast is fetched (s)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("1800")
+                    .validator(validate_weather_interval),
+            )
+            .arg(
+                Arg::with_name("DATA_FOLDER")
+                    .long("data-folder")
+                    .env("DATA_FOLDER")
+                    .help("Folder where readings are stored")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("/data"),
+            )
+            .arg(
+                Arg::with_name("RAW_RETENTION")
+                    .long("raw-retention")
+                    .env("RAW_RETENTION")
+                    .help("Raw readings are compacted into 1-minute buckets after this period (days)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("7")
+                    .validator(validate_retention),
+            )
+            .arg(
+                Arg::with_name("MINUTE_RETENTION")
+                    .long("minute-retention")
+                    .env("MINUTE_RETENTION")
+                    .help("1-minute buckets are compacted into 1-hour buckets after this period (days)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("90")
+                    .validator(validate_retention),
+            )
+            .arg(
+                Arg::with_name("COMPACTION_INTERVAL")
+                    .long("compaction-interval")
+                    .env("COMPACTION_INTERVAL")
+                    .help("Interval in which the stored readings are compacted (s)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("3600")
+                    .validator(validate_compaction_interval),
+            )
+            .arg(
+                Arg::with_name("STORAGE_FLUSH_INTERVAL")
+                    .long("storage-flush-interval")
+                    .env("STORAGE_FLUSH_INTERVAL")
+                    .help("Buffered readings are written to the storage at least this often (s)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("60")
+                    .validator(validate_storage_flush_interval),
+            )
+            .arg(
+                Arg::with_name("STORAGE_FLUSH_SIZE")
+                    .long("storage-flush-size")
+                    .env("STORAGE_FLUSH_SIZE")
+                    .help("Buffered readings are written to the storage when there's this number of them")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("1000")
+                    .validator(validate_storage_flush_size),
+            )
+            .arg(
+                Arg::with_name("LOG_LEVEL")
+                    .long("log-level")
+                    .env("LOG_LEVEL")
+                    .help("Log level")
+                    .takes_value(true)
+                    .required(true)
+                    .possible_values(&["critical", "error", "warning", "info", "debug", "trace"])
+                    .default_value("info"),
+            )
+            .arg(
+                Arg::with_name("LOG_TARGET")
+                    .long("log-target")
+                    .env("LOG_TARGET")
+                    .help("Log target (standard output / error or log file, journald or syslog)")
+                    .takes_value(true)
+                    .required(true)
+                    .possible_value(LogTarget::Standard.as_ref())
+                    .possible_value(LogTarget::Journald.as_ref())
+                    .possible_value(LogTarget::Syslog.as_ref())
+                    .default_value(LogTarget::Standard.as_ref()),
+            )
+            .arg(
+                Arg::with_name("LOG_JOURNALD_SOCKET")
+                    .long("log-journald-socket")
+                    .env("LOG_JOURNALD_SOCKET")
+                    .help("Journald native protocol socket")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value(DEFAULT_JOURNALD_SOCKET),
+            )
+            .arg(
+                Arg::with_name("LOG_SYSLOG_ADDRESS")
+                    .long("log-syslog-address")
+                    .env("LOG_SYSLOG_ADDRESS")
+                    .help("Syslog server address (udp://host:port or tcp://host:port)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("udp://127.0.0.1:514")
+                    .validator(validate_syslog_address),
+            )
+            .arg(
+                Arg::with_name("LOG_FORMAT")
+                    .long("log-format")
+                    .env("LOG_FORMAT")
+                    .help("Log format (term goes to stderr, json to stdout, unless log file is set)")
+                    .takes_value(true)
+                    .required(true)
+                    .possible_value(LogFormat::Term.as_ref())
+                    .possible_value(LogFormat::Json.as_ref())
+                    .default_value(LogFormat::Term.as_ref()),
+            )
+            .arg(
+                Arg::with_name("LOG_FILE")
+                    .long("log-file")
+                    .env("LOG_FILE")
+                    .help("Log file path")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("LOG_FILE_MAX_SIZE")
+                    .long("log-file-max-size")
+                    .env("LOG_FILE_MAX_SIZE")
+                    .help("Log file is rotated when it exceeds this size (bytes)")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("10485760")
+                    .validator(validate_log_file_max_size),
+            )
+            .arg(
+                Arg::with_name("LOG_FILE_ROTATION")
+                    .long("log-file-rotation")
+                    .env("LOG_FILE_ROTATION")
+                    .help("Time based log file rotation")
+                    .takes_value(true)
+                    .required(true)
+                    .possible_value(Rotation::Never.as_ref())
+                    .possible_value(Rotation::Hourly.as_ref())
+                    .possible_value(Rotation::Daily.as_ref())
+                    .default_value(Rotation::Daily.as_ref()),
+            )
+            .arg(
+                Arg::with_name("LOG_FILE_COUNT")
+                    .long("log-file-count")
+                    .env("LOG_FILE_COUNT")
+                    .help("Number of rotated log files to keep")
+                    .takes_value(true)
+                    .required(true)
+                    .default_value("5")
+                    .validator(validate_log_file_count),
+            )
+            .subcommand(
+                SubCommand::with_name("export")
+                    .about("Export recorded readings to stdout")
+                    .arg(
+                        Arg::with_name("SENSOR")
+                            .long("sensor")
+                            .help("Thermometer location or virtual sensor name (all if not provided)")
+                            .takes_value(true)
+                            .validator(validate_location),
+                    )
+                    .arg(
+                        Arg::with_name("FROM")
+                            .long("from")
+                            .help("Export readings taken at or after this time (RFC3339 or YYYY-MM-DD)")
+                            .takes_value(true)
+                            .validator(validate_time),
+                    )
+                    .arg(
+                        Arg::with_name("TO")
+                            .long("to")
+                            .help("Export readings taken at or before this time (RFC3339 or YYYY-MM-DD)")
+                            .takes_value(true)
+                            .validator(validate_time),
+                    )
+                    .arg(
+                        Arg::with_name("RESOLUTION")
+                            .long("resolution")
+                            .help("Readings resolution (auto picks the finest one stored for the whole range)")
+                            .takes_value(true)
+                            .required(true)
+                            .possible_value("auto")
+                            .possible_value(Resolution::Raw.as_ref())
+                            .possible_value(Resolution::Minute.as_ref())
+                            .possible_value(Resolution::Hour.as_ref())
+                            .default_value("auto"),
+                    )
+                    .arg(
+                        Arg::with_name("FORMAT")
+                            .long("format")
+                            .help("Output format")
+                            .takes_value(true)
+                            .required(true)
+                            .possible_value(Format::Csv.as_ref())
+                            .possible_value(Format::JsonLines.as_ref())
+                            .default_value(Format::Csv.as_ref()),
+                    ),
+            )
+            .subcommand(
+                SubCommand::with_name("import")
+                    .about("Import readings from exported CSV files")
+                    .arg(Arg::with_name("FILE").help("CSV file").required(true).multiple(true)),
+            )
             .get_matches();
 
         // It's ok to unwrap all values. If it crashes, it's programmer error in argument definition.
         let inside_thermometer_device = matches.value_of("INSIDE_THERMOMETER").unwrap().to_string();
         let outside_thermometer_device = matches.value_of("OUTSIDE_THERMOMETER").unwrap().to_string();
-        let temperature_units = matches.value_of("TEMPERATURE_UNITS").unwrap().parse::<Units>().unwrap();
+        let locale = matches.value_of("LOCALE").unwrap().parse::<Locale>().unwrap();
+        let temperature_units = matches
+            .value_of("TEMPERATURE_UNITS")
+            .map(|s| s.parse::<Units>().unwrap())
+            .unwrap_or_else(|| locale.units());
         let max_fps = matches.value_of("MAX_FPS").unwrap().parse::<u64>().unwrap();
+        let layout = matches.value_of("LAYOUT").unwrap().parse::<LayoutMode>().unwrap();
+        let display_rotation = matches
+            .value_of("DISPLAY_ROTATION")
+            .unwrap()
+            .parse::<DisplayRotation>()
+            .unwrap();
+        let ui_scale = matches.value_of("UI_SCALE").unwrap().parse::<f64>().unwrap();
+        let assets_folder = matches.value_of("ASSETS_FOLDER").map(|s| s.to_string());
+        let theme_file = matches.value_of("THEME_FILE").map(|s| s.to_string());
+        let theme_mode = matches.value_of("THEME_MODE").unwrap().parse::<ThemeMode>().unwrap();
+        let night_time = NightTime {
+            start: parse_time_of_day(matches.value_of("NIGHT_START").unwrap()).unwrap(),
+            end: parse_time_of_day(matches.value_of("NIGHT_END").unwrap()).unwrap(),
+        };
+        let backlight_path = matches.value_of("BACKLIGHT_PATH").unwrap().to_string();
+        let backlight_schedule = BacklightSchedule {
+            day_brightness: matches
+                .value_of("BACKLIGHT_DAY_BRIGHTNESS")
+                .unwrap()
+                .parse::<u8>()
+                .unwrap(),
+            night_brightness: matches
+                .value_of("BACKLIGHT_NIGHT_BRIGHTNESS")
+                .unwrap()
+                .parse::<u8>()
+                .unwrap(),
+            night: night_time,
+            blank: match (
+                matches.value_of("BACKLIGHT_BLANK_START"),
+                matches.value_of("BACKLIGHT_BLANK_END"),
+            ) {
+                (Some(start), Some(end)) => Some(NightTime {
+                    start: parse_time_of_day(start).unwrap(),
+                    end: parse_time_of_day(end).unwrap(),
+                }),
+                _ => None,
+            },
+            idle_timeout: match matches
+                .value_of("BACKLIGHT_IDLE_TIMEOUT")
+                .unwrap()
+                .parse::<u64>()
+                .unwrap()
+            {
+                0 => None,
+                seconds => Some(StdDuration::from_secs(seconds)),
+            },
+        };
         let temperature_interval = matches
             .value_of("TEMPERATURE_INTERVAL")
             .unwrap()
             .parse::<u64>()
             .unwrap();
+        let temperature_overrun = matches
+            .value_of("TEMPERATURE_OVERRUN")
+            .unwrap()
+            .parse::<Overrun>()
+            .unwrap();
+        let temperature_read_timeout = matches
+            .value_of("TEMPERATURE_READ_TIMEOUT")
+            .unwrap()
+            .parse::<u64>()
+            .unwrap();
+        let inside_thermometer_filters =
+            parse_filters(matches.value_of("INSIDE_THERMOMETER_FILTERS").unwrap()).unwrap();
+        let outside_thermometer_filters =
+            parse_filters(matches.value_of("OUTSIDE_THERMOMETER_FILTERS").unwrap()).unwrap();
+        let inside_thermometer_precision = matches
+            .value_of("INSIDE_THERMOMETER_PRECISION")
+            .unwrap()
+            .parse::<usize>()
+            .unwrap();
+        let outside_thermometer_precision = matches
+            .value_of("OUTSIDE_THERMOMETER_PRECISION")
+            .unwrap()
+            .parse::<usize>()
+            .unwrap();
+        let history_size = matches.value_of("HISTORY_SIZE").unwrap().parse::<usize>().unwrap();
+        let trend_window = matches.value_of("TREND_WINDOW").unwrap().parse::<u64>().unwrap();
+        let trend_threshold = matches.value_of("TREND_THRESHOLD").unwrap().parse::<f64>().unwrap();
+        let extra_sensors = matches
+            .value_of("EXTRA_SENSORS")
+            .map(|s| parse_sensors(s).unwrap())
+            .unwrap_or_default();
+        let virtual_sensors = matches
+            .value_of("VIRTUAL_SENSORS")
+            .map(|s| parse_virtual_sensors(s).unwrap())
+            .unwrap_or_default();
+        let mut sensors = vec![Location::Inside, Location::Outside];
+        sensors.extend(extra_sensors.iter().map(|s| s.location));
+        if let Err(e) = check_operands(&virtual_sensors, &sensors) {
+            clap::Error::with_description(&e.to_string(), clap::ErrorKind::ValueValidation).exit();
+        }
+        let sensor_labels = matches
+            .value_of("SENSOR_LABELS")
+            .map(|s| parse_labels(s).unwrap())
+            .unwrap_or_default();
+        let sensor_order = matches
+            .value_of("SENSOR_ORDER")
+            .map(|s| parse_order(s).unwrap())
+            .unwrap_or_default();
+        sensors.extend(virtual_sensors.iter().map(|s| s.location));
+        if let Err(e) = check_sensors(&sensor_labels, &sensor_order, &sensors) {
+            clap::Error::with_description(&e.to_string(), clap::ErrorKind::ValueValidation).exit();
+        }
+        let weather_source = matches
+            .value_of("WEATHER_SOURCE")
+            .map(|s| s.parse::<WeatherSource>().unwrap());
+        let weather_interval = matches.value_of("WEATHER_INTERVAL").unwrap().parse::<u64>().unwrap();
+        let data_folder = matches.value_of("DATA_FOLDER").unwrap().to_string();
+        let retention = Retention {
+            raw: Duration::days(matches.value_of("RAW_RETENTION").unwrap().parse::<i64>().unwrap()),
+            minute: Duration::days(matches.value_of("MINUTE_RETENTION").unwrap().parse::<i64>().unwrap()),
+        };
+        let compaction_interval = matches.value_of("COMPACTION_INTERVAL").unwrap().parse::<u64>().unwrap();
+        let storage_batch = Batch {
+            size: matches
+                .value_of("STORAGE_FLUSH_SIZE")
+                .unwrap()
+                .parse::<usize>()
+                .unwrap(),
+            interval: StdDuration::from_secs(
+                matches
+                    .value_of("STORAGE_FLUSH_INTERVAL")
+                    .unwrap()
+                    .parse::<u64>()
+                    .unwrap(),
+            ),
+        };
+        let log_level = matches.value_of("LOG_LEVEL").unwrap().parse::<Level>().unwrap();
+        let log_target = matches.value_of("LOG_TARGET").unwrap().parse::<LogTarget>().unwrap();
+        let log_journald_socket = matches.value_of("LOG_JOURNALD_SOCKET").unwrap().to_string();
+        let log_syslog_address = matches
+            .value_of("LOG_SYSLOG_ADDRESS")
+            .unwrap()
+            .parse::<SyslogAddress>()
+            .unwrap();
+        let log_format = matches.value_of("LOG_FORMAT").unwrap().parse::<LogFormat>().unwrap();
+        let log_file = matches.value_of("LOG_FILE").map(|s| s.to_string());
+        let log_file_max_size = matches.value_of("LOG_FILE_MAX_SIZE").unwrap().parse::<u64>().unwrap();
+        let log_file_rotation = matches
+            .value_of("LOG_FILE_ROTATION")
+            .unwrap()
+            .parse::<Rotation>()
+            .unwrap();
+        let log_file_count = matches.value_of("LOG_FILE_COUNT").unwrap().parse::<usize>().unwrap();
+        let timezone = matches.value_of("TIMEZONE").unwrap().parse::<Timezone>().unwrap();
+        let clock_sync_check = matches
+            .value_of("CLOCK_SYNC_CHECK")
+            .unwrap()
+            .parse::<SyncCheck>()
+            .unwrap();
+        let command = Command::new(&matches, timezone);
 
         Config {
             inside_thermometer_device,
             outside_thermometer_device,
             temperature_units,
+            locale,
+            timezone,
+            clock_sync_check,
             max_fps,
+            layout,
+            display_rotation,
+            ui_scale,
+            assets_folder,
+            theme_file,
+            theme_mode,
+            night_time,
+            backlight_path,
+            backlight_schedule,
             temperature_interval,
+            temperature_overrun,
+            temperature_read_timeout,
+            inside_thermometer_filters,
+            outside_thermometer_filters,
+            inside_thermometer_precision,
+            outside_thermometer_precision,
+            history_size,
+            trend_window,
+            trend_threshold,
+            extra_sensors,
+            virtual_sensors,
+            sensor_labels,
+            sensor_order,
+            weather_source,
+            weather_interval,
+            data_folder,
+            retention,
+            compaction_interval,
+            storage_batch,
+            log_level,
+            log_target,
+            log_journald_socket,
+            log_syslog_address,
+            log_format,
+            log_file,
+            log_file_max_size,
+            log_file_rotation,
+            log_file_count,
+            command,
         }
     }
 
@@ -144,23 +1101,307 @@ impl Config {
         &self.outside_thermometer_device
     }
 
-    /// Temperature units.
+    /// Initial temperature units (persisted units take precedence).
     pub fn temperature_units(&self) -> Units {
         self.temperature_units
     }
 
+    /// UI locale.
+    pub fn locale(&self) -> Locale {
+        self.locale
+    }
+
+    /// Timezone of the displayed time.
+    pub fn timezone(&self) -> Timezone {
+        self.timezone
+    }
+
+    /// System clock synchronisation check.
+    pub fn clock_sync_check(&self) -> SyncCheck {
+        self.clock_sync_check
+    }
+
     /// Max frames per second.
     pub fn max_fps(&self) -> u64 {
         self.max_fps
     }
 
+    /// Layout mode.
+    pub fn layout(&self) -> LayoutMode {
+        self.layout
+    }
+
+    /// Display rotation.
+    pub fn display_rotation(&self) -> DisplayRotation {
+        self.display_rotation
+    }
+
+    /// Additional UI (font) scale.
+    pub fn ui_scale(&self) -> f64 {
+        self.ui_scale
+    }
+
+    /// Assets folder, `None` if it should be searched for.
+    pub fn assets_folder(&self) -> Option<&str> {
+        self.assets_folder.as_ref().map(String::as_str)
+    }
+
+    /// Theme file path, default theme is used if not set.
+    pub fn theme_file(&self) -> Option<&str> {
+        self.theme_file.as_ref().map(String::as_str)
+    }
+
+    /// Theme mode.
+    pub fn theme_mode(&self) -> ThemeMode {
+        self.theme_mode
+    }
+
+    /// Night time range for the auto theme mode.
+    pub fn night_time(&self) -> NightTime {
+        self.night_time
+    }
+
+    /// Display backlight folder.
+    pub fn backlight_path(&self) -> &str {
+        &self.backlight_path
+    }
+
+    /// Display backlight schedule.
+    pub fn backlight_schedule(&self) -> &BacklightSchedule {
+        &self.backlight_schedule
+    }
+
     /// Interval in which temperatures are read from sensors (ms)
     pub fn temperature_interval(&self) -> u64 {
         self.temperature_interval
     }
+
+    /// What to do when temperature reading takes longer than interval.
+    pub fn temperature_overrun(&self) -> Overrun {
+        self.temperature_overrun
+    }
+
+    /// Temperature read timeout (ms)
+    pub fn temperature_read_timeout(&self) -> u64 {
+        self.temperature_read_timeout
+    }
+
+    /// Inside thermometer filters.
+    pub fn inside_thermometer_filters(&self) -> &[FilterKind] {
+        &self.inside_thermometer_filters
+    }
+
+    /// Outside thermometer filters.
+    pub fn outside_thermometer_filters(&self) -> &[FilterKind] {
+        &self.outside_thermometer_filters
+    }
+
+    /// Number of decimal places displayed for the sensor.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - sensor location
+    pub fn sensor_precision(&self, location: Location) -> usize {
+        match location {
+            Location::Inside => self.inside_thermometer_precision,
+            Location::Outside => self.outside_thermometer_precision,
+            Location::Named(_) => named_sensor_precision(location, &self.extra_sensors, &self.virtual_sensors),
+        }
+    }
+
+    /// Number of readings kept in history per thermometer.
+    pub fn history_size(&self) -> usize {
+        self.history_size
+    }
+
+    /// Temperature trend window (minutes).
+    pub fn trend_window(&self) -> u64 {
+        self.trend_window
+    }
+
+    /// Temperature trend threshold (°C per hour).
+    pub fn trend_threshold(&self) -> f64 {
+        self.trend_threshold
+    }
+
+    /// Additional sensors, in the order in which they are defined.
+    pub fn extra_sensors(&self) -> &[Sensor] {
+        &self.extra_sensors
+    }
+
+    /// Virtual sensors, in the order in which they are defined.
+    pub fn virtual_sensors(&self) -> &[VirtualSensor] {
+        &self.virtual_sensors
+    }
+
+    /// Sensor label, default label if the sensor is not labeled.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - sensor location
+    pub fn sensor_label(&self, location: Location) -> Label {
+        self.sensor_labels
+            .iter()
+            .find(|l| l.location == location)
+            .cloned()
+            .unwrap_or_else(|| Label::default_for(location, self.locale))
+    }
+
+    /// Sensors in the display order, sensors missing in the order follow.
+    pub fn sensor_order(&self) -> &[Location] {
+        &self.sensor_order
+    }
+
+    /// Weather forecast source, `None` if the forecast is disabled.
+    pub fn weather_source(&self) -> Option<&WeatherSource> {
+        self.weather_source.as_ref()
+    }
+
+    /// Interval in which the weather forecast is fetched (s).
+    pub fn weather_interval(&self) -> u64 {
+        self.weather_interval
+    }
+
+    /// Folder where readings are stored.
+    pub fn data_folder(&self) -> &str {
+        &self.data_folder
+    }
+
+    /// Readings retention periods.
+    pub fn retention(&self) -> &Retention {
+        &self.retention
+    }
+
+    /// Interval in which the stored readings are compacted (s).
+    pub fn compaction_interval(&self) -> u64 {
+        self.compaction_interval
+    }
+
+    /// Storage write batching.
+    pub fn storage_batch(&self) -> Batch {
+        self.storage_batch
+    }
+
+    /// Log level.
+    pub fn log_level(&self) -> Level {
+        self.log_level
+    }
+
+    /// Log target.
+    pub fn log_target(&self) -> LogTarget {
+        self.log_target
+    }
+
+    /// Journald native protocol socket.
+    pub fn log_journald_socket(&self) -> &str {
+        &self.log_journald_socket
+    }
+
+    /// Syslog server address.
+    pub fn log_syslog_address(&self) -> &SyslogAddress {
+        &self.log_syslog_address
+    }
+
+    /// Log format.
+    pub fn log_format(&self) -> LogFormat {
+        self.log_format
+    }
+
+    /// Log file path or `None` if logs should not be written into a file.
+    pub fn log_file(&self) -> Option<&str> {
+        self.log_file.as_ref().map(String::as_str)
+    }
+
+    /// Log file is rotated when it exceeds this size (bytes).
+    pub fn log_file_max_size(&self) -> u64 {
+        self.log_file_max_size
+    }
+
+    /// Time based log file rotation.
+    pub fn log_file_rotation(&self) -> Rotation {
+        self.log_file_rotation
+    }
+
+    /// Number of rotated log files to keep.
+    pub fn log_file_count(&self) -> usize {
+        self.log_file_count
+    }
+
+    /// Command to run.
+    pub fn command(&self) -> &Command {
+        &self.command
+    }
+}
+
+/// Number of decimal places displayed for the additional or virtual sensor,
+/// `DEFAULT_SENSOR_PRECISION` if the sensor doesn't set it.
+///
+/// # Arguments
+///
+/// * `location` - sensor location
+/// * `sensors` - additional sensors
+/// * `virtual_sensors` - virtual sensors
+fn named_sensor_precision(location: Location, sensors: &[Sensor], virtual_sensors: &[VirtualSensor]) -> usize {
+    let sensor = sensors.iter().find(|s| s.location == location).map(|s| s.precision);
+    let virtual_sensor = virtual_sensors
+        .iter()
+        .find(|s| s.location == location)
+        .map(|s| s.precision);
+
+    sensor
+        .or(virtual_sensor)
+        .and_then(|p| p)
+        .unwrap_or(DEFAULT_SENSOR_PRECISION)
 }
 
 lazy_static! {
     /// Shared configuration.
     pub static ref CONFIG: Config = Config::new();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{named_sensor_precision, validate, DEFAULT_SENSOR_PRECISION};
+    use derived::parse_virtual_sensors;
+    use processing::parse_sensors;
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(validate::<f64>("1.5".to_string(), Some(0.25), Some(4.0)), Ok(()));
+        assert_eq!(
+            validate::<f64>("large".to_string(), Some(0.25), Some(4.0)),
+            Err("Value must be a number: large".to_string())
+        );
+        assert_eq!(
+            validate::<f64>("5".to_string(), Some(0.25), Some(4.0)),
+            Err("Value must not be greater than 4".to_string())
+        );
+        assert_eq!(
+            validate::<u64>("1.5".to_string(), Some(1), None),
+            Err("Value must be a non-negative integer: 1.5".to_string())
+        );
+        assert_eq!(
+            validate::<i64>("x".to_string(), Some(1), None),
+            Err("Value must be an integer: x".to_string())
+        );
+    }
+
+    #[test]
+    fn test_named_sensor_precision() {
+        let sensors = parse_sensors("attic(2) = simulated:sine; garage = simulated:walk").unwrap();
+        let virtual_sensors = parse_virtual_sensors("delta(0) = inside - attic; room = avg(inside, attic)").unwrap();
+        let precision = |i: usize| {
+            let location = if i < 2 {
+                sensors[i].location
+            } else {
+                virtual_sensors[i - 2].location
+            };
+            named_sensor_precision(location, &sensors, &virtual_sensors)
+        };
+
+        assert_eq!(precision(0), 2);
+        assert_eq!(precision(1), DEFAULT_SENSOR_PRECISION);
+        assert_eq!(precision(2), 0);
+        assert_eq!(precision(3), DEFAULT_SENSOR_PRECISION);
+    }
+}
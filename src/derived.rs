@@ -0,0 +1,307 @@
+//! Derived (virtual) sensors.
+//!
+//! Virtual sensor temperature is computed from readings of other sensors. Sensors
+//! are defined as `NAME[(PRECISION)] = EXPRESSION`, where the expression is one of:
+//!
+//! * `a - b` - temperature difference
+//! * `avg(a, b, ...)` - average temperature
+//! * `min(a, b, ...)` - lowest temperature
+//! * `max(a, b, ...)` - highest temperature
+//!
+//! Operands are sensors (`inside`, `outside`, additional sensors) or virtual sensors
+//! defined before the one being defined.
+use error::Error;
+use processing::parse_sensor_name;
+use state::{Location, Reading, Status};
+use std::str::FromStr;
+use w1::thermometer::Temperature;
+
+/// Virtual sensor expression.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Expression {
+    /// First operand minus the second one.
+    Difference(Location, Location),
+    /// Average of all operands.
+    Average(Vec<Location>),
+    /// Lowest operand.
+    Min(Vec<Location>),
+    /// Highest operand.
+    Max(Vec<Location>),
+}
+
+impl Expression {
+    /// Sensors the expression depends on.
+    pub fn operands(&self) -> Vec<Location> {
+        match self {
+            Expression::Difference(a, b) => vec![*a, *b],
+            Expression::Average(operands) | Expression::Min(operands) | Expression::Max(operands) => operands.clone(),
+        }
+    }
+
+    /// `true` if the expression is a temperature difference.
+    pub fn is_difference(&self) -> bool {
+        match self {
+            Expression::Difference(..) => true,
+            _ => false,
+        }
+    }
+
+    /// Combine operand values (millidegrees celsius).
+    fn combine(&self, values: &[i64]) -> i64 {
+        match self {
+            Expression::Difference(..) => values[0] - values[1],
+            Expression::Average(..) => {
+                let sum: i64 = values.iter().sum();
+                (sum as f64 / values.len() as f64).round() as i64
+            }
+            Expression::Min(..) => values.iter().cloned().min().unwrap(),
+            Expression::Max(..) => values.iter().cloned().max().unwrap(),
+        }
+    }
+
+    /// Evaluate expression, returns `None` if any operand has no reading yet.
+    ///
+    /// Derived reading is only as recent as the oldest operand reading and it's
+    /// rejected if any operand reading was rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `reading` - returns the last reading of the sensor
+    pub fn evaluate<F>(&self, reading: F) -> Option<Reading>
+    where
+        F: Fn(Location) -> Option<Reading>,
+    {
+        let readings = self
+            .operands()
+            .into_iter()
+            .map(reading)
+            .collect::<Option<Vec<Reading>>>()?;
+
+        let raw: Vec<i64> = readings.iter().map(|r| r.raw.value()).collect();
+        let filtered: Vec<i64> = readings.iter().map(|r| r.filtered.value()).collect();
+
+        Some(Reading {
+            timestamp: readings.iter().map(|r| r.timestamp).min()?,
+            raw: Temperature::new(self.combine(&raw)),
+            filtered: Temperature::new(self.combine(&filtered)),
+            status: if readings.iter().any(|r| r.status == Status::Rejected) {
+                Status::Rejected
+            } else {
+                Status::Ok
+            },
+        })
+    }
+}
+
+impl FromStr for Expression {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || Error::from(format!("Invalid virtual sensor expression: {}", s));
+
+        if let Some(index) = s.find('(') {
+            let function = s[..index].trim();
+            if !s.ends_with(')') {
+                return Err(invalid());
+            }
+            let arguments = &s[index + 1..s.len() - 1];
+            let operands = arguments
+                .split(',')
+                .map(|a| a.trim().parse::<Location>())
+                .collect::<Result<Vec<Location>, Error>>()?;
+
+            return match function {
+                "avg" => Ok(Expression::Average(operands)),
+                "min" => Ok(Expression::Min(operands)),
+                "max" => Ok(Expression::Max(operands)),
+                _ => Err(invalid()),
+            };
+        }
+
+        let mut parts = s.splitn(2, '-');
+        let a = parts.next().unwrap_or("").trim();
+        let b = parts.next().ok_or_else(invalid)?.trim();
+        Ok(Expression::Difference(a.parse()?, b.parse()?))
+    }
+}
+
+/// Virtual sensor definition.
+#[derive(PartialEq, Debug, Clone)]
+pub struct VirtualSensor {
+    /// Virtual sensor location (name).
+    pub location: Location,
+    /// Expression computing the temperature.
+    pub expression: Expression,
+    /// Number of displayed decimal places or `None` for the default one.
+    pub precision: Option<usize>,
+}
+
+impl FromStr for VirtualSensor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '=');
+        let name = parts.next().unwrap_or("").trim();
+        let expression = parts.next().ok_or_else(|| {
+            Error::from(format!(
+                "Invalid virtual sensor, expected NAME[(PRECISION)] = EXPRESSION: {}",
+                s
+            ))
+        })?;
+
+        match parse_sensor_name(name)? {
+            (location @ Location::Named(_), precision) => Ok(VirtualSensor {
+                location,
+                expression: expression.parse()?,
+                precision,
+            }),
+            _ => Err(Error::from(format!("Invalid virtual sensor name: {}", name))),
+        }
+    }
+}
+
+/// Parse semicolon separated virtual sensor definitions.
+///
+/// # Arguments
+///
+/// * `s` - definitions, e.g. `delta = inside - outside; room = avg(inside, delta)`
+pub fn parse_virtual_sensors(s: &str) -> Result<Vec<VirtualSensor>, Error> {
+    let mut sensors: Vec<VirtualSensor> = vec![];
+
+    for definition in s.split(';').filter(|d| !d.trim().is_empty()) {
+        let sensor = definition.parse::<VirtualSensor>()?;
+
+        if sensors.iter().any(|s| s.location == sensor.location) {
+            return Err(Error::from(format!(
+                "Duplicate virtual sensor: {}",
+                sensor.location.as_ref()
+            )));
+        }
+
+        sensors.push(sensor);
+    }
+
+    Ok(sensors)
+}
+
+/// Check that every operand is a sensor or a virtual sensor defined before.
+///
+/// # Arguments
+///
+/// * `virtual_sensors` - virtual sensors in the order in which they are defined
+/// * `sensors` - sensors which are read from thermometers
+pub fn check_operands(virtual_sensors: &[VirtualSensor], sensors: &[Location]) -> Result<(), Error> {
+    for (index, sensor) in virtual_sensors.iter().enumerate() {
+        if sensors.contains(&sensor.location) {
+            return Err(Error::from(format!(
+                "Virtual sensor {} is already a sensor",
+                sensor.location.as_ref()
+            )));
+        }
+
+        let known = |location: &Location| {
+            sensors.contains(location) || virtual_sensors[..index].iter().any(|s| s.location == *location)
+        };
+        if let Some(unknown) = sensor.expression.operands().iter().find(|l| !known(l)) {
+            return Err(Error::from(format!(
+                "Unknown sensor {} in virtual sensor {}",
+                unknown.as_ref(),
+                sensor.location.as_ref()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{check_operands, parse_virtual_sensors, Expression};
+    use chrono::{Duration, TimeZone, Utc};
+    use state::{Location, Reading, Status};
+    use w1::thermometer::Temperature;
+
+    fn reading(seconds: i64, value: i64, status: Status) -> Reading {
+        Reading {
+            timestamp: Utc.ymd(2018, 7, 1).and_hms(12, 0, 0) + Duration::seconds(seconds),
+            raw: Temperature::new(value + 100),
+            filtered: Temperature::new(value),
+            status,
+        }
+    }
+
+    #[test]
+    fn test_parse_virtual_sensors() {
+        let sensors = parse_virtual_sensors("delta = inside - outside; room(2)=avg(inside, outside, delta);").unwrap();
+
+        assert_eq!(sensors.len(), 2);
+        assert_eq!(sensors[0].location.as_ref(), "delta");
+        assert_eq!(
+            sensors[0].expression,
+            Expression::Difference(Location::Inside, Location::Outside)
+        );
+        assert_eq!(
+            sensors[1].expression,
+            Expression::Average(vec![Location::Inside, Location::Outside, sensors[0].location])
+        );
+        assert_eq!(sensors[0].precision, None);
+        assert_eq!(sensors[1].location.as_ref(), "room");
+        assert_eq!(sensors[1].precision, Some(2));
+
+        assert!(parse_virtual_sensors("").unwrap().is_empty());
+        assert!(parse_virtual_sensors("inside = min(inside, outside)").is_err());
+        assert!(parse_virtual_sensors("a = median(inside, outside)").is_err());
+        assert!(parse_virtual_sensors("a = max(inside, outside").is_err());
+        assert!(parse_virtual_sensors("a = inside + outside").is_err());
+        assert!(parse_virtual_sensors("a = inside - outside; a = outside - inside").is_err());
+        assert!(parse_virtual_sensors("a(5) = inside - outside").is_err());
+    }
+
+    #[test]
+    fn test_check_operands() {
+        let sensors = [Location::Inside, Location::Outside, "attic".parse().unwrap()];
+        let check = |s: &str| check_operands(&parse_virtual_sensors(s).unwrap(), &sensors);
+
+        assert!(check("a = attic - outside; b = avg(a, inside)").is_ok());
+        assert!(check("a = max(inside, b); b = min(inside, outside)").is_err());
+        assert!(check("a = max(inside, garage)").is_err());
+        assert!(check("attic = max(inside, outside)").is_err());
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let inside = reading(0, 21_000, Status::Ok);
+        let outside = reading(-1, -4_500, Status::Ok);
+        let sensor = |location: Location| match location {
+            Location::Inside => Some(inside),
+            Location::Outside => Some(outside),
+            _ => None,
+        };
+
+        let delta = Expression::Difference(Location::Inside, Location::Outside)
+            .evaluate(sensor)
+            .unwrap();
+        assert_eq!(delta.filtered, Temperature::new(25_500));
+        assert_eq!(delta.raw, Temperature::new(25_500));
+        assert_eq!(delta.timestamp, outside.timestamp);
+        assert_eq!(delta.status, Status::Ok);
+
+        let operands = vec![Location::Inside, Location::Outside];
+        let avg = Expression::Average(operands.clone()).evaluate(sensor).unwrap();
+        assert_eq!(avg.filtered, Temperature::new(8_250));
+        let min = Expression::Min(operands.clone()).evaluate(sensor).unwrap();
+        assert_eq!(min.filtered, Temperature::new(-4_500));
+        let max = Expression::Max(operands).evaluate(sensor).unwrap();
+        assert_eq!(max.filtered, Temperature::new(21_000));
+
+        let missing = "missing".parse::<Location>().unwrap();
+        assert!(Expression::Max(vec![Location::Inside, missing])
+            .evaluate(sensor)
+            .is_none());
+
+        let rejected = |_| Some(reading(0, 0, Status::Rejected));
+        let max = Expression::Max(vec![Location::Inside]).evaluate(rejected).unwrap();
+        assert_eq!(max.status, Status::Rejected);
+    }
+}
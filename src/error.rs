@@ -1,27 +1,120 @@
 use std;
 use std::fmt::{Display, Formatter};
-use std::io::Error as IOError;
+use std::io::{Error as IOError, ErrorKind};
+use std::sync::Arc;
 
 #[derive(Debug, Clone)]
-pub struct Error {
-    msg: String,
+pub enum Error {
+    /// W1 device doesn't exist (device path).
+    DeviceNotFound(String),
+    /// Device reported CRC mismatch.
+    CrcMismatch,
+    /// Device returned power on reset value (t=85000), conversion didn't happen.
+    PowerOnReset,
+    /// Device returned invalid value (t=-1 when the conversion failed, ...).
+    InvalidReading(i64),
+    /// Unable to parse data (device output, stored records, arguments, ...).
+    Parse(String),
+    /// I/O error.
+    Io(Arc<IOError>),
+    /// Operation timed out.
+    Timeout,
+    /// Error with additional context.
+    Context { msg: String, source: Box<Error> },
+    /// Other error.
+    Other(String),
+}
+
+impl Error {
+    /// Wrap error with additional context.
+    ///
+    /// # Arguments
+    ///
+    /// * `msg` - context message
+    pub fn context<S>(self, msg: S) -> Error
+    where
+        S: Into<String>,
+    {
+        Error::Context {
+            msg: msg.into(),
+            source: Box::new(self),
+        }
+    }
+
+    /// Innermost error (context removed).
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::Context { source, .. } => source.root(),
+            _ => self,
+        }
+    }
+
+    /// Error kind name (suitable for logs and metrics labels).
+    pub fn kind(&self) -> &'static str {
+        match self.root() {
+            Error::DeviceNotFound(_) => "device_not_found",
+            Error::CrcMismatch => "crc_mismatch",
+            Error::PowerOnReset => "power_on_reset",
+            Error::InvalidReading(_) => "invalid_reading",
+            Error::Parse(_) => "parse",
+            Error::Io(_) => "io",
+            Error::Timeout => "timeout",
+            Error::Context { .. } | Error::Other(_) => "other",
+        }
+    }
+
+    /// Transient sensor error, next reading will likely succeed.
+    pub fn is_transient(&self) -> bool {
+        match self.root() {
+            Error::CrcMismatch | Error::PowerOnReset | Error::InvalidReading(_) | Error::Timeout => true,
+            _ => false,
+        }
+    }
 }
 
 impl std::error::Error for Error {
     fn description(&self) -> &str {
-        &self.msg
+        match self {
+            Error::DeviceNotFound(_) => "device not found",
+            Error::CrcMismatch => "CRC mismatch",
+            Error::PowerOnReset => "power on reset value",
+            Error::InvalidReading(_) => "invalid temperature value",
+            Error::Parse(_) => "parse error",
+            Error::Io(_) => "I/O error",
+            Error::Timeout => "timed out",
+            Error::Context { msg, .. } => msg,
+            Error::Other(msg) => msg,
+        }
+    }
+
+    fn cause(&self) -> Option<&dyn std::error::Error> {
+        match self {
+            Error::Io(e) => Some(e.as_ref()),
+            Error::Context { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
     }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
-        f.write_str(&self.msg)
+        match self {
+            Error::DeviceNotFound(path) => write!(f, "Device not found: {}", path),
+            Error::CrcMismatch => f.write_str("CRC mismatch"),
+            Error::PowerOnReset => f.write_str("Sensor error, power on reset value (t=85000)"),
+            Error::InvalidReading(value) => write!(f, "Invalid temperature value ({})", value),
+            Error::Parse(msg) => write!(f, "Parse error: {}", msg),
+            Error::Io(e) => write!(f, "I/O error: {}", e),
+            Error::Timeout => f.write_str("Timed out"),
+            Error::Context { msg, source } => write!(f, "{}: {}", msg, source),
+            Error::Other(msg) => f.write_str(msg),
+        }
     }
 }
 
 impl From<String> for Error {
     fn from(msg: String) -> Error {
-        Error { msg }
+        Error::Other(msg)
     }
 }
 
@@ -33,6 +126,41 @@ impl<'a> From<&'a str> for Error {
 
 impl From<IOError> for Error {
     fn from(e: IOError) -> Error {
-        Error::from(format!("I/O error: {}", e))
+        match e.kind() {
+            ErrorKind::TimedOut => Error::Timeout,
+            _ => Error::Io(Arc::new(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Error;
+    use std::error::Error as StdError;
+    use std::io::{Error as IOError, ErrorKind};
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_io_error_conversion() {
+        let e = Error::from(IOError::new(ErrorKind::PermissionDenied, "denied"));
+        assert_eq!(e.kind(), "io");
+        assert!(e.cause().is_some());
+
+        assert_eq!(Error::from(IOError::new(ErrorKind::TimedOut, "slow")).kind(), "timeout");
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_context() {
+        let e = Error::CrcMismatch.context("Unable to read /sys/bus/w1/devices/28-1/w1_slave");
+
+        assert_eq!(
+            e.to_string(),
+            "Unable to read /sys/bus/w1/devices/28-1/w1_slave: CRC mismatch"
+        );
+        assert_eq!(e.kind(), "crc_mismatch");
+        assert!(e.is_transient());
+        assert!(e.cause().unwrap().cause().is_none());
+        assert!(!Error::DeviceNotFound("28-1".to_string()).is_transient());
     }
 }
@@ -0,0 +1,422 @@
+//! Export & import of recorded readings.
+//!
+//! Exported CSV files (`sensor,timestamp,raw,filtered,units,status`) can be imported back.
+//! Aggregated readings (`sensor,timestamp,resolution,count,min,mean,max,units`) can't.
+use chrono::{DateTime, NaiveDate, SecondsFormat, Utc};
+use clock::Timezone;
+use error::Error;
+use rollup::{Bucket, Resolution};
+use serde_json;
+use state::{Location, Reading};
+use std::io::{BufRead, Write};
+use std::str::FromStr;
+use w1::thermometer::{Temperature, Units};
+
+/// CSV header.
+const CSV_HEADER: &str = "sensor,timestamp,raw,filtered,units,status";
+
+/// Aggregated readings CSV header.
+const BUCKETS_CSV_HEADER: &str = "sensor,timestamp,resolution,count,min,mean,max,units";
+
+/// Export format.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Format {
+    Csv,
+    JsonLines,
+}
+
+impl AsRef<str> for Format {
+    fn as_ref(&self) -> &str {
+        match self {
+            Format::Csv => "csv",
+            Format::JsonLines => "jsonl",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "csv" => Ok(Format::Csv),
+            "jsonl" => Ok(Format::JsonLines),
+            _ => Err(Error::from(format!("Invalid export format: {}", s))),
+        }
+    }
+}
+
+/// Exported reading.
+#[derive(Serialize)]
+struct Record<'a> {
+    sensor: &'a str,
+    timestamp: String,
+    raw: f64,
+    filtered: f64,
+    units: &'a str,
+    status: &'a str,
+}
+
+impl<'a> Record<'a> {
+    fn new(location: &'a Location, reading: &'a Reading, units: &'a Units) -> Record<'a> {
+        // Readings are stored with 0.001 °C resolution, hide conversion noise
+        let round = |t: Temperature| (t.in_units(*units) * 1_000.0).round() / 1_000.0;
+
+        Record {
+            sensor: location.as_ref(),
+            timestamp: format_timestamp(&reading.timestamp),
+            raw: round(reading.raw),
+            filtered: round(reading.filtered),
+            units: units.as_ref(),
+            status: reading.status.as_ref(),
+        }
+    }
+}
+
+/// Exported bucket (aggregated readings).
+#[derive(Serialize)]
+struct BucketRecord<'a> {
+    sensor: &'a str,
+    timestamp: String,
+    resolution: &'a str,
+    count: u64,
+    min: f64,
+    mean: f64,
+    max: f64,
+    units: &'a str,
+}
+
+impl<'a> BucketRecord<'a> {
+    fn new(
+        location: &'a Location,
+        bucket: &'a Bucket,
+        resolution: &'a Resolution,
+        units: &'a Units,
+    ) -> BucketRecord<'a> {
+        let round = |t: Temperature| (t.in_units(*units) * 1_000.0).round() / 1_000.0;
+
+        BucketRecord {
+            sensor: location.as_ref(),
+            timestamp: format_timestamp(&bucket.start),
+            resolution: resolution.as_ref(),
+            count: bucket.count,
+            min: round(bucket.min),
+            mean: round(bucket.mean()),
+            max: round(bucket.max),
+            units: units.as_ref(),
+        }
+    }
+}
+
+/// Format timestamp as RFC3339 (ISO-8601) string.
+///
+/// # Arguments
+///
+/// * `timestamp` - timestamp
+fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Parse time provided on the command line.
+///
+/// Accepts RFC3339 (`2018-07-01T12:00:00+02:00`) or date only (`2018-07-01`, midnight in
+/// the given timezone).
+///
+/// # Arguments
+///
+/// * `s` - time
+/// * `timezone` - timezone of the date only time
+pub fn parse_time(s: &str, timezone: Timezone) -> Result<DateTime<Utc>, Error> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Ok(t.with_timezone(&Utc));
+    }
+
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| timezone.midnight(d))
+        .ok_or_else(|| Error::from(format!("Invalid time (expected RFC3339 or YYYY-MM-DD): {}", s)))
+}
+
+/// Write readings in the given format.
+///
+/// # Arguments
+///
+/// * `writer` - output
+/// * `readings` - readings to export
+/// * `format` - output format
+/// * `units` - temperature units
+pub fn export<W>(writer: &mut W, readings: &[(Location, Reading)], format: Format, units: Units) -> Result<(), Error>
+where
+    W: Write,
+{
+    if format == Format::Csv {
+        writeln!(writer, "{}", CSV_HEADER)?;
+    }
+
+    for (location, reading) in readings {
+        let record = Record::new(location, reading, &units);
+
+        match format {
+            Format::Csv => writeln!(
+                writer,
+                "{},{},{},{},{},{}",
+                record.sensor, record.timestamp, record.raw, record.filtered, record.units, record.status
+            )?,
+            Format::JsonLines => writeln!(
+                writer,
+                "{}",
+                serde_json::to_string(&record).map_err(|e| Error::from(format!("JSON error: {}", e)))?
+            )?,
+        };
+    }
+
+    Ok(())
+}
+
+/// Write aggregated readings in the given format.
+///
+/// # Arguments
+///
+/// * `writer` - output
+/// * `buckets` - buckets to export
+/// * `resolution` - buckets resolution
+/// * `format` - output format
+/// * `units` - temperature units
+pub fn export_buckets<W>(
+    writer: &mut W,
+    buckets: &[(Location, Bucket)],
+    resolution: Resolution,
+    format: Format,
+    units: Units,
+) -> Result<(), Error>
+where
+    W: Write,
+{
+    if format == Format::Csv {
+        writeln!(writer, "{}", BUCKETS_CSV_HEADER)?;
+    }
+
+    for (location, bucket) in buckets {
+        let record = BucketRecord::new(location, bucket, &resolution, &units);
+
+        match format {
+            Format::Csv => writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{}",
+                record.sensor,
+                record.timestamp,
+                record.resolution,
+                record.count,
+                record.min,
+                record.mean,
+                record.max,
+                record.units
+            )?,
+            Format::JsonLines => writeln!(
+                writer,
+                "{}",
+                serde_json::to_string(&record).map_err(|e| Error::from(format!("JSON error: {}", e)))?
+            )?,
+        };
+    }
+
+    Ok(())
+}
+
+/// Parse exported CSV.
+///
+/// # Arguments
+///
+/// * `reader` - CSV input
+pub fn parse_csv<R>(reader: R) -> Result<Vec<(Location, Reading)>, Error>
+where
+    R: BufRead,
+{
+    let mut lines = reader.lines();
+
+    let header = lines
+        .next()
+        .ok_or_else(|| Error::Parse("missing CSV header".to_string()))??;
+    let columns: Vec<&str> = header.trim().split(',').collect();
+    let column = |name: &str| {
+        columns
+            .iter()
+            .position(|c| *c == name)
+            .ok_or_else(|| Error::Parse(format!("missing CSV column: {}", name)))
+    };
+    let (sensor, timestamp, raw, filtered, units, status) = (
+        column("sensor")?,
+        column("timestamp")?,
+        column("raw")?,
+        column("filtered")?,
+        column("units")?,
+        column("status")?,
+    );
+
+    let mut readings = vec![];
+
+    for (index, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let values: Vec<&str> = line.trim().split(',').collect();
+        let value = |column: usize| {
+            values
+                .get(column)
+                .cloned()
+                .ok_or_else(|| Error::Parse(format!("missing value on line {}", index + 2)))
+        };
+        let temperature = |column: usize, units: Units| -> Result<Temperature, Error> {
+            let v = value(column)?;
+            v.parse::<f64>()
+                .map(|v| Temperature::from_units(v, units))
+                .map_err(|e| Error::Parse(format!("invalid temperature {} on line {}: {}", v, index + 2, e)))
+        };
+
+        let units = value(units)?.parse::<Units>()?;
+        let reading = Reading {
+            timestamp: DateTime::parse_from_rfc3339(value(timestamp)?)
+                .map_err(|e| Error::Parse(format!("invalid timestamp on line {}: {}", index + 2, e)))?
+                .with_timezone(&Utc),
+            raw: temperature(raw, units)?,
+            filtered: temperature(filtered, units)?,
+            status: value(status)?.parse()?,
+        };
+
+        readings.push((value(sensor)?.parse::<Location>()?, reading));
+    }
+
+    Ok(readings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{export, export_buckets, parse_csv, parse_time, Format};
+    use chrono::{TimeZone, Utc};
+    use clock::{Timezone, Zone};
+    use rollup::{aggregate, Bucket, Resolution};
+    use state::{Location, Reading, Status};
+    use w1::thermometer::{Temperature, Units};
+
+    fn readings() -> Vec<(Location, Reading)> {
+        vec![
+            (
+                Location::Inside,
+                Reading {
+                    timestamp: Utc.ymd(2018, 7, 1).and_hms_milli(12, 0, 0, 500),
+                    raw: Temperature::new(21_500),
+                    filtered: Temperature::new(21_250),
+                    status: Status::Ok,
+                },
+            ),
+            (
+                Location::Outside,
+                Reading {
+                    timestamp: Utc.ymd(2018, 7, 1).and_hms(12, 0, 1),
+                    raw: Temperature::new(60_000),
+                    filtered: Temperature::new(-5_000),
+                    status: Status::Rejected,
+                },
+            ),
+        ]
+    }
+
+    #[test]
+    fn test_export_csv() {
+        let mut output = vec![];
+        export(&mut output, &readings(), Format::Csv, Units::Fahrenheit).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "sensor,timestamp,raw,filtered,units,status\n\
+             inside,2018-07-01T12:00:00.500Z,70.7,70.25,fahrenheit,ok\n\
+             outside,2018-07-01T12:00:01.000Z,140,23,fahrenheit,rejected\n"
+        );
+    }
+
+    #[test]
+    fn test_export_json_lines() {
+        let mut output = vec![];
+        export(&mut output, &readings()[..1], Format::JsonLines, Units::Celsius).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"sensor\":\"inside\",\"timestamp\":\"2018-07-01T12:00:00.500Z\",\"raw\":21.5,\
+             \"filtered\":21.25,\"units\":\"celsius\",\"status\":\"ok\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_export_buckets() {
+        let buckets = aggregate(
+            readings().iter().map(|(l, r)| (*l, Bucket::from_reading(r))),
+            Resolution::Hour,
+        );
+
+        let mut output = vec![];
+        export_buckets(&mut output, &buckets, Resolution::Hour, Format::Csv, Units::Celsius).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "sensor,timestamp,resolution,count,min,mean,max,units\n\
+             inside,2018-07-01T12:00:00.000Z,hour,1,21.25,21.25,21.25,celsius\n\
+             outside,2018-07-01T12:00:00.000Z,hour,1,-5,-5,-5,celsius\n"
+        );
+
+        let mut output = vec![];
+        export_buckets(
+            &mut output,
+            &buckets[..1],
+            Resolution::Hour,
+            Format::JsonLines,
+            Units::Celsius,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "{\"sensor\":\"inside\",\"timestamp\":\"2018-07-01T12:00:00.000Z\",\"resolution\":\"hour\",\
+             \"count\":1,\"min\":21.25,\"mean\":21.25,\"max\":21.25,\"units\":\"celsius\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let mut output = vec![];
+        export(&mut output, &readings(), Format::Csv, Units::Fahrenheit).unwrap();
+
+        let parsed = parse_csv(&output[..]).unwrap();
+        assert_eq!(parsed.len(), 2);
+        for ((l1, r1), (l2, r2)) in parsed.iter().zip(readings().iter()) {
+            assert_eq!(l1, l2);
+            assert_eq!(r1.timestamp, r2.timestamp);
+            assert_eq!(r1.raw, r2.raw);
+            assert_eq!(r1.filtered, r2.filtered);
+            assert_eq!(r1.status, r2.status);
+        }
+    }
+
+    #[test]
+    fn test_parse_csv_errors() {
+        assert!(parse_csv(&b""[..]).is_err());
+        assert!(parse_csv(&b"sensor,timestamp\n"[..]).is_err());
+        assert!(parse_csv(&b"sensor,timestamp,raw,filtered,units,status\ninside,now,1,1,celsius,ok\n"[..]).is_err());
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(
+            parse_time("2018-07-01T12:00:00+02:00", Timezone::System).unwrap(),
+            Utc.ymd(2018, 7, 1).and_hms(10, 0, 0)
+        );
+        assert!(parse_time("2018-07-01", Timezone::System).is_ok());
+
+        let prague = Zone::parse("Europe/Prague", include_bytes!("clock/fixtures/Europe/Prague")).unwrap();
+        let prague = Box::leak(Box::new(prague));
+        assert_eq!(
+            parse_time("2018-07-01", Timezone::Named(prague)).unwrap(),
+            Utc.ymd(2018, 6, 30).and_hms(22, 0, 0)
+        );
+        assert!(parse_time("yesterday", Timezone::System).is_err());
+    }
+}
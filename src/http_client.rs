@@ -0,0 +1,202 @@
+//! Minimal HTTP/1.1 client (plain HTTP, GET only).
+use config;
+use error::Error;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+/// Max response size (bytes), larger responses are rejected.
+const MAX_RESPONSE_SIZE: u64 = 1024 * 1024;
+
+/// Split `http://host[:port][/path]` URL into host, port & path.
+///
+/// # Arguments
+///
+/// * `url` - URL
+pub fn parse_url(url: &str) -> Result<(&str, u16, &str), Error> {
+    let invalid = |reason: &str| Error::Parse(format!("invalid URL {}: {}", url, reason));
+
+    if !url.starts_with("http://") {
+        return Err(invalid("only http:// is supported"));
+    }
+    let rest = &url["http://".len()..];
+    let (authority, path) = match rest.find('/') {
+        Some(index) => (&rest[..index], &rest[index..]),
+        None => (rest, "/"),
+    };
+
+    let (host, port) = match authority.rfind(':') {
+        Some(index) => (
+            &authority[..index],
+            authority[index + 1..]
+                .parse::<u16>()
+                .map_err(|_| invalid("invalid port"))?,
+        ),
+        None => (authority, 80),
+    };
+
+    if host.is_empty() {
+        return Err(invalid("missing host"));
+    }
+
+    Ok((host, port, path))
+}
+
+/// Decode chunked transfer encoding.
+///
+/// # Arguments
+///
+/// * `data` - chunked body
+fn decode_chunked(mut data: &[u8]) -> Result<Vec<u8>, Error> {
+    let invalid = || Error::Parse("invalid chunked HTTP body".to_string());
+    let mut body = vec![];
+
+    loop {
+        let line_end = data.windows(2).position(|w| w == b"\r\n").ok_or_else(invalid)?;
+        let line = String::from_utf8_lossy(&data[..line_end]);
+        // Chunk extensions are ignored
+        let size = usize::from_str_radix(line.split(';').next().unwrap_or("").trim(), 16).map_err(|_| invalid())?;
+        data = &data[line_end + 2..];
+
+        if size == 0 {
+            return Ok(body);
+        }
+        if data.len() < size + 2 {
+            return Err(invalid());
+        }
+        body.extend_from_slice(&data[..size]);
+        data = &data[size + 2..];
+    }
+}
+
+/// Read the whole response, fails if it's larger than `MAX_RESPONSE_SIZE`.
+///
+/// # Arguments
+///
+/// * `reader` - response reader
+fn read_response<R>(reader: R) -> Result<Vec<u8>, Error>
+where
+    R: Read,
+{
+    let mut response = vec![];
+    reader.take(MAX_RESPONSE_SIZE + 1).read_to_end(&mut response)?;
+
+    if response.len() as u64 > MAX_RESPONSE_SIZE {
+        return Err(Error::Other(format!(
+            "HTTP response too large: more than {} bytes",
+            MAX_RESPONSE_SIZE
+        )));
+    }
+    Ok(response)
+}
+
+/// Parse HTTP response, returns body of the `200` response.
+///
+/// # Arguments
+///
+/// * `response` - raw response
+fn parse_response(response: &[u8]) -> Result<String, Error> {
+    let head_end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| Error::Parse("invalid HTTP response: missing header".to_string()))?;
+    let head = String::from_utf8_lossy(&response[..head_end]);
+    let body = &response[head_end + 4..];
+
+    let mut lines = head.split("\r\n");
+    let status = lines.next().unwrap_or("");
+    match status.split_whitespace().nth(1) {
+        Some("200") => {}
+        _ => return Err(Error::Other(format!("HTTP request failed: {}", status))),
+    };
+
+    let chunked = lines.any(|line| {
+        let mut parts = line.splitn(2, ':');
+        let name = parts.next().unwrap_or("").trim();
+        let value = parts.next().unwrap_or("").trim();
+        name.eq_ignore_ascii_case("transfer-encoding") && value.eq_ignore_ascii_case("chunked")
+    });
+
+    let body = if chunked { decode_chunked(body)? } else { body.to_vec() };
+    String::from_utf8(body).map_err(|_| Error::Parse("invalid HTTP response: body is not UTF-8".to_string()))
+}
+
+/// Send GET request and return response body.
+///
+/// # Arguments
+///
+/// * `url` - `http://` URL
+/// * `timeout` - connect, read & write timeout
+pub fn get(url: &str, timeout: Duration) -> Result<String, Error> {
+    let (host, port, path) = parse_url(url)?;
+
+    let address = (host, port).to_socket_addrs()?.next().ok_or_else(|| {
+        Error::from(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("unable to resolve {}", host),
+        ))
+    })?;
+
+    let mut stream = TcpStream::connect_timeout(&address, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUser-Agent: {}/{}\r\nAccept: application/json\r\nConnection: close\r\n\r\n",
+        path,
+        host,
+        config::PKG_NAME,
+        config::PKG_VERSION
+    )?;
+
+    let response = read_response(stream)?;
+    parse_response(&response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_response, parse_url, read_response, MAX_RESPONSE_SIZE};
+    use std::io::{self, Read};
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            parse_url("http://api.open-meteo.com/v1/forecast?latitude=50.08").unwrap(),
+            ("api.open-meteo.com", 80, "/v1/forecast?latitude=50.08")
+        );
+        assert_eq!(parse_url("http://localhost:8080").unwrap(), ("localhost", 8080, "/"));
+        assert!(parse_url("http://localhost:port/").is_err());
+        assert!(parse_url("http:///forecast").is_err());
+        assert!(parse_url("ftp://localhost/").is_err());
+    }
+
+    #[test]
+    fn test_parse_response() {
+        let response = b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}";
+        assert_eq!(parse_response(response).unwrap(), "{}");
+
+        let response = b"HTTP/1.1 200 OK\r\ntransfer-encoding: Chunked\r\n\r\n4\r\n{\"a\"\r\n3;x=y\r\n:1}\r\n0\r\n\r\n";
+        assert_eq!(parse_response(response).unwrap(), "{\"a\":1}");
+
+        let response = b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n";
+        assert_eq!(
+            parse_response(response).unwrap_err().to_string(),
+            "HTTP request failed: HTTP/1.1 400 Bad Request"
+        );
+
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\n").is_err());
+        assert!(parse_response(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\n{").is_err());
+    }
+
+    #[test]
+    fn test_response_too_large() {
+        let response = read_response(io::repeat(b'a').take(MAX_RESPONSE_SIZE)).unwrap();
+        assert_eq!(response.len() as u64, MAX_RESPONSE_SIZE);
+
+        assert_eq!(
+            read_response(io::repeat(b'a')).unwrap_err().to_string(),
+            "HTTP response too large: more than 1048576 bytes"
+        );
+    }
+}
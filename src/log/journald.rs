@@ -0,0 +1,221 @@
+//! systemd-journald drain.
+//!
+//! Records are sent as native protocol datagrams to the journald socket, one datagram per
+//! record. Unix sockets are available only on unix, elsewhere the drain can't be created and
+//! the logs go to the fallback terminal drain.
+use slog::{Drain, OwnedKVList, Record};
+use std::io;
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+use std::path::PathBuf;
+
+/// Default journald native protocol socket.
+pub const DEFAULT_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Syslog identifier of our journal entries.
+const SYSLOG_IDENTIFIER: &str = "thermometer";
+
+/// Convert key to the journal field name.
+///
+/// Field names consist of upper case letters, digits and underscores only and must
+/// not start with an underscore (trusted fields) or a digit.
+///
+/// # Arguments
+///
+/// * `key` - record key
+fn field_name(key: &str) -> Option<String> {
+    let name: String = key
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .skip_while(|c| *c == '_' || c.is_ascii_digit())
+        .take(64)
+        .collect();
+
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+/// Append field to the datagram.
+///
+/// # Arguments
+///
+/// * `buf` - datagram
+/// * `name` - field name
+/// * `value` - field value
+fn append_field(buf: &mut Vec<u8>, name: &str, value: &str) {
+    buf.extend_from_slice(name.as_bytes());
+    if value.contains('\n') {
+        // Multi line values are length prefixed (64 bit little endian)
+        buf.push(b'\n');
+        let length = value.len() as u64;
+        buf.extend((0..8).map(|i| (length >> (i * 8)) as u8));
+    } else {
+        buf.push(b'=');
+    }
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(b'\n');
+}
+
+/// Encode record as a journald native protocol datagram.
+///
+/// # Arguments
+///
+/// * `record` - log record
+/// * `values` - logger key-value pairs
+#[cfg_attr(not(unix), allow(dead_code))]
+fn encode(record: &Record, values: &OwnedKVList) -> Vec<u8> {
+    let mut buf = vec![];
+
+    append_field(&mut buf, "MESSAGE", &format!("{}", record.msg()));
+    append_field(&mut buf, "PRIORITY", &super::severity(record.level()).to_string());
+    append_field(&mut buf, "SYSLOG_IDENTIFIER", SYSLOG_IDENTIFIER);
+    append_field(&mut buf, "CODE_FILE", record.file());
+    append_field(&mut buf, "CODE_LINE", &record.line().to_string());
+    append_field(&mut buf, "CODE_FUNC", record.module());
+
+    for (key, value) in super::key_values(record, values) {
+        if let Some(name) = field_name(key) {
+            append_field(&mut buf, &name, &value);
+        }
+    }
+
+    buf
+}
+
+/// Drain sending records to systemd-journald via the native protocol.
+///
+/// Record key-value pairs are sent as journal fields (`device` -> `DEVICE`).
+pub struct Journald {
+    #[cfg(unix)]
+    socket: UnixDatagram,
+    #[cfg(unix)]
+    path: PathBuf,
+}
+
+#[cfg(unix)]
+impl Journald {
+    /// Create journald drain.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - journald native protocol socket path
+    pub fn new<P>(path: P) -> io::Result<Journald>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        if !path.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("journald socket {} not found", path.display()),
+            ));
+        }
+
+        Ok(Journald {
+            socket: UnixDatagram::unbound()?,
+            path,
+        })
+    }
+}
+
+#[cfg(not(unix))]
+impl Journald {
+    /// Create journald drain, always fails without unix sockets.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - journald native protocol socket path
+    pub fn new<P>(path: P) -> io::Result<Journald>
+    where
+        P: Into<PathBuf>,
+    {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "journald socket {} is not supported on this platform",
+                path.into().display()
+            ),
+        ))
+    }
+}
+
+#[cfg(unix)]
+impl Drain for Journald {
+    type Ok = ();
+    type Err = io::Error;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> io::Result<()> {
+        self.socket.send_to(&encode(record, values), &self.path).map(|_| ())
+    }
+}
+
+#[cfg(not(unix))]
+impl Drain for Journald {
+    type Ok = ();
+    type Err = io::Error;
+
+    fn log(&self, _record: &Record, _values: &OwnedKVList) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::{append_field, field_name, Journald};
+    use slog::{Drain, Logger};
+    use std::env;
+    use std::fs;
+    use std::os::unix::net::UnixDatagram;
+
+    #[test]
+    fn test_field_name() {
+        assert_eq!(field_name("device"), Some("DEVICE".to_string()));
+        assert_eq!(field_name("raw_celsius"), Some("RAW_CELSIUS".to_string()));
+        assert_eq!(field_name("_1-file.name"), Some("FILE_NAME".to_string()));
+        assert_eq!(field_name("__"), None);
+    }
+
+    #[test]
+    fn test_append_multi_line_field() {
+        let mut buf = vec![];
+        append_field(&mut buf, "ERROR", "a\nb");
+        assert_eq!(buf, b"ERROR\n\x03\x00\x00\x00\x00\x00\x00\x00a\nb\n".to_vec());
+    }
+
+    #[test]
+    fn test_send_record() {
+        let folder = env::temp_dir().join("thermometer-log-journald");
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        let path = folder.join("socket");
+
+        let journal = UnixDatagram::bind(&path).unwrap();
+        let logger = Logger::root(Journald::new(&path).unwrap().fuse(), o!("version" => "1.0"));
+        warn!(logger, "Failed to read temperature"; "device" => "28-000009e8ac40", "celsius" => 21.5);
+
+        let mut buf = [0; 4096];
+        let size = journal.recv(&mut buf).unwrap();
+        let datagram = String::from_utf8_lossy(&buf[..size]).to_string();
+
+        assert!(datagram.starts_with("MESSAGE=Failed to read temperature\nPRIORITY=4\nSYSLOG_IDENTIFIER=thermometer\n"));
+        assert!(datagram.contains("\nDEVICE=28-000009e8ac40\n"));
+        assert!(datagram.contains("\nCELSIUS=21.5\n"));
+        assert!(datagram.ends_with("\nVERSION=1.0\n"));
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_missing_socket() {
+        assert!(Journald::new("/nonexistent/journal/socket").is_err());
+    }
+}
@@ -0,0 +1,214 @@
+//! Root logger construction.
+//!
+//! The drain is selected by the configured log target: standard output / error or rotating
+//! log file (`term` or `json` format), journald native protocol or RFC5424 syslog. Drain
+//! which can't be created falls back to the terminal on stderr. Records below the configured
+//! level are dropped by the level filter before they reach the asynchronous drain.
+//!
+//! The level is fixed when the logger is created, changing it requires a restart.
+mod journald;
+mod rotating_file;
+mod syslog;
+
+pub use self::journald::{Journald, DEFAULT_SOCKET as DEFAULT_JOURNALD_SOCKET};
+pub use self::rotating_file::{RotatingFile, Rotation};
+pub use self::syslog::{Address as SyslogAddress, Syslog};
+
+use config;
+use error::Error;
+use slog::{self, Drain, Key, Level, LevelFilter, Logger, Never, OwnedKVList, Record, Serializer, KV};
+use slog_async::Async;
+use slog_json::Json;
+use slog_term::{FullFormat, PlainDecorator, TermDecorator};
+use std::fmt;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// Log format.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Format {
+    /// Human readable format.
+    Term,
+    /// JSON object per line.
+    Json,
+}
+
+impl AsRef<str> for Format {
+    fn as_ref(&self) -> &str {
+        match self {
+            Format::Term => "term",
+            Format::Json => "json",
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "term" => Ok(Format::Term),
+            "json" => Ok(Format::Json),
+            _ => Err(Error::from(format!("Invalid log format: {}", s))),
+        }
+    }
+}
+
+/// Log target.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Target {
+    /// Standard output / error or log file.
+    Standard,
+    /// systemd-journald native protocol.
+    Journald,
+    /// RFC5424 syslog over UDP or TCP.
+    Syslog,
+}
+
+impl AsRef<str> for Target {
+    fn as_ref(&self) -> &str {
+        match self {
+            Target::Standard => "standard",
+            Target::Journald => "journald",
+            Target::Syslog => "syslog",
+        }
+    }
+}
+
+impl FromStr for Target {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "standard" => Ok(Target::Standard),
+            "journald" => Ok(Target::Journald),
+            "syslog" => Ok(Target::Syslog),
+            _ => Err(Error::from(format!("Invalid log target: {}", s))),
+        }
+    }
+}
+
+/// Syslog severity of the log level.
+///
+/// # Arguments
+///
+/// * `level` - log level
+fn severity(level: Level) -> u8 {
+    match level {
+        Level::Critical => 2,
+        Level::Error => 3,
+        Level::Warning => 4,
+        Level::Info => 6,
+        Level::Debug | Level::Trace => 7,
+    }
+}
+
+/// Serializer collecting key-value pairs as strings.
+struct KeyValues(Vec<(Key, String)>);
+
+impl Serializer for KeyValues {
+    fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments) -> slog::Result {
+        self.0.push((key, format!("{}", val)));
+        Ok(())
+    }
+}
+
+/// Record key-value pairs followed by the logger ones.
+///
+/// # Arguments
+///
+/// * `record` - log record
+/// * `values` - logger key-value pairs
+fn key_values(record: &Record, values: &OwnedKVList) -> Vec<(Key, String)> {
+    let mut serializer = KeyValues(vec![]);
+    // Serializer never fails
+    let _ = record.kv().serialize(record, &mut serializer);
+    let _ = values.serialize(record, &mut serializer);
+    serializer.0
+}
+
+/// Boxed drain.
+type BoxedDrain = Box<dyn Drain<Ok = (), Err = Never> + Send>;
+
+/// Create drain writing into the given writer.
+///
+/// # Arguments
+///
+/// * `writer` - output
+/// * `format` - log format
+fn writer_drain<W>(writer: W, format: Format) -> BoxedDrain
+where
+    W: Write + Send + 'static,
+{
+    match format {
+        Format::Term => Box::new(FullFormat::new(PlainDecorator::new(writer)).build().fuse()),
+        Format::Json => Box::new(Json::new(writer).add_default_keys().build().fuse()),
+    }
+}
+
+/// Create standard output / error or log file drain.
+///
+/// Without log file, terminal format goes to stderr and JSON to stdout.
+fn standard_drain() -> Result<BoxedDrain, Error> {
+    let format = config::CONFIG.log_format();
+
+    match config::CONFIG.log_file() {
+        Some(path) => {
+            let file = RotatingFile::open(
+                path,
+                config::CONFIG.log_file_max_size(),
+                config::CONFIG.log_file_rotation(),
+                config::CONFIG.log_file_count(),
+            )?;
+            Ok(writer_drain(file, format))
+        }
+        None => match format {
+            Format::Term => Ok(Box::new(FullFormat::new(TermDecorator::new().build()).build().fuse())),
+            Format::Json => Ok(writer_drain(io::stdout(), format)),
+        },
+    }
+}
+
+/// Create drain based on the configuration.
+///
+/// Journald and syslog messages are dropped if they can't be delivered.
+fn drain() -> Result<BoxedDrain, Error> {
+    match config::CONFIG.log_target() {
+        Target::Standard => standard_drain(),
+        Target::Journald => Ok(Box::new(
+            Journald::new(config::CONFIG.log_journald_socket())?.ignore_res(),
+        )),
+        Target::Syslog => Ok(Box::new(Syslog::new(config::CONFIG.log_syslog_address())?.ignore_res())),
+    }
+}
+
+/// Create root logger.
+///
+/// Falls back to the terminal on stderr if the configured target can't be used.
+pub fn create_root_logger() -> Logger {
+    let values = o!(
+        "version" => config::PKG_VERSION,
+    );
+
+    let (drain, error) = match drain() {
+        Ok(drain) => (drain, None),
+        Err(e) => (
+            Box::new(FullFormat::new(TermDecorator::new().build()).build().fuse()) as BoxedDrain,
+            Some(e),
+        ),
+    };
+
+    let drain = LevelFilter::new(drain, config::CONFIG.log_level()).fuse();
+    let async_drain = Async::new(drain).build().fuse();
+
+    let logger = Logger::root(async_drain, values);
+
+    if let Some(e) = error {
+        error!(logger, "Failed to initialize log target, logging to stderr";
+            "error" => %e,
+            "target" => config::CONFIG.log_target().as_ref(),
+            "file" => config::CONFIG.log_file());
+    }
+
+    logger
+}
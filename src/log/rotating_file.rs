@@ -0,0 +1,233 @@
+use chrono::{DateTime, Datelike, Local, Timelike};
+use error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Time based log file rotation.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Rotation {
+    Never,
+    Hourly,
+    Daily,
+}
+
+impl Rotation {
+    /// Rotation period identifier, log file is rotated when it changes.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - local time
+    fn period(self, time: &DateTime<Local>) -> Option<i64> {
+        let days = i64::from(time.num_days_from_ce());
+        match self {
+            Rotation::Never => None,
+            Rotation::Hourly => Some(days * 24 + i64::from(time.hour())),
+            Rotation::Daily => Some(days),
+        }
+    }
+}
+
+impl AsRef<str> for Rotation {
+    fn as_ref(&self) -> &str {
+        match self {
+            Rotation::Never => "never",
+            Rotation::Hourly => "hourly",
+            Rotation::Daily => "daily",
+        }
+    }
+}
+
+impl FromStr for Rotation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "never" => Ok(Rotation::Never),
+            "hourly" => Ok(Rotation::Hourly),
+            "daily" => Ok(Rotation::Daily),
+            _ => Err(Error::from(format!("Invalid log rotation: {}", s))),
+        }
+    }
+}
+
+/// Log file rotated by size and time.
+///
+/// Rotated files are suffixed with a number (`thermometer.log.1` is the most recent one).
+/// Files are rotated at line boundaries only, log records are never split.
+pub struct RotatingFile {
+    path: PathBuf,
+    max_size: u64,
+    rotation: Rotation,
+    count: usize,
+    file: File,
+    size: u64,
+    period: Option<i64>,
+    line_start: bool,
+}
+
+impl RotatingFile {
+    /// Open log file, new data are appended.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - log file path
+    /// * `max_size` - file is rotated if it grows beyond this size (bytes)
+    /// * `rotation` - time based rotation
+    /// * `count` - number of rotated files to keep
+    pub fn open<P>(path: P, max_size: u64, rotation: Rotation, count: usize) -> io::Result<RotatingFile>
+    where
+        P: Into<PathBuf>,
+    {
+        let path = path.into();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+
+        Ok(RotatingFile {
+            path,
+            max_size,
+            rotation,
+            count,
+            file,
+            size,
+            period: rotation.period(&Local::now()),
+            line_start: true,
+        })
+    }
+
+    /// Rotated file path.
+    ///
+    /// # Arguments
+    ///
+    /// * `index` - rotated file index (1 = most recent)
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", index));
+        path.into()
+    }
+
+    /// Rotate files and start new empty log file.
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+
+        let _ = fs::remove_file(self.rotated_path(self.count));
+        for index in (1..self.count).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+
+        if self.count > 0 {
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+
+    /// Write buffer, rotate file if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf` - data to write
+    /// * `now` - current time
+    fn write_at(&mut self, buf: &[u8], now: &DateTime<Local>) -> io::Result<usize> {
+        let period = self.rotation.period(now);
+
+        if self.line_start && self.size > 0 && (self.size + buf.len() as u64 > self.max_size || period != self.period) {
+            self.rotate()?;
+        }
+        self.period = period;
+
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        if written > 0 {
+            self.line_start = buf[written - 1] == b'\n';
+        }
+        Ok(written)
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.write_at(buf, &Local::now())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RotatingFile, Rotation};
+    use chrono::{Local, TimeZone};
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+
+    fn read(path: &Path) -> String {
+        fs::read_to_string(path).unwrap_or_default()
+    }
+
+    #[test]
+    fn test_size_rotation() {
+        let folder = env::temp_dir().join("thermometer-log-size-rotation");
+        let _ = fs::remove_dir_all(&folder);
+        let path = folder.join("thermometer.log");
+
+        let mut file = RotatingFile::open(&path, 10, Rotation::Never, 2).unwrap();
+        let now = Local::now();
+        for line in &["first", "second ", "line\n", "third\n", "fourth\n"] {
+            file.write_at(line.as_bytes(), &now).unwrap();
+        }
+
+        // Record split into several writes is never split between files
+        assert_eq!(read(&path), "fourth\n");
+        assert_eq!(read(&path.with_extension("log.1")), "third\n");
+        assert_eq!(read(&path.with_extension("log.2")), "firstsecond line\n");
+        assert!(!path.with_extension("log.3").exists());
+
+        file.write_at(b"fifth\n", &now).unwrap();
+        assert_eq!(read(&path.with_extension("log.2")), "third\n");
+        assert!(!path.with_extension("log.3").exists());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_time_rotation() {
+        let folder = env::temp_dir().join("thermometer-log-time-rotation");
+        let _ = fs::remove_dir_all(&folder);
+        let path = folder.join("thermometer.log");
+
+        let mut file = RotatingFile::open(&path, 1_000, Rotation::Hourly, 5).unwrap();
+        file.write_at(b"a\n", &Local.ymd(2018, 7, 1).and_hms(10, 0, 0)).unwrap();
+        file.write_at(b"b\n", &Local.ymd(2018, 7, 1).and_hms(10, 59, 59))
+            .unwrap();
+        file.write_at(b"c\n", &Local.ymd(2018, 7, 1).and_hms(11, 0, 0)).unwrap();
+
+        assert_eq!(read(&path), "c\n");
+        assert_eq!(read(&path.with_extension("log.1")), "a\nb\n");
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_rotation_conversion() {
+        for rotation in &[Rotation::Never, Rotation::Hourly, Rotation::Daily] {
+            assert_eq!(rotation.as_ref().parse::<Rotation>().unwrap(), *rotation);
+        }
+    }
+}
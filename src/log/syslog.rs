@@ -0,0 +1,337 @@
+//! RFC5424 syslog drain.
+//!
+//! Records are sent to the remote syslog server over UDP (one datagram per message) or TCP
+//! (octet counting framing, reconnected after failures). Messages which can't be delivered
+//! are dropped, logging never blocks the application on an unreachable server for long.
+use chrono::{DateTime, SecondsFormat, Utc};
+use error::Error;
+use slog::{Drain, OwnedKVList, Record};
+use std::fs;
+use std::io::{self, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::process;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Application name.
+const APP_NAME: &str = "thermometer";
+
+/// Structured data ID (32473 is the private enterprise number reserved for documentation).
+const SD_ID: &str = "thermometer@32473";
+
+/// Syslog facility (daemon).
+const FACILITY: u8 = 3;
+
+/// TCP connect & write timeout (s).
+const TCP_TIMEOUT: u64 = 5;
+
+/// Syslog transport.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Transport {
+    Udp,
+    Tcp,
+}
+
+impl AsRef<str> for Transport {
+    fn as_ref(&self) -> &str {
+        match self {
+            Transport::Udp => "udp",
+            Transport::Tcp => "tcp",
+        }
+    }
+}
+
+/// Syslog server address (`udp://host:port` or `tcp://host:port`).
+#[derive(PartialEq, Debug, Clone)]
+pub struct Address {
+    transport: Transport,
+    address: String,
+}
+
+impl Address {
+    /// Resolve address.
+    fn resolve(&self) -> io::Result<SocketAddr> {
+        self.address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("unable to resolve {}", self.address)))
+    }
+}
+
+impl FromStr for Address {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let separator = s.find("://").ok_or_else(|| {
+            Error::from(format!(
+                "Invalid syslog address (expected udp://host:port or tcp://host:port): {}",
+                s
+            ))
+        })?;
+        let (scheme, address) = (&s[..separator], &s[separator + 3..]);
+
+        let transport = match scheme {
+            "udp" => Transport::Udp,
+            "tcp" => Transport::Tcp,
+            _ => return Err(Error::from(format!("Invalid syslog transport: {}", scheme))),
+        };
+
+        match address.rfind(':').map(|i| (&address[..i], &address[i + 1..])) {
+            Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => Ok(Address {
+                transport,
+                address: address.to_string(),
+            }),
+            _ => Err(Error::from(format!("Invalid syslog host:port: {}", address))),
+        }
+    }
+}
+
+/// Escape structured data parameter value.
+///
+/// # Arguments
+///
+/// * `value` - parameter value
+fn escape_param_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c == '"' || c == '\\' || c == ']' {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Convert key to the structured data parameter name.
+///
+/// # Arguments
+///
+/// * `key` - record key
+fn param_name(key: &str) -> Option<String> {
+    let name: String = key
+        .chars()
+        .filter(|c| c.is_ascii_graphic() && !"=]\"".contains(*c))
+        .take(32)
+        .collect();
+
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
+/// Format record as a RFC5424 message.
+///
+/// # Arguments
+///
+/// * `record` - log record
+/// * `values` - logger key-value pairs
+/// * `timestamp` - message timestamp
+/// * `hostname` - host name
+/// * `process_id` - process ID
+fn format_message(
+    record: &Record,
+    values: &OwnedKVList,
+    timestamp: &DateTime<Utc>,
+    hostname: &str,
+    process_id: u32,
+) -> String {
+    let params: Vec<String> = super::key_values(record, values)
+        .iter()
+        .filter_map(|(key, value)| param_name(key).map(|name| format!("{}=\"{}\"", name, escape_param_value(value))))
+        .collect();
+
+    let structured_data = if params.is_empty() {
+        "-".to_string()
+    } else {
+        format!("[{} {}]", SD_ID, params.join(" "))
+    };
+
+    format!(
+        "<{}>1 {} {} {} {} - {} {}",
+        FACILITY * 8 + super::severity(record.level()),
+        timestamp.to_rfc3339_opts(SecondsFormat::Micros, true),
+        hostname,
+        APP_NAME,
+        process_id,
+        structured_data,
+        record.msg()
+    )
+}
+
+/// Host name or `-` (nil value) if it can't be determined.
+fn hostname() -> String {
+    fs::read_to_string("/proc/sys/kernel/hostname")
+        .ok()
+        .map(|s| {
+            s.trim()
+                .chars()
+                .filter(|c| c.is_ascii_graphic())
+                .take(255)
+                .collect::<String>()
+        })
+        .filter(|s| !s.is_empty())
+        .unwrap_or_else(|| "-".to_string())
+}
+
+/// Connection to the syslog server.
+enum Connection {
+    Udp(UdpSocket),
+    /// TCP connection is established lazily and re-established after failures.
+    Tcp(SocketAddr, Mutex<Option<TcpStream>>),
+}
+
+/// Drain sending RFC5424 messages to the syslog server.
+///
+/// Record key-value pairs are sent as structured data parameters. TCP messages are
+/// framed with octet counting (RFC6587).
+pub struct Syslog {
+    connection: Connection,
+    hostname: String,
+    process_id: u32,
+}
+
+impl Syslog {
+    /// Create syslog drain.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - syslog server address
+    pub fn new(address: &Address) -> io::Result<Syslog> {
+        let server = address.resolve()?;
+
+        let connection = match address.transport {
+            Transport::Udp => {
+                let local = if server.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" };
+                let socket = UdpSocket::bind(local)?;
+                socket.connect(server)?;
+                Connection::Udp(socket)
+            }
+            Transport::Tcp => Connection::Tcp(server, Mutex::new(None)),
+        };
+
+        Ok(Syslog {
+            connection,
+            hostname: hostname(),
+            process_id: process::id(),
+        })
+    }
+
+    /// Send message over TCP, connect if needed.
+    ///
+    /// # Arguments
+    ///
+    /// * `server` - syslog server address
+    /// * `stream` - current connection
+    /// * `message` - message to send
+    fn send_tcp(server: &SocketAddr, stream: &Mutex<Option<TcpStream>>, message: &str) -> io::Result<()> {
+        let mut stream = stream
+            .lock()
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "syslog connection lock poisoned"))?;
+
+        if stream.is_none() {
+            let timeout = Duration::from_secs(TCP_TIMEOUT);
+            let s = TcpStream::connect_timeout(server, timeout)?;
+            s.set_write_timeout(Some(timeout))?;
+            *stream = Some(s);
+        }
+
+        let result = stream
+            .as_mut()
+            .unwrap()
+            .write_all(format!("{} {}", message.len(), message).as_bytes());
+        if result.is_err() {
+            // Reconnect with the next message
+            *stream = None;
+        }
+        result
+    }
+}
+
+impl Drain for Syslog {
+    type Ok = ();
+    type Err = io::Error;
+
+    fn log(&self, record: &Record, values: &OwnedKVList) -> io::Result<()> {
+        let message = format_message(record, values, &Utc::now(), &self.hostname, self.process_id);
+
+        match self.connection {
+            Connection::Udp(ref socket) => socket.send(message.as_bytes()).map(|_| ()),
+            Connection::Tcp(ref server, ref stream) => Syslog::send_tcp(server, stream, &message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{escape_param_value, param_name, Address, Syslog, Transport};
+    use slog::{Drain, Logger};
+    use std::io::Read;
+    use std::net::{TcpListener, UdpSocket};
+    use std::process;
+
+    #[test]
+    fn test_address() {
+        let address = "tcp://logs.example.com:601".parse::<Address>().unwrap();
+        assert_eq!(address.transport, Transport::Tcp);
+        assert_eq!(address.address, "logs.example.com:601");
+
+        assert!("udp://[::1]:514".parse::<Address>().is_ok());
+        assert!("logs.example.com:514".parse::<Address>().is_err());
+        assert!("http://logs.example.com:514".parse::<Address>().is_err());
+        assert!("udp://logs.example.com".parse::<Address>().is_err());
+        assert!("udp://:514".parse::<Address>().is_err());
+    }
+
+    #[test]
+    fn test_structured_data() {
+        assert_eq!(escape_param_value("a\"b\\c]d"), "a\\\"b\\\\c\\]d");
+        assert_eq!(param_name("raw celsius="), Some("rawcelsius".to_string()));
+        assert_eq!(param_name(" "), None);
+    }
+
+    #[test]
+    fn test_send_udp() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = format!("udp://{}", server.local_addr().unwrap())
+            .parse::<Address>()
+            .unwrap();
+
+        let logger = Logger::root(Syslog::new(&address).unwrap().fuse(), o!());
+        error!(logger, "Failed to read temperature"; "device" => "28-000009e8ac40", "error" => "CRC \"mismatch\"");
+
+        let mut buf = [0; 4096];
+        let size = server.recv(&mut buf).unwrap();
+        let message = String::from_utf8_lossy(&buf[..size]).to_string();
+
+        assert!(message.starts_with("<27>1 "));
+        assert!(message.ends_with(
+            " - [thermometer@32473 error=\"CRC \\\"mismatch\\\"\" device=\"28-000009e8ac40\"] \
+             Failed to read temperature"
+        ));
+    }
+
+    #[test]
+    fn test_send_tcp() {
+        let server = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = format!("tcp://{}", server.local_addr().unwrap())
+            .parse::<Address>()
+            .unwrap();
+
+        let logger = Logger::root(Syslog::new(&address).unwrap().fuse(), o!());
+        info!(logger, "Starting");
+
+        let mut message = String::new();
+        let (stream, _) = server.accept().unwrap();
+        drop(logger);
+        stream.take(1024).read_to_string(&mut message).unwrap();
+
+        let mut parts = message.splitn(2, ' ');
+        let (length, message) = (parts.next().unwrap(), parts.next().unwrap());
+        assert_eq!(length.parse::<usize>().unwrap(), message.len());
+        assert!(message.starts_with("<30>1 "));
+        assert!(message.ends_with(&format!(" thermometer {} - - Starting", process::id())));
+    }
+}
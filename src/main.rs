@@ -2,12 +2,20 @@ extern crate chrono;
 extern crate clap;
 extern crate find_folder;
 extern crate futures;
+extern crate image;
 #[macro_use]
 extern crate lazy_static;
+extern crate libc;
 extern crate piston_window;
+extern crate rand;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
+extern crate serde_json;
 #[macro_use]
 extern crate slog;
 extern crate slog_async;
+extern crate slog_json;
 extern crate slog_term;
 extern crate tokio;
 extern crate tokio_codec;
@@ -15,21 +23,207 @@ extern crate tokio_fs;
 extern crate tokio_timer;
 
 mod app;
+mod clock;
 mod config;
+mod derived;
 mod error;
+mod export;
+mod http_client;
 mod log;
 mod processing;
+mod rollup;
+mod settings;
+mod shutdown;
 mod state;
+mod storage;
+mod trend;
 mod w1;
+mod weather;
 
-fn main() {
-    let logger = log::create_root_logger();
-    let shared_state = state::SharedState::new();
+use chrono::Utc;
+use config::Command;
+use error::Error;
+use rollup::Resolution;
+use settings::Settings;
+use slog::Logger;
+use std::cmp;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::process;
+use std::time::Duration;
+use storage::{Batch, Storage};
+
+/// Load persisted settings, configured ones are used if there are none or they can't be loaded.
+///
+/// # Arguments
+///
+/// * `logger` - root logger
+fn load_settings(logger: &Logger) -> Settings {
+    let default = Settings::new(config::CONFIG.temperature_units());
+
+    match Settings::load(config::CONFIG.data_folder()) {
+        Ok(settings) => settings.unwrap_or(default),
+        Err(e) => {
+            error!(logger, "Failed to load settings, using configured ones"; "error" => %e);
+            default
+        }
+    }
+}
+
+/// Open storage and log the recovery.
+///
+/// # Arguments
+///
+/// * `batch` - write batching
+/// * `logger` - root logger
+fn open_storage(batch: Batch, logger: &Logger) -> Result<Storage, Error> {
+    let storage = Storage::open(config::CONFIG.data_folder(), batch)?;
+
+    let recovery = storage.recovery();
+    if recovery.journal {
+        warn!(logger, "Interrupted compaction finished");
+    }
+    if recovery.truncated > 0 {
+        warn!(logger, "Incomplete stored readings removed"; "bytes" => recovery.truncated);
+    }
+
+    Ok(storage)
+}
+
+/// Read thermometers and display temperatures.
+///
+/// # Arguments
+///
+/// * `logger` - root logger
+fn run(logger: &Logger) {
+    let storage = match open_storage(config::CONFIG.storage_batch(), logger) {
+        Ok(storage) => Some(storage),
+        Err(e) => {
+            error!(logger, "Failed to open storage, readings will not be persisted";
+                "error" => %e,
+                "folder" => config::CONFIG.data_folder());
+            None
+        }
+    };
+
+    let shared_state = state::SharedState::new(
+        config::CONFIG.history_size(),
+        load_settings(logger),
+        storage,
+        config::CONFIG.virtual_sensors().to_vec(),
+    );
+
+    // Readings must be held back from the first one if the clock is not synchronized
+    let now = Utc::now();
+    if !config::CONFIG.clock_sync_check().is_synchronized(now) {
+        warn!(logger, "Clock not synchronized, readings are held back"; "time" => %now);
+        let _ = shared_state.set_clock_synchronized(false, now);
+    }
 
     info!(logger, "Spawning background thread for processing");
-    processing::spawn_background_thread(shared_state.clone(), logger.clone());
+    let background = processing::spawn_background_thread(shared_state.clone(), logger.clone());
+
+    if let Some(source) = config::CONFIG.weather_source() {
+        info!(logger, "Spawning background thread for weather forecast");
+        weather::spawn_background_thread(
+            source,
+            Duration::from_secs(config::CONFIG.weather_interval()),
+            shared_state.clone(),
+            logger.clone(),
+        );
+    }
+
+    if let Err(e) = shutdown::install() {
+        error!(logger, "Failed to install signal handlers"; "error" => %e);
+    }
 
-    // TODO: Add some shutdown logic (SIGTERM), especially for Docker image on resinOS
     info!(logger, "Launching UI");
-    app::run(shared_state);
+    app::run(shared_state.clone(), logger.clone());
+
+    // Readers & compaction must not touch the storage during the final flush
+    info!(logger, "Stopping background processing");
+    background.stop();
+    if let Err(e) = shared_state.flush_storage() {
+        error!(logger, "Failed to write stored readings"; "error" => %e);
+    }
+}
+
+/// Execute command (other than `Command::Run`).
+///
+/// # Arguments
+///
+/// * `command` - command to execute
+/// * `logger` - root logger
+fn execute(command: &Command, logger: &Logger) -> Result<(), Error> {
+    match command {
+        Command::Run => unreachable!(),
+        Command::Export {
+            location,
+            from,
+            to,
+            resolution,
+            format,
+        } => {
+            // Running thermometer can write to the storage, export doesn't modify it
+            let storage = Storage::open_read_only(config::CONFIG.data_folder());
+            let requested = resolution.unwrap_or_else(|| config::CONFIG.retention().resolution(*from, Utc::now()));
+            // Compacted readings can't be exported in a finer resolution
+            let stored = storage.resolution(*location, *from, *to)?;
+            if resolution.is_some() && stored > requested {
+                warn!(logger, "Readings are exported in the finest stored resolution";
+                    "requested" => requested.as_ref(),
+                    "resolution" => stored.as_ref());
+            }
+            let resolution = cmp::max(requested, stored);
+            let units = load_settings(logger).units;
+            let stdout = io::stdout();
+            let mut writer = BufWriter::new(stdout.lock());
+
+            let count = match resolution {
+                Resolution::Raw => {
+                    let readings = storage.readings(*location, *from, *to)?;
+                    export::export(&mut writer, &readings, *format, units)?;
+                    readings.len()
+                }
+                _ => {
+                    let buckets = storage.buckets(*location, *from, *to, resolution)?;
+                    export::export_buckets(&mut writer, &buckets, resolution, *format, units)?;
+                    buckets.len()
+                }
+            };
+            info!(logger, "Readings exported"; "count" => count, "resolution" => resolution.as_ref());
+        }
+        Command::Import { files } => {
+            // Fails if the data folder is locked by the running thermometer
+            let mut storage = open_storage(config::CONFIG.storage_batch(), logger)?;
+
+            for file in files {
+                let readings = File::open(file)
+                    .map_err(Error::from)
+                    .and_then(|f| export::parse_csv(BufReader::new(f)))
+                    .map_err(|e| e.context(format!("Unable to import {}", file)))?;
+                let count = storage.import(readings)?;
+                info!(logger, "Readings imported"; "file" => file, "count" => count);
+            }
+        }
+    };
+
+    Ok(())
+}
+
+fn main() {
+    let logger = log::create_root_logger();
+
+    let command = config::CONFIG.command();
+    if let Command::Run = command {
+        run(&logger);
+        return;
+    }
+
+    if let Err(e) = execute(command, &logger) {
+        error!(logger, "Command failed"; "error" => %e);
+        // Flush async logger before exit
+        drop(logger);
+        process::exit(1);
+    }
 }
@@ -0,0 +1,304 @@
+//! Temperature signal filters.
+//!
+//! Filters are chained per sensor and applied in the order in which they are defined.
+//! Every filter gets the output of the previous one. A filter can also reject a value
+//! (spike rejection), in which case the rest of the chain is skipped.
+use error::Error;
+use std::collections::VecDeque;
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::time::Instant;
+use w1::thermometer::Temperature;
+
+/// Number of consecutive rejected values after which the spike filter gives up
+/// and accepts the new value (the temperature has really changed).
+const MAX_CONSECUTIVE_REJECTIONS: usize = 3;
+
+/// Filter definition.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FilterKind {
+    /// Moving median over the given number of values.
+    Median(usize),
+    /// Exponential moving average with the given smoothing factor (0, 1].
+    Ema(f64),
+    /// Rejects values changing faster than the given rate (degrees celsius per second).
+    Spike(f64),
+}
+
+impl FromStr for FilterKind {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.trim().splitn(2, ':');
+        let name = parts.next().unwrap_or("");
+        let argument = parts
+            .next()
+            .ok_or_else(|| Error::from(format!("Missing filter argument: {}", s)))?;
+
+        match name {
+            "median" => match argument.parse::<usize>() {
+                Ok(size) if size > 0 => Ok(FilterKind::Median(size)),
+                _ => Err(Error::from(format!("Invalid median window size: {}", argument))),
+            },
+            "ema" => match argument.parse::<f64>() {
+                Ok(alpha) if alpha > 0.0 && alpha <= 1.0 => Ok(FilterKind::Ema(alpha)),
+                _ => Err(Error::from(format!("Invalid EMA smoothing factor: {}", argument))),
+            },
+            "spike" => match argument.parse::<f64>() {
+                Ok(rate) if rate > 0.0 => Ok(FilterKind::Spike(rate)),
+                _ => Err(Error::from(format!("Invalid spike max rate of change: {}", argument))),
+            },
+            _ => Err(Error::from(format!("Invalid filter: {}", name))),
+        }
+    }
+}
+
+impl Display for FilterKind {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), fmt::Error> {
+        match self {
+            FilterKind::Median(size) => write!(f, "median:{}", size),
+            FilterKind::Ema(alpha) => write!(f, "ema:{}", alpha),
+            FilterKind::Spike(rate) => write!(f, "spike:{}", rate),
+        }
+    }
+}
+
+/// Parse comma separated list of filters (`spike:5,median:5,ema:0.3`).
+///
+/// `none` stands for an empty list (no filtering).
+///
+/// # Arguments
+///
+/// * `s` - filters definition
+pub fn parse_filters(s: &str) -> Result<Vec<FilterKind>, Error> {
+    if s.trim() == "none" {
+        return Ok(vec![]);
+    }
+
+    s.split(',')
+        .filter(|f| !f.trim().is_empty())
+        .map(|f| f.parse::<FilterKind>())
+        .collect()
+}
+
+/// Trait that must be implemented by all filters.
+trait Filter: Send {
+    /// Filter value.
+    ///
+    /// Returns `None` if the value was rejected.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - temperature
+    /// * `instant` - time at which the temperature was read
+    fn apply(&mut self, value: Temperature, instant: Instant) -> Option<Temperature>;
+}
+
+/// Moving median.
+struct Median {
+    size: usize,
+    window: VecDeque<i64>,
+}
+
+impl Filter for Median {
+    fn apply(&mut self, value: Temperature, _instant: Instant) -> Option<Temperature> {
+        if self.window.len() == self.size {
+            self.window.pop_front();
+        }
+        self.window.push_back(value.value());
+
+        let mut sorted: Vec<i64> = self.window.iter().cloned().collect();
+        sorted.sort();
+
+        // Both indexes point to the same value if the window length is odd
+        let length = sorted.len();
+        Some(Temperature::new((sorted[(length - 1) / 2] + sorted[length / 2]) / 2))
+    }
+}
+
+/// Exponential moving average.
+struct Ema {
+    alpha: f64,
+    average: Option<f64>,
+}
+
+impl Filter for Ema {
+    fn apply(&mut self, value: Temperature, _instant: Instant) -> Option<Temperature> {
+        let value = value.value() as f64;
+        let average = match self.average {
+            Some(average) => average + self.alpha * (value - average),
+            None => value,
+        };
+        self.average = Some(average);
+
+        Some(Temperature::new(average.round() as i64))
+    }
+}
+
+/// Max rate of change spike rejection.
+struct Spike {
+    max_rate: f64,
+    last: Option<(Temperature, Instant)>,
+    rejected: usize,
+}
+
+impl Filter for Spike {
+    fn apply(&mut self, value: Temperature, instant: Instant) -> Option<Temperature> {
+        if let Some((last, last_instant)) = self.last {
+            let elapsed = instant.duration_since(last_instant);
+            let seconds = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) / 1_000_000_000.0;
+            let delta = (value.celsius() - last.celsius()).abs();
+
+            // Zero elapsed time means that any change is a spike
+            if delta > self.max_rate * seconds && self.rejected < MAX_CONSECUTIVE_REJECTIONS {
+                self.rejected += 1;
+                return None;
+            }
+        }
+
+        self.rejected = 0;
+        self.last = Some((value, instant));
+        Some(value)
+    }
+}
+
+/// Chain of filters applied to sensor values.
+pub struct FilterChain {
+    filters: Vec<Box<dyn Filter>>,
+}
+
+impl FilterChain {
+    /// Create new `FilterChain`.
+    ///
+    /// # Arguments
+    ///
+    /// * `kinds` - filter definitions in the order in which they should be applied
+    pub fn new(kinds: &[FilterKind]) -> FilterChain {
+        let filters = kinds
+            .iter()
+            .map(|kind| -> Box<dyn Filter> {
+                match *kind {
+                    FilterKind::Median(size) => Box::new(Median {
+                        size,
+                        window: VecDeque::with_capacity(size),
+                    }),
+                    FilterKind::Ema(alpha) => Box::new(Ema { alpha, average: None }),
+                    FilterKind::Spike(max_rate) => Box::new(Spike {
+                        max_rate,
+                        last: None,
+                        rejected: 0,
+                    }),
+                }
+            })
+            .collect();
+
+        FilterChain { filters }
+    }
+
+    /// Filter value.
+    ///
+    /// Returns `None` if any filter in the chain rejected the value.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - raw temperature
+    /// * `instant` - time at which the temperature was read
+    pub fn apply(&mut self, value: Temperature, instant: Instant) -> Option<Temperature> {
+        self.filters
+            .iter_mut()
+            .try_fold(value, |value, filter| filter.apply(value, instant))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_filters, FilterChain, FilterKind};
+    use std::time::{Duration, Instant};
+    use w1::thermometer::Temperature;
+
+    fn apply_all(chain: &mut FilterChain, values: &[i64]) -> Vec<Option<i64>> {
+        let start = Instant::now();
+        values
+            .iter()
+            .enumerate()
+            .map(|(i, v)| {
+                chain
+                    .apply(Temperature::new(*v), start + Duration::from_millis(500 * i as u64))
+                    .map(|t| t.value())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_filters() {
+        assert_eq!(
+            parse_filters("spike:5, median:3,ema:0.5").unwrap(),
+            vec![FilterKind::Spike(5.0), FilterKind::Median(3), FilterKind::Ema(0.5)]
+        );
+        assert_eq!(parse_filters("none").unwrap(), vec![]);
+        assert_eq!(parse_filters("").unwrap(), vec![]);
+        assert!(parse_filters("median:0").is_err());
+        assert!(parse_filters("ema:1.5").is_err());
+        assert!(parse_filters("spike").is_err());
+        assert!(parse_filters("mean:3").is_err());
+    }
+
+    #[test]
+    fn test_filter_kind_display() {
+        for kind in &[FilterKind::Median(5), FilterKind::Ema(0.25), FilterKind::Spike(2.5)] {
+            assert_eq!(kind.to_string().parse::<FilterKind>().unwrap(), *kind);
+        }
+    }
+
+    #[test]
+    fn test_empty_chain() {
+        let mut chain = FilterChain::new(&[]);
+        assert_eq!(apply_all(&mut chain, &[1_000, 50_000]), vec![Some(1_000), Some(50_000)]);
+    }
+
+    #[test]
+    fn test_median() {
+        let mut chain = FilterChain::new(&[FilterKind::Median(3)]);
+        assert_eq!(
+            apply_all(&mut chain, &[1_000, 3_000, 40_000, 2_000, 4_000]),
+            vec![Some(1_000), Some(2_000), Some(3_000), Some(3_000), Some(4_000)]
+        );
+    }
+
+    #[test]
+    fn test_ema() {
+        let mut chain = FilterChain::new(&[FilterKind::Ema(0.5)]);
+        assert_eq!(
+            apply_all(&mut chain, &[10_000, 20_000, 20_000]),
+            vec![Some(10_000), Some(15_000), Some(17_500)]
+        );
+    }
+
+    #[test]
+    fn test_spike_rejection() {
+        // 1 °C per second, values are 500 ms apart
+        let mut chain = FilterChain::new(&[FilterKind::Spike(1.0)]);
+        assert_eq!(
+            apply_all(&mut chain, &[20_000, 20_400, 60_000, 20_600]),
+            vec![Some(20_000), Some(20_400), None, Some(20_600)]
+        );
+    }
+
+    #[test]
+    fn test_spike_gives_up_after_consecutive_rejections() {
+        let mut chain = FilterChain::new(&[FilterKind::Spike(1.0)]);
+        assert_eq!(
+            apply_all(&mut chain, &[20_000, 60_000, 60_000, 60_000, 60_000, 60_000]),
+            vec![Some(20_000), None, None, None, Some(60_000), Some(60_000)]
+        );
+    }
+
+    #[test]
+    fn test_rejected_value_skips_rest_of_chain() {
+        let mut chain = FilterChain::new(&[FilterKind::Spike(1.0), FilterKind::Ema(0.5)]);
+        assert_eq!(
+            apply_all(&mut chain, &[20_000, 60_000, 20_000]),
+            vec![Some(20_000), None, Some(20_000)]
+        );
+    }
+}
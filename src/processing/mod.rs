@@ -1,46 +1,364 @@
+pub mod filter;
 mod temperature;
 
-use self::temperature::{Location, TemperatureReader};
+use self::filter::{parse_filters, FilterChain, FilterKind};
+use self::temperature::TemperatureReader;
+use chrono::Utc;
 use config;
-use futures::Future;
+use error::Error;
+use futures::sync::oneshot;
+use futures::{future, Future, Stream};
 use slog::Logger;
-use state::SharedState;
+use state::{Location, SharedState};
+use std::str::FromStr;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use storage::Compaction;
 use tokio;
-use w1::thermometer::ds18b20::DS18B20;
+use tokio::runtime::Runtime;
+use tokio_timer::Interval;
+use w1::thermometer;
 
-/// Start Tokio runtime and spawn tasks.
+/// Interval in which the system clock synchronisation is checked (s).
+const CLOCK_CHECK_INTERVAL: u64 = 10;
+
+/// Max number of displayed decimal places.
+pub const MAX_PRECISION: usize = 3;
+
+/// What to do when a reading takes longer than the reading period.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Overrun {
+    /// Skip missed readings, continue with the next scheduled one.
+    Skip,
+    /// Catch up, missed readings are taken immediately one after another.
+    Burst,
+}
+
+impl AsRef<str> for Overrun {
+    fn as_ref(&self) -> &str {
+        match self {
+            Overrun::Skip => "skip",
+            Overrun::Burst => "burst",
+        }
+    }
+}
+
+impl FromStr for Overrun {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(Overrun::Skip),
+            "burst" => Ok(Overrun::Burst),
+            _ => Err(Error::from(format!("Invalid overrun behavior: {}", s))),
+        }
+    }
+}
+
+/// Additional sensor read from a thermometer device.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Sensor {
+    /// Sensor location (name).
+    pub location: Location,
+    /// Thermometer device (W1 device ID, simulation or remote definition).
+    pub device: String,
+    /// Filters applied to the readings, none by default.
+    pub filters: Vec<FilterKind>,
+    /// Number of displayed decimal places or `None` for the default one.
+    pub precision: Option<usize>,
+}
+
+/// Parse sensor name with the optional number of displayed decimal places, e.g. `attic`
+/// or `attic(2)`.
+///
+/// # Arguments
+///
+/// * `s` - sensor name
+pub fn parse_sensor_name(s: &str) -> Result<(Location, Option<usize>), Error> {
+    let s = s.trim();
+    match s.find('(') {
+        Some(index) if s.ends_with(')') => {
+            let precision = s[index + 1..s.len() - 1]
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .filter(|p| *p <= MAX_PRECISION)
+                .ok_or_else(|| Error::from(format!("Invalid sensor precision (0 - {}): {}", MAX_PRECISION, s)))?;
+            Ok((s[..index].trim().parse()?, Some(precision)))
+        }
+        _ => Ok((s.parse()?, None)),
+    }
+}
+
+impl FromStr for Sensor {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, '=');
+        let name = parts.next().unwrap_or("").trim();
+        let mut definition = parts.next().unwrap_or("").splitn(2, '|');
+        let device = definition
+            .next()
+            .map(|d| d.trim())
+            .filter(|d| !d.is_empty())
+            .ok_or_else(|| {
+                Error::from(format!(
+                    "Invalid sensor, expected NAME[(PRECISION)] = DEVICE [| FILTERS]: {}",
+                    s
+                ))
+            })?;
+        let filters = match definition.next() {
+            Some(filters) => parse_filters(filters)?,
+            None => vec![],
+        };
+
+        match parse_sensor_name(name)? {
+            (location @ Location::Named(_), precision) => {
+                thermometer::validate(device)?;
+                Ok(Sensor {
+                    location,
+                    device: device.to_string(),
+                    filters,
+                    precision,
+                })
+            }
+            _ => Err(Error::from(format!("Invalid sensor name: {}", name))),
+        }
+    }
+}
+
+/// Parse semicolon separated additional sensors.
+///
+/// # Arguments
+///
+/// * `s` - sensors, e.g. `kitchen = remote:http://kitchen:8080/readings; attic(2) = 28-000009e8f6e8 | median:5`
+pub fn parse_sensors(s: &str) -> Result<Vec<Sensor>, Error> {
+    let mut sensors: Vec<Sensor> = vec![];
+
+    for definition in s.split(';').filter(|d| !d.trim().is_empty()) {
+        let sensor = definition.parse::<Sensor>()?;
+
+        if sensors.iter().any(|s| s.location == sensor.location) {
+            return Err(Error::from(format!("Duplicate sensor: {}", sensor.location.as_ref())));
+        }
+
+        sensors.push(sensor);
+    }
+
+    Ok(sensors)
+}
+
+/// Temperature reading schedule.
+#[derive(Debug, Copy, Clone)]
+pub struct Schedule {
+    /// Readings are taken at fixed rate, phase aligned to multiples of the period.
+    pub period: Duration,
+    /// What to do when a reading takes longer than the period.
+    pub overrun: Overrun,
+    /// Reading is abandoned if it doesn't finish within this timeout, no new reading is
+    /// started until the abandoned one finishes.
+    pub read_timeout: Duration,
+}
+
+impl Schedule {
+    /// Schedule based on the configuration.
+    fn from_config() -> Schedule {
+        Schedule {
+            period: Duration::from_millis(config::CONFIG.temperature_interval()),
+            overrun: config::CONFIG.temperature_overrun(),
+            read_timeout: Duration::from_millis(config::CONFIG.temperature_read_timeout()),
+        }
+    }
+}
+
+/// Periodically compact stored readings.
+///
+/// # Arguments
+///
+/// * `state` - shared application state
+/// * `logger` - logger
+fn compaction_task(state: SharedState, logger: Logger) -> Box<dyn Future<Item = (), Error = ()> + Send> {
+    let period = Duration::from_secs(config::CONFIG.compaction_interval());
+    let error_logger = logger.clone();
+
+    Box::new(
+        Interval::new(Instant::now(), period)
+            .map_err(|e| Error::from(format!("Compaction timer failed: {}", e)))
+            .for_each(move |_| {
+                match state.compact_storage(Utc::now(), config::CONFIG.retention()) {
+                    Ok(compaction) => {
+                        if compaction != Compaction::default() {
+                            info!(logger, "Stored readings compacted";
+                                "readings" => compaction.readings,
+                                "minutes" => compaction.minutes);
+                        }
+                    }
+                    Err(e) => error!(logger, "Failed to compact stored readings"; "error" => %e),
+                };
+                Ok(())
+            })
+            .map_err(move |e| error!(error_logger, "Compaction stopped"; "error" => %e)),
+    )
+}
+
+/// Periodically write buffered readings, so they're written even if no new readings
+/// arrive.
+///
+/// # Arguments
+///
+/// * `state` - shared application state
+/// * `logger` - logger
+fn flush_task(state: SharedState, logger: Logger) -> Box<dyn Future<Item = (), Error = ()> + Send> {
+    let period = config::CONFIG.storage_batch().interval;
+    let error_logger = logger.clone();
+
+    Box::new(
+        Interval::new(Instant::now() + period, period)
+            .map_err(|e| Error::from(format!("Flush timer failed: {}", e)))
+            .for_each(move |_| {
+                if let Err(e) = state.flush_storage() {
+                    error!(logger, "Failed to write stored readings"; "error" => %e);
+                }
+                Ok(())
+            })
+            .map_err(move |e| error!(error_logger, "Flushing stopped"; "error" => %e)),
+    )
+}
+
+/// Periodically check the system clock synchronisation.
+///
+/// # Arguments
+///
+/// * `state` - shared application state
+/// * `logger` - logger
+fn clock_task(state: SharedState, logger: Logger) -> Box<dyn Future<Item = (), Error = ()> + Send> {
+    let error_logger = logger.clone();
+
+    Box::new(
+        Interval::new(Instant::now(), Duration::from_secs(CLOCK_CHECK_INTERVAL))
+            .map_err(|e| Error::from(format!("Clock timer failed: {}", e)))
+            .for_each(move |_| {
+                let now = Utc::now();
+                let synchronized = config::CONFIG.clock_sync_check().is_synchronized(now);
+
+                if synchronized != state.state().clock_synchronized {
+                    if synchronized {
+                        info!(logger, "Clock synchronized"; "time" => %now);
+                    } else {
+                        warn!(logger, "Clock not synchronized, readings are held back"; "time" => %now);
+                    }
+                }
+
+                if let Err(e) = state.set_clock_synchronized(synchronized, now) {
+                    error!(logger, "Failed to record held back readings"; "error" => %e);
+                }
+                Ok(())
+            })
+            .map_err(move |e| error!(error_logger, "Clock check stopped"; "error" => %e)),
+    )
+}
+
+/// Log virtual sensor readings, virtual sensors are evaluated in the state.
 ///
 /// # Arguments
 ///
 /// * `state` - shared application state
+/// * `logger` - logger
+fn virtual_sensors_task(state: &SharedState, logger: Logger) -> Box<dyn Future<Item = (), Error = ()> + Send> {
+    let locations = config::CONFIG.virtual_sensors().iter().map(|s| s.location).collect();
+
+    Box::new(state.subscribe(Some(locations)).for_each(move |update| {
+        debug!(logger, "Temperature";
+            "sensor" => update.location.as_ref(),
+            "timestamp" => %update.reading.timestamp.to_rfc3339(),
+            "celsius" => update.reading.filtered.celsius());
+        Ok(())
+    }))
+}
+
+/// Start Tokio runtime and spawn tasks, runtime is shut down when the shutdown is
+/// requested.
+///
+/// # Arguments
+///
+/// * `state` - shared application state
+/// * `shutdown` - completes when the shutdown is requested (or the sender is dropped)
 ///
 /// # Note
 ///
 /// This function blocks and will not return immediately. It must be spawned
 /// on another thread than main (UI).
-fn background_thread(state: SharedState, logger: Logger) {
+fn background_thread(state: SharedState, logger: Logger, shutdown: oneshot::Receiver<()>) {
     let inside_reader = TemperatureReader::new(
-        Box::new(DS18B20::new(config::CONFIG.inside_thermometer_device())),
+        // It's ok to unwrap, device is validated in `Config`
+        thermometer::create(config::CONFIG.inside_thermometer_device()).unwrap(),
         Location::Inside,
-        Duration::from_millis(config::CONFIG.temperature_interval()),
+        FilterChain::new(config::CONFIG.inside_thermometer_filters()),
+        Schedule::from_config(),
         state.clone(),
         logger.clone(),
     );
 
     let outside_reader = TemperatureReader::new(
-        Box::new(DS18B20::new(config::CONFIG.outside_thermometer_device())),
+        thermometer::create(config::CONFIG.outside_thermometer_device()).unwrap(),
         Location::Outside,
-        Duration::from_millis(config::CONFIG.temperature_interval()),
-        state,
-        logger,
+        FilterChain::new(config::CONFIG.outside_thermometer_filters()),
+        Schedule::from_config(),
+        state.clone(),
+        logger.clone(),
     );
 
-    let handler = inside_reader.select(outside_reader).then(|_| Ok(()));
+    let mut readers = vec![inside_reader, outside_reader];
+    for sensor in config::CONFIG.extra_sensors() {
+        readers.push(TemperatureReader::new(
+            thermometer::create(&sensor.device).unwrap(),
+            sensor.location,
+            FilterChain::new(&sensor.filters),
+            Schedule::from_config(),
+            state.clone(),
+            logger.clone(),
+        ));
+    }
+
+    let clock = clock_task(state.clone(), logger.clone());
+    let flush = flush_task(state.clone(), logger.clone());
+    let virtual_sensors = virtual_sensors_task(&state, logger.clone());
+    let compaction = compaction_task(state, logger.clone());
+    let handler: Box<dyn Future<Item = (), Error = ()> + Send> = Box::new(future::lazy(move || {
+        tokio::spawn(clock);
+        tokio::spawn(flush);
+        tokio::spawn(compaction);
+        tokio::spawn(virtual_sensors);
+        future::select_all(readers).then(|_| Ok(()))
+    }));
+
+    let mut runtime = match Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            error!(logger, "Failed to start Tokio runtime"; "error" => %e);
+            return;
+        }
+    };
+    let _: Result<(), ()> = runtime.block_on(handler.select(shutdown.then(|_| Ok(()))).then(|_| Ok(())));
+
+    // Spawned tasks are dropped, running ones (compaction included) finish their step first
+    let _ = runtime.shutdown_now().wait();
+    info!(logger, "Background processing stopped");
+}
 
-    // TODO: Add some shutdown logic (SIGTERM), especially for Docker image on resinOS
-    tokio::run(Box::new(handler));
+/// Running background processing thread.
+pub struct BackgroundThread {
+    shutdown: oneshot::Sender<()>,
+    thread: thread::JoinHandle<()>,
+}
+
+impl BackgroundThread {
+    /// Stop background processing and wait until it's stopped, no reading is recorded
+    /// and no compaction runs afterwards.
+    pub fn stop(self) {
+        let _ = self.shutdown.send(());
+        let _ = self.thread.join();
+    }
 }
 
 /// Spawn new thread with Tokio.
@@ -48,6 +366,47 @@ fn background_thread(state: SharedState, logger: Logger) {
 /// # Arguments
 ///
 /// * `state` - shared application state
-pub fn spawn_background_thread(state: SharedState, logger: Logger) {
-    thread::spawn(move || background_thread(state, logger));
+pub fn spawn_background_thread(state: SharedState, logger: Logger) -> BackgroundThread {
+    let (shutdown, receiver) = oneshot::channel();
+
+    BackgroundThread {
+        shutdown,
+        thread: thread::spawn(move || background_thread(state, logger, receiver)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_sensors;
+    use processing::filter::FilterKind;
+
+    #[test]
+    fn test_parse_sensors() {
+        let sensors =
+            parse_sensors("kitchen = remote:http://kitchen:8080/readings,sensor:inside; attic=28-000009e8f6e8")
+                .unwrap();
+
+        assert_eq!(sensors.len(), 2);
+        assert_eq!(sensors[0].location.as_ref(), "kitchen");
+        assert_eq!(sensors[0].device, "remote:http://kitchen:8080/readings,sensor:inside");
+        assert_eq!(sensors[1].device, "28-000009e8f6e8");
+        assert!(sensors[1].filters.is_empty());
+        assert_eq!(sensors[1].precision, None);
+
+        let sensors = parse_sensors("attic (2) = 28-000009e8f6e8 | median:5,ema:0.3").unwrap();
+        assert_eq!(sensors[0].location.as_ref(), "attic");
+        assert_eq!(sensors[0].device, "28-000009e8f6e8");
+        assert_eq!(sensors[0].filters, vec![FilterKind::Median(5), FilterKind::Ema(0.3)]);
+        assert_eq!(sensors[0].precision, Some(2));
+
+        assert!(parse_sensors("").unwrap().is_empty());
+        assert!(parse_sensors("inside = 28-000009e8f6e8").is_err());
+        assert!(parse_sensors("attic").is_err());
+        assert!(parse_sensors("attic = | median:5").is_err());
+        assert!(parse_sensors("attic = 28-000009e8f6e8 | median:x").is_err());
+        assert!(parse_sensors("attic = remote:ftp://attic/readings").is_err());
+        assert!(parse_sensors("attic(4) = 28-000009e8f6e8").is_err());
+        assert!(parse_sensors("attic(x) = 28-000009e8f6e8").is_err());
+        assert!(parse_sensors("attic = simulated:sine; attic = simulated:walk").is_err());
+    }
 }
@@ -1,46 +1,171 @@
-use super::Location;
+use chrono::{DateTime, Duration as OldDuration, TimeZone, Utc};
 use error::Error;
-use futures::{Async, Future, Poll};
+use futures::{Async, Future, Poll, Stream};
+use processing::filter::FilterChain;
+use processing::{Overrun, Schedule};
 use slog::Logger;
-use state::SharedState;
-use std::time::Duration;
-use tokio_timer::{sleep, Delay};
-use w1::thermometer::{Temperature, Thermometer, Units};
+use state::{Location, Reading, SharedState, Status};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio_timer::{Delay, Interval};
+use w1::thermometer::{Temperature, Thermometer};
+
+/// Temperature read which gives up if it doesn't finish within the timeout.
+///
+/// Blocking file system calls issued by tokio-fs can't be interrupted. The read future
+/// is kept after the timeout (`into_read`) and polled until it finishes, no other read
+/// of the sensor is started meanwhile, so a hung bus doesn't exhaust the blocking pool.
+struct TimedRead {
+    read: Box<dyn Future<Item = Temperature, Error = Error> + Send>,
+    deadline: Delay,
+}
+
+impl TimedRead {
+    /// Start reading temperature.
+    ///
+    /// # Arguments
+    ///
+    /// * `thermometer` - thermometer to read
+    /// * `timeout` - read timeout
+    fn new(thermometer: &dyn Thermometer, timeout: Duration) -> TimedRead {
+        TimedRead {
+            read: thermometer.temperature(),
+            deadline: Delay::new(Instant::now() + timeout),
+        }
+    }
+
+    /// Read which is still in flight after the timeout.
+    fn into_read(self) -> Box<dyn Future<Item = Temperature, Error = Error> + Send> {
+        self.read
+    }
+}
+
+impl Future for TimedRead {
+    type Item = Temperature;
+    type Error = Error;
+
+    fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
+        if let Async::Ready(temperature) = self.read.poll()? {
+            return Ok(Async::Ready(temperature));
+        }
+
+        match self.deadline.poll() {
+            Ok(Async::Ready(())) => Err(Error::Timeout),
+            Ok(Async::NotReady) => Ok(Async::NotReady),
+            Err(e) => Err(Error::from(format!("Read timer failed: {}", e))),
+        }
+    }
+}
+
+/// Delay until the next multiple of the period since the Unix epoch.
+///
+/// Readers with the same period are phase aligned, their reading timestamps line up.
+///
+/// # Arguments
+///
+/// * `period` - reading period
+/// * `now` - current time
+fn phase_delay(period: Duration, now: SystemTime) -> Duration {
+    let period = duration_millis(period).max(1);
+    let since_epoch = now.duration_since(UNIX_EPOCH).map(duration_millis).unwrap_or(0);
+
+    match since_epoch % period {
+        0 => Duration::from_millis(0),
+        offset => Duration::from_millis(period - offset),
+    }
+}
+
+/// Duration in milliseconds.
+fn duration_millis(duration: Duration) -> u64 {
+    duration.as_secs() * 1_000 + u64::from(duration.subsec_millis())
+}
+
+/// Reading timestamp of the scheduled tick (rounded to the period grid).
+///
+/// # Arguments
+///
+/// * `tick` - scheduled tick
+/// * `period` - reading period
+fn tick_timestamp(tick: Instant, period: Duration) -> DateTime<Utc> {
+    let now = Utc::now();
+    let instant = Instant::now();
+    let elapsed = if instant > tick {
+        OldDuration::from_std(instant.duration_since(tick)).unwrap_or_else(|_| OldDuration::zero())
+    } else {
+        OldDuration::zero()
+    };
+    let millis = (now - elapsed).timestamp_millis();
+
+    // Round to the nearest period (floor of the shifted value, negative values included)
+    let period = duration_millis(period).max(1) as i64;
+    let shifted = millis + period / 2;
+    let millis = shifted - (shifted % period + period) % period;
+
+    Utc.timestamp(0, 0) + OldDuration::milliseconds(millis)
+}
 
 pub struct TemperatureReader {
     thermometer: Box<dyn Thermometer + Send>,
     location: Location,
-    interval: Duration,
+    filter_chain: FilterChain,
+    last_filtered: Option<Temperature>,
+    schedule: Schedule,
     shared_state: SharedState,
     logger: Logger,
-    delay_handler: Option<Delay>,
-    reader_handler: Option<Box<dyn Future<Item = Temperature, Error = Error> + Send>>,
+    interval: Interval,
+    reader_handler: Option<(Instant, TimedRead)>,
+    /// Read which timed out, but it's still in flight.
+    stalled: Option<Box<dyn Future<Item = Temperature, Error = Error> + Send>>,
 }
 
 impl TemperatureReader {
     pub fn new(
         thermometer: Box<dyn Thermometer + Send>,
         location: Location,
-        interval: Duration,
+        filter_chain: FilterChain,
+        schedule: Schedule,
         shared_state: SharedState,
         logger: Logger,
     ) -> TemperatureReader {
+        let start = Instant::now() + phase_delay(schedule.period, SystemTime::now());
+
         TemperatureReader {
             thermometer,
             shared_state,
             location,
-            interval,
+            filter_chain,
+            last_filtered: None,
+            interval: Interval::new(start, schedule.period),
+            schedule,
             logger,
-            delay_handler: None,
             reader_handler: None,
+            stalled: None,
         }
     }
 
-    fn update_temperature(&self, temperature: Temperature) {
-        match self.location {
-            Location::Inside => self.shared_state.set_inside_temperature(temperature),
-            Location::Outside => self.shared_state.set_outside_temperature(temperature),
+    fn update_temperature(&mut self, temperature: Temperature, tick: Instant) -> Reading {
+        // Keep displaying the last accepted value if the new one was rejected
+        let (filtered, status) = match self.filter_chain.apply(temperature, tick) {
+            Some(filtered) => (filtered, Status::Ok),
+            None => (self.last_filtered.unwrap_or(temperature), Status::Rejected),
+        };
+        self.last_filtered = Some(filtered);
+
+        let reading = Reading {
+            timestamp: tick_timestamp(tick, self.schedule.period),
+            raw: temperature,
+            filtered,
+            status,
+        };
+
+        let result = self.shared_state.set_temperature(self.location, reading);
+
+        if let Err(e) = result {
+            error!(self.logger, "Failed to store temperature";
+                "error" => %e,
+                "device" => self.thermometer.device_path());
         }
+
+        reading
     }
 }
 
@@ -49,49 +174,226 @@ impl Future for TemperatureReader {
     type Error = ();
 
     fn poll(&mut self) -> Poll<Self::Item, Self::Error> {
-        if let Some(mut delay_handler) = self.delay_handler.take() {
-            match delay_handler.poll() {
-                Ok(Async::NotReady) => {
-                    self.delay_handler = Some(delay_handler);
-                    return Ok(Async::NotReady);
+        loop {
+            if let Some(mut stalled) = self.stalled.take() {
+                match stalled.poll() {
+                    Ok(Async::NotReady) => self.stalled = Some(stalled),
+                    // Reading is too old to be used
+                    result => {
+                        info!(self.logger, "Stalled temperature read finished";
+                            "ok" => result.is_ok(),
+                            "device" => self.thermometer.device_path());
+                    }
+                }
+            }
+
+            if let Some((tick, mut reader_handler)) = self.reader_handler.take() {
+                match reader_handler.poll() {
+                    Ok(Async::NotReady) => {
+                        self.reader_handler = Some((tick, reader_handler));
+                        return Ok(Async::NotReady);
+                    }
+                    Ok(Async::Ready(t)) => {
+                        let reading = self.update_temperature(t, tick);
+
+                        debug!(self.logger, "Temperature";
+                            "device" => self.thermometer.device_path(),
+                            "timestamp" => %reading.timestamp.to_rfc3339(),
+                            "celsius" => reading.filtered.celsius(),
+                            "fahrenheit" => reading.filtered.fahrenheit(),
+                            "raw_celsius" => reading.raw.celsius(),
+                            "status" => reading.status.as_ref());
+                    }
+                    Err(Error::Timeout) => {
+                        error!(self.logger, "Failed to read temperature";
+                            "error" => %Error::Timeout,
+                            "kind" => Error::Timeout.kind(),
+                            "device" => self.thermometer.device_path());
+                        self.stalled = Some(reader_handler.into_read());
+                    }
+                    Err(ref e) if e.is_transient() => {
+                        // CRC mismatch, power on reset, ... - sensor is there, next reading will likely succeed
+                        warn!(self.logger, "Failed to read temperature";
+                            "error" => %e,
+                            "kind" => e.kind(),
+                            "device" => self.thermometer.device_path());
+                    }
+                    Err(e) => {
+                        error!(self.logger, "Failed to read temperature";
+                            "error" => %e,
+                            "kind" => e.kind(),
+                            "device" => self.thermometer.device_path());
+                    }
+                };
+            }
+
+            match self.interval.poll() {
+                Ok(Async::Ready(Some(tick))) => {
+                    // Read took longer than the period, next tick is already due as well
+                    if self.schedule.overrun == Overrun::Skip && tick + self.schedule.period <= Instant::now() {
+                        debug!(self.logger, "Skipping missed reading"; "device" => self.thermometer.device_path());
+                        continue;
+                    }
+
+                    // Hung bus, wait for the stalled read instead of piling up blocking reads
+                    if self.stalled.is_some() {
+                        debug!(self.logger, "Skipping reading, stalled read is still in flight";
+                            "device" => self.thermometer.device_path());
+                        continue;
+                    }
+
+                    self.reader_handler = Some((
+                        tick,
+                        TimedRead::new(self.thermometer.as_ref(), self.schedule.read_timeout),
+                    ));
                 }
+                Ok(Async::Ready(None)) => return Ok(Async::Ready(())),
+                Ok(Async::NotReady) => return Ok(Async::NotReady),
                 Err(e) => {
+                    // Timer fails on shutdown or when it's at capacity, it doesn't recover
                     error!(self.logger, "Timer failed"; "error" => %e);
+                    return Err(());
                 }
-                _ => {}
             };
+        }
+    }
+}
 
-            // Read temperature when timer fires or fails
+#[cfg(test)]
+mod tests {
+    use super::{phase_delay, tick_timestamp, TemperatureReader, TimedRead};
+    use error::Error;
+    use futures::{future, Future};
+    use processing::filter::FilterChain;
+    use processing::{Overrun, Schedule};
+    use settings::Settings;
+    use slog::{Discard, Logger};
+    use state::{Location, SharedState};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant, UNIX_EPOCH};
+    use tokio::runtime::current_thread::Runtime;
+    use tokio_timer::Delay;
+    use w1::device::{Device, SlaveDevice};
+    use w1::thermometer::{Temperature, Thermometer, Units};
+
+    /// Thermometer double counting started reads.
+    struct Stub {
+        reads: Arc<AtomicUsize>,
+        /// Temperature returned by the reads, `None` simulates stalled bus (read never finishes).
+        temperature: Option<Temperature>,
+    }
 
-            self.reader_handler = Some(self.thermometer.temperature());
+    impl Stub {
+        /// Thermometer with stalled bus.
+        fn stalled() -> Stub {
+            Stub {
+                reads: Arc::new(AtomicUsize::new(0)),
+                temperature: None,
+            }
         }
 
-        if let Some(mut reader_handler) = self.reader_handler.take() {
-            match reader_handler.poll() {
-                Ok(Async::NotReady) => {
-                    self.reader_handler = Some(reader_handler);
-                    return Ok(Async::NotReady);
-                }
-                Ok(Async::Ready(t)) => {
-                    debug!(self.logger, "Temperature";
-                        "device" => self.thermometer.device_path(),
-                        "celsius" => t.to_string(Units::Celsius),
-                        "fahrenheit" => t.to_string(Units::Fahrenheit));
+        /// Thermometer returning the temperature.
+        ///
+        /// # Arguments
+        ///
+        /// * `temperature` - temperature returned by the reads
+        fn returning(temperature: Temperature) -> Stub {
+            Stub {
+                temperature: Some(temperature),
+                ..Stub::stalled()
+            }
+        }
+    }
 
-                    self.update_temperature(t);
-                }
-                Err(e) => {
-                    error!(self.logger, "Failed to read temperature";
-                        "error" => %e,
-                        "device" => self.thermometer.device_path());
-                }
-            };
+    impl Device for Stub {
+        fn device_path(&self) -> &str {
+            "stub"
+        }
+    }
+
+    impl SlaveDevice for Stub {}
 
-            self.delay_handler = Some(sleep(self.interval));
-        } else {
-            self.reader_handler = Some(self.thermometer.temperature());
+    impl Thermometer for Stub {
+        fn temperature(&self) -> Box<dyn Future<Item = Temperature, Error = Error> + Send> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            match self.temperature {
+                Some(temperature) => Box::new(future::ok(temperature)),
+                None => Box::new(future::empty()),
+            }
         }
+    }
+
+    #[test]
+    fn test_read_timeout() {
+        let mut runtime = Runtime::new().unwrap();
+        let result = runtime.block_on(TimedRead::new(&Stub::stalled(), Duration::from_millis(50)));
+
+        match result {
+            Err(Error::Timeout) => {}
+            other => panic!("Expected timeout, got {:?}", other),
+        };
+    }
+
+    #[test]
+    fn test_read_within_timeout() {
+        let mut runtime = Runtime::new().unwrap();
+        let thermometer = Stub::returning(Temperature::new(21_500));
+        let result = runtime.block_on(TimedRead::new(&thermometer, Duration::from_millis(50)));
+
+        assert_eq!(result.unwrap(), Temperature::new(21_500));
+        assert_eq!(thermometer.reads.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_single_read_in_flight() {
+        let thermometer = Stub::stalled();
+        let reads = thermometer.reads.clone();
+        let schedule = Schedule {
+            period: Duration::from_millis(20),
+            overrun: Overrun::Burst,
+            read_timeout: Duration::from_millis(10),
+        };
+        let reader = TemperatureReader::new(
+            Box::new(thermometer),
+            Location::Inside,
+            FilterChain::new(&[]),
+            schedule,
+            SharedState::new(10, Settings::new(Units::Celsius), None, vec![]),
+            Logger::root(Discard, o!()),
+        );
+
+        // Read times out after 10 ms, but it's still in flight during the following periods
+        let mut runtime = Runtime::new().unwrap();
+        let stop = Delay::new(Instant::now() + Duration::from_millis(200)).map_err(|_| ());
+        runtime
+            .block_on(reader.select(stop).map(|_| ()).map_err(|_| ()))
+            .unwrap();
+
+        assert_eq!(reads.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_phase_delay() {
+        let period = Duration::from_millis(500);
+
+        assert_eq!(
+            phase_delay(period, UNIX_EPOCH + Duration::from_millis(10_000)),
+            Duration::from_millis(0)
+        );
+        assert_eq!(
+            phase_delay(period, UNIX_EPOCH + Duration::from_millis(10_120)),
+            Duration::from_millis(380)
+        );
+        assert_eq!(
+            phase_delay(period, UNIX_EPOCH + Duration::from_millis(10_499)),
+            Duration::from_millis(1)
+        );
+    }
 
-        self.poll()
+    #[test]
+    fn test_tick_timestamp_on_grid() {
+        let timestamp = tick_timestamp(Instant::now(), Duration::from_millis(750));
+        assert_eq!(timestamp.timestamp_millis() % 750, 0);
     }
 }
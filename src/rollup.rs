@@ -0,0 +1,335 @@
+//! Aggregated (downsampled) readings for long-term storage.
+//!
+//! Raw readings are kept for the raw retention period, then they're compacted into
+//! 1-minute buckets. Minute buckets are kept for the minute retention period, then
+//! they're compacted into 1-hour buckets which are kept forever. Every bucket contains
+//! the number of readings and min / mean / max of the filtered temperatures.
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use error::Error;
+use state::{Location, Reading};
+use std::collections::HashMap;
+use std::str::FromStr;
+use w1::thermometer::Temperature;
+
+/// Readings resolution, ordered from the finest one.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug, Copy, Clone)]
+pub enum Resolution {
+    /// Raw readings.
+    Raw,
+    /// 1-minute buckets.
+    Minute,
+    /// 1-hour buckets.
+    Hour,
+}
+
+impl Resolution {
+    /// Bucket length in milliseconds (`1` for raw readings).
+    fn millis(self) -> i64 {
+        match self {
+            Resolution::Raw => 1,
+            Resolution::Minute => 60_000,
+            Resolution::Hour => 3_600_000,
+        }
+    }
+
+    /// Start of the bucket containing the timestamp.
+    ///
+    /// # Arguments
+    ///
+    /// * `timestamp` - timestamp
+    pub fn bucket_start(self, timestamp: DateTime<Utc>) -> DateTime<Utc> {
+        let millis = timestamp.timestamp_millis();
+        let bucket = self.millis();
+        from_millis(millis - (millis % bucket + bucket) % bucket)
+    }
+}
+
+impl AsRef<str> for Resolution {
+    fn as_ref(&self) -> &str {
+        match self {
+            Resolution::Raw => "raw",
+            Resolution::Minute => "minute",
+            Resolution::Hour => "hour",
+        }
+    }
+}
+
+impl FromStr for Resolution {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raw" => Ok(Resolution::Raw),
+            "minute" => Ok(Resolution::Minute),
+            "hour" => Ok(Resolution::Hour),
+            _ => Err(Error::from(format!("Invalid resolution: {}", s))),
+        }
+    }
+}
+
+/// Timestamp from milliseconds since the Unix epoch.
+fn from_millis(millis: i64) -> DateTime<Utc> {
+    Utc.timestamp(0, 0) + Duration::milliseconds(millis)
+}
+
+/// Retention periods.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Retention {
+    /// Raw readings are kept for this period.
+    pub raw: Duration,
+    /// Minute buckets are kept for this period.
+    pub minute: Duration,
+}
+
+impl Retention {
+    /// Raw readings taken before this time are compacted into minute buckets.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - current time
+    pub fn raw_cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        Resolution::Minute.bucket_start(now - self.raw)
+    }
+
+    /// Minute buckets starting before this time are compacted into hour buckets.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - current time
+    pub fn minute_cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        Resolution::Hour.bucket_start(now - self.minute)
+    }
+
+    /// Finest resolution available for the whole range starting at `from`.
+    ///
+    /// # Arguments
+    ///
+    /// * `from` - range start or `None` if the range is unbounded
+    /// * `now` - current time
+    pub fn resolution(&self, from: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Resolution {
+        match from {
+            Some(from) if from >= self.raw_cutoff(now) => Resolution::Raw,
+            Some(from) if from >= self.minute_cutoff(now) => Resolution::Minute,
+            _ => Resolution::Hour,
+        }
+    }
+}
+
+/// Aggregated readings.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Bucket {
+    /// Bucket start.
+    pub start: DateTime<Utc>,
+    /// Number of readings.
+    pub count: u64,
+    /// Lowest filtered temperature.
+    pub min: Temperature,
+    /// Mean filtered temperature (degrees celsius multiplied by 1_000.0).
+    pub mean: f64,
+    /// Highest filtered temperature.
+    pub max: Temperature,
+}
+
+impl Bucket {
+    /// Bucket with a single reading.
+    ///
+    /// # Arguments
+    ///
+    /// * `reading` - reading
+    pub fn from_reading(reading: &Reading) -> Bucket {
+        Bucket {
+            start: reading.timestamp,
+            count: 1,
+            min: reading.filtered,
+            mean: reading.filtered.value() as f64,
+            max: reading.filtered,
+        }
+    }
+
+    /// Mean filtered temperature.
+    pub fn mean(&self) -> Temperature {
+        Temperature::new(self.mean.round() as i64)
+    }
+
+    /// Merge other bucket into this one, start is not changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `other` - bucket to merge
+    fn merge(&mut self, other: &Bucket) {
+        let count = self.count + other.count;
+        self.mean = (self.mean * self.count as f64 + other.mean * other.count as f64) / count as f64;
+        self.count = count;
+        if other.min.value() < self.min.value() {
+            self.min = other.min;
+        }
+        if other.max.value() > self.max.value() {
+            self.max = other.max;
+        }
+    }
+}
+
+/// Aggregate buckets into buckets of the given resolution.
+///
+/// Returns buckets ordered by start time and location.
+///
+/// # Arguments
+///
+/// * `buckets` - buckets of the same or finer resolution
+/// * `resolution` - target resolution
+pub fn aggregate<I>(buckets: I, resolution: Resolution) -> Vec<(Location, Bucket)>
+where
+    I: IntoIterator<Item = (Location, Bucket)>,
+{
+    let mut aggregated: HashMap<(Location, DateTime<Utc>), Bucket> = HashMap::new();
+
+    for (location, bucket) in buckets {
+        let start = resolution.bucket_start(bucket.start);
+        aggregated
+            .entry((location, start))
+            .and_modify(|b| b.merge(&bucket))
+            .or_insert(Bucket { start, ..bucket });
+    }
+
+    let mut buckets: Vec<(Location, Bucket)> = aggregated.into_iter().map(|((l, _), b)| (l, b)).collect();
+    buckets.sort_by(|a, b| (a.1.start, a.0.as_ref()).cmp(&(b.1.start, b.0.as_ref())));
+    buckets
+}
+
+/// Format bucket as a storage record: `location,start,count,min,mean,max`.
+///
+/// # Arguments
+///
+/// * `location` - thermometer location
+/// * `bucket` - bucket
+pub fn format_bucket(location: Location, bucket: &Bucket) -> String {
+    format!(
+        "{},{},{},{},{},{}",
+        location.as_ref(),
+        bucket.start.timestamp_millis(),
+        bucket.count,
+        bucket.min.value(),
+        bucket.mean().value(),
+        bucket.max.value()
+    )
+}
+
+/// Parse bucket storage record.
+///
+/// # Arguments
+///
+/// * `line` - record line
+pub fn parse_bucket(line: &str) -> Result<(Location, Bucket), Error> {
+    let columns: Vec<&str> = line.trim().split(',').collect();
+    if columns.len() != 6 {
+        return Err(Error::Parse(format!("invalid bucket: {}", line)));
+    }
+
+    let parse_i64 = |s: &str| {
+        s.parse::<i64>()
+            .map_err(|e| Error::Parse(format!("invalid bucket value {}: {}", s, e)))
+    };
+
+    let count = parse_i64(columns[2])?;
+    if count <= 0 {
+        return Err(Error::Parse(format!("invalid bucket count: {}", line)));
+    }
+
+    let bucket = Bucket {
+        start: from_millis(parse_i64(columns[1])?),
+        count: count as u64,
+        min: Temperature::new(parse_i64(columns[3])?),
+        mean: parse_i64(columns[4])? as f64,
+        max: Temperature::new(parse_i64(columns[5])?),
+    };
+
+    Ok((columns[0].parse::<Location>()?, bucket))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{aggregate, format_bucket, parse_bucket, Bucket, Resolution, Retention};
+    use chrono::{Duration, TimeZone, Utc};
+    use state::{Location, Reading, Status};
+    use w1::thermometer::Temperature;
+
+    fn bucket(seconds: i64, value: i64) -> Bucket {
+        Bucket::from_reading(&Reading {
+            timestamp: Utc.ymd(2018, 7, 1).and_hms(12, 0, 0) + Duration::seconds(seconds),
+            raw: Temperature::new(value),
+            filtered: Temperature::new(value),
+            status: Status::Ok,
+        })
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let buckets = vec![
+            (Location::Inside, bucket(0, 20_000)),
+            (Location::Outside, bucket(10, 5_000)),
+            (Location::Inside, bucket(30, 21_000)),
+            (Location::Inside, bucket(59, 22_500)),
+            (Location::Inside, bucket(60, 23_000)),
+        ];
+
+        let minutes = aggregate(buckets.clone(), Resolution::Minute);
+        assert_eq!(minutes.len(), 3);
+        assert_eq!(minutes[0].0, Location::Inside);
+        assert_eq!(minutes[0].1.start, Utc.ymd(2018, 7, 1).and_hms(12, 0, 0));
+        assert_eq!(minutes[0].1.count, 3);
+        assert_eq!(minutes[0].1.min, Temperature::new(20_000));
+        assert_eq!(minutes[0].1.mean(), Temperature::new(21_167));
+        assert_eq!(minutes[0].1.max, Temperature::new(22_500));
+        assert_eq!(minutes[1].0, Location::Outside);
+        assert_eq!(minutes[2].1.start, Utc.ymd(2018, 7, 1).and_hms(12, 1, 0));
+
+        // Aggregated minutes give the same hour as raw readings
+        let hours = aggregate(minutes, Resolution::Hour);
+        assert_eq!(hours, aggregate(buckets, Resolution::Hour));
+        assert_eq!(hours[0].1.count, 4);
+        assert_eq!(hours[0].1.mean(), Temperature::new(21_625));
+    }
+
+    #[test]
+    fn test_bucket_round_trip() {
+        let bucket = aggregate(
+            vec![
+                (Location::Outside, bucket(0, -2_500)),
+                (Location::Outside, bucket(1, -2_000)),
+            ],
+            Resolution::Minute,
+        )[0]
+        .1;
+        let line = format_bucket(Location::Outside, &bucket);
+        assert_eq!(line, "outside,1530446400000,2,-2500,-2250,-2000");
+        assert_eq!(parse_bucket(&line).unwrap(), (Location::Outside, bucket));
+        assert!(parse_bucket("outside,1530446400000,0,-2500,-2250,-2000").is_err());
+        assert!(parse_bucket("outside,1530446400000,2,-2500").is_err());
+    }
+
+    #[test]
+    fn test_retention() {
+        let retention = Retention {
+            raw: Duration::days(7),
+            minute: Duration::days(90),
+        };
+        let now = Utc.ymd(2018, 7, 10).and_hms(12, 30, 30);
+
+        assert_eq!(retention.raw_cutoff(now), Utc.ymd(2018, 7, 3).and_hms(12, 30, 0));
+        assert_eq!(retention.minute_cutoff(now), Utc.ymd(2018, 4, 11).and_hms(12, 0, 0));
+
+        assert_eq!(
+            retention.resolution(Some(now - Duration::days(1)), now),
+            Resolution::Raw
+        );
+        assert_eq!(
+            retention.resolution(Some(now - Duration::days(30)), now),
+            Resolution::Minute
+        );
+        assert_eq!(
+            retention.resolution(Some(now - Duration::days(365)), now),
+            Resolution::Hour
+        );
+        assert_eq!(retention.resolution(None, now), Resolution::Hour);
+    }
+}
@@ -0,0 +1,114 @@
+//! User settings changed at runtime (from the UI, ...).
+//!
+//! Settings are persisted in the `settings.json` file in the data folder.
+use error::Error;
+use serde_json;
+use std::fs::{self, File};
+use std::io::{BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use w1::thermometer::Units;
+
+/// Settings file name inside the data folder.
+const SETTINGS_FILE_NAME: &str = "settings.json";
+
+/// User settings.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    /// Temperature units.
+    pub units: Units,
+}
+
+impl Settings {
+    /// Create new `Settings`.
+    ///
+    /// # Arguments
+    ///
+    /// * `units` - temperature units
+    pub fn new(units: Units) -> Settings {
+        Settings { units }
+    }
+
+    /// Settings file path.
+    ///
+    /// # Arguments
+    ///
+    /// * `folder` - data folder
+    fn path<P>(folder: P) -> PathBuf
+    where
+        P: AsRef<Path>,
+    {
+        folder.as_ref().join(SETTINGS_FILE_NAME)
+    }
+
+    /// Load settings, returns `None` if settings weren't saved yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `folder` - data folder
+    pub fn load<P>(folder: P) -> Result<Option<Settings>, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = Settings::path(folder);
+
+        let file = match File::open(&path) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(Error::from(e).context(format!("Unable to open {}", path.display()))),
+        };
+
+        serde_json::from_reader(BufReader::new(file))
+            .map(Some)
+            .map_err(|e| Error::Parse(format!("invalid settings {}: {}", path.display(), e)))
+    }
+
+    /// Save settings.
+    ///
+    /// Settings are written into a temporary file which replaces the current one,
+    /// so the settings file is never left half written.
+    ///
+    /// # Arguments
+    ///
+    /// * `folder` - data folder
+    pub fn save<P>(&self, folder: P) -> Result<(), Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = Settings::path(&folder);
+        let tmp_path = path.with_extension("json.tmp");
+
+        let json = serde_json::to_string_pretty(self).map_err(|e| Error::from(format!("JSON error: {}", e)))?;
+
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(json.as_bytes())?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, &path)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Settings;
+    use std::env;
+    use std::fs;
+    use w1::thermometer::Units;
+
+    #[test]
+    fn test_save_and_load() {
+        let folder = env::temp_dir().join("thermometer-settings-test");
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+
+        assert_eq!(Settings::load(&folder).unwrap(), None);
+
+        Settings::new(Units::Kelvin).save(&folder).unwrap();
+        assert_eq!(Settings::load(&folder).unwrap(), Some(Settings::new(Units::Kelvin)));
+
+        fs::write(folder.join("settings.json"), "{\"units\":\"rankine\"}").unwrap();
+        assert!(Settings::load(&folder).is_err());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
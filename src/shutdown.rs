@@ -0,0 +1,62 @@
+//! Graceful shutdown on `SIGTERM` & `SIGINT`.
+//!
+//! Docker (resinOS) stops the container with `SIGTERM` and kills it a few seconds later.
+//! Signal handler only sets the flag, the UI loop polls it and returns, then the background
+//! processing is stopped and the buffered readings are written to the storage before the
+//! process exits.
+use error::Error;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set when the termination signal was received.
+static TERMINATED: AtomicBool = AtomicBool::new(false);
+
+/// Check if the termination signal was received.
+pub fn terminated() -> bool {
+    TERMINATED.load(Ordering::SeqCst)
+}
+
+/// Signal handler.
+#[cfg(unix)]
+extern "C" fn handle(_: ::libc::c_int) {
+    // Only async-signal-safe operations are allowed here
+    TERMINATED.store(true, Ordering::SeqCst);
+}
+
+/// Install `SIGTERM` & `SIGINT` handlers.
+#[cfg(unix)]
+pub fn install() -> Result<(), Error> {
+    use libc;
+    use std::io;
+
+    for signum in &[libc::SIGINT, libc::SIGTERM] {
+        let handler = handle as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        if unsafe { libc::signal(*signum, handler) } == libc::SIG_ERR {
+            return Err(Error::from(io::Error::last_os_error()).context(format!("Unable to handle signal {}", signum)));
+        }
+    }
+    Ok(())
+}
+
+/// Install `SIGTERM` & `SIGINT` handlers.
+#[cfg(not(unix))]
+pub fn install() -> Result<(), Error> {
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::{handle, terminated, TERMINATED};
+    use libc;
+    use std::sync::atomic::Ordering;
+
+    #[test]
+    fn test_terminated() {
+        assert!(!terminated());
+
+        // Handler is called directly, real signal would hit the whole test process
+        handle(libc::SIGTERM);
+        assert!(terminated());
+
+        TERMINATED.store(false, Ordering::SeqCst);
+    }
+}
@@ -1,56 +1,698 @@
-use std::sync::{Arc, Mutex};
-use w1::thermometer::Temperature;
+use chrono::{DateTime, Duration, Utc};
+use derived::VirtualSensor;
+use error::Error;
+use futures::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use rollup::Retention;
+use settings::Settings;
+use std::collections::{HashMap, VecDeque};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, RwLock};
+use std::time::Instant;
+use storage::{Compaction, Storage};
+use w1::thermometer::{Temperature, Units};
+use weather::Forecast;
+
+lazy_static! {
+    /// Sensor names, names are never freed so `Location` can stay `Copy`.
+    static ref NAMES: Mutex<Vec<&'static str>> = Mutex::new(vec![]);
+}
+
+/// Interned sensor name.
+///
+/// # Arguments
+///
+/// * `name` - sensor name
+fn intern(name: &str) -> &'static str {
+    let mut names = lock(&NAMES);
+
+    match names.iter().find(|n| **n == name) {
+        Some(interned) => interned,
+        None => {
+            let interned: &'static str = Box::leak(name.to_string().into_boxed_str());
+            names.push(interned);
+            interned
+        }
+    }
+}
+
+/// Lock the mutex, a panic in another thread while holding the lock (poisoned mutex)
+/// is ignored.
+///
+/// # Arguments
+///
+/// * `mutex` - mutex to lock
+fn lock<'a, T>(mutex: &'a Mutex<T>) -> MutexGuard<'a, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Thermometer location.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Location {
+    Inside,
+    Outside,
+    /// Sensor identified by name (additional, remote or virtual sensor).
+    Named(&'static str),
+}
+
+impl AsRef<str> for Location {
+    fn as_ref(&self) -> &str {
+        match self {
+            Location::Inside => "inside",
+            Location::Outside => "outside",
+            Location::Named(name) => name,
+        }
+    }
+}
+
+impl FromStr for Location {
+    type Err = Error;
+
+    /// Any other name than `inside` & `outside` made of lowercase letters, digits
+    /// and underscores (starting with a letter) is a named sensor.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "inside" => Ok(Location::Inside),
+            "outside" => Ok(Location::Outside),
+            _ if s.starts_with(|c: char| c.is_ascii_lowercase())
+                && s.chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') =>
+            {
+                Ok(Location::Named(intern(s)))
+            }
+            _ => Err(Error::from(format!("Invalid thermometer location: {}", s))),
+        }
+    }
+}
+
+/// Reading status.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Status {
+    /// Raw value was accepted by filters.
+    Ok,
+    /// Raw value was rejected by filters, filtered value is the last accepted one.
+    Rejected,
+}
+
+impl AsRef<str> for Status {
+    fn as_ref(&self) -> &str {
+        match self {
+            Status::Ok => "ok",
+            Status::Rejected => "rejected",
+        }
+    }
+}
+
+impl FromStr for Status {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ok" => Ok(Status::Ok),
+            "rejected" => Ok(Status::Rejected),
+            _ => Err(Error::from(format!("Invalid reading status: {}", s))),
+        }
+    }
+}
+
+/// Temperature reading.
+#[derive(Clone, Copy, Debug)]
+pub struct Reading {
+    /// Time at which the temperature was read.
+    pub timestamp: DateTime<Utc>,
+    /// Temperature as read from the sensor.
+    pub raw: Temperature,
+    /// Filtered temperature (last accepted value if the raw one was rejected).
+    pub filtered: Temperature,
+    /// Reading status.
+    pub status: Status,
+}
 
 /// Application state.
 #[derive(Clone)]
 pub struct State {
-    pub inside_temperature: Option<Temperature>,
-    pub outside_temperature: Option<Temperature>,
+    pub inside_temperature: Option<Reading>,
+    pub outside_temperature: Option<Reading>,
+    /// Named (additional, remote & virtual) sensor readings.
+    pub named_temperatures: HashMap<Location, Reading>,
+    /// Today's weather forecast.
+    pub forecast: Option<Forecast>,
+    /// System clock is synchronised, reading timestamps can be trusted.
+    pub clock_synchronized: bool,
+    pub settings: Settings,
 }
 
-impl Default for State {
-    fn default() -> State {
+impl State {
+    /// Create new `State` without readings.
+    ///
+    /// # Arguments
+    ///
+    /// * `settings` - user settings
+    pub fn new(settings: Settings) -> State {
         State {
             inside_temperature: None,
             outside_temperature: None,
+            named_temperatures: HashMap::new(),
+            forecast: None,
+            clock_synchronized: true,
+            settings,
+        }
+    }
+
+    /// Last sensor reading.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - sensor location
+    pub fn temperature(&self, location: Location) -> Option<Reading> {
+        match location {
+            Location::Inside => self.inside_temperature,
+            Location::Outside => self.outside_temperature,
+            Location::Named(_) => self.named_temperatures.get(&location).cloned(),
         }
     }
 }
 
-/// Shared cloneable application state.
+/// Recent readings history.
+pub struct History {
+    size: usize,
+    readings: HashMap<Location, VecDeque<Reading>>,
+}
+
+impl History {
+    /// Create new `History`.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - max number of readings kept per location
+    pub fn new(size: usize) -> History {
+        History {
+            size,
+            readings: HashMap::new(),
+        }
+    }
+
+    /// Append reading, oldest reading is dropped if the history is full.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - thermometer location
+    /// * `reading` - new reading
+    pub fn push(&mut self, location: Location, reading: Reading) {
+        let size = self.size;
+        let readings = self
+            .readings
+            .entry(location)
+            .or_insert_with(|| VecDeque::with_capacity(size));
+
+        if readings.len() == size {
+            readings.pop_front();
+        }
+        readings.push_back(reading);
+    }
+
+    /// Readings, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - thermometer location
+    pub fn readings(&self, location: Location) -> Vec<Reading> {
+        self.readings
+            .get(&location)
+            .map(|r| r.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Readings taken at or after the given time, oldest first.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - thermometer location
+    /// * `since` - oldest reading time
+    pub fn readings_since(&self, location: Location, since: DateTime<Utc>) -> Vec<Reading> {
+        let readings = match self.readings.get(&location) {
+            Some(readings) => readings,
+            None => return vec![],
+        };
+
+        // Readings are ordered, no need to check all of them
+        let count = readings.iter().rev().take_while(|r| r.timestamp >= since).count();
+        readings.iter().skip(readings.len() - count).cloned().collect()
+    }
+}
+
+/// Sensor update event.
+#[derive(Clone, Copy, Debug)]
+pub struct Update {
+    /// Updated sensor.
+    pub location: Location,
+    /// New reading.
+    pub reading: Reading,
+}
+
+/// Update events subscriber.
+struct Subscriber {
+    /// Subscribed sensors, `None` if subscribed to all sensors.
+    locations: Option<Vec<Location>>,
+    sender: UnboundedSender<Update>,
+}
+
+impl Subscriber {
+    /// Send the update if the subscriber is interested in it.
+    ///
+    /// Returns `false` if the subscriber is gone (stream was dropped).
+    fn send(&self, update: Update) -> bool {
+        match self.locations {
+            Some(ref locations) if !locations.contains(&update.location) => true,
+            _ => self.sender.unbounded_send(update).is_ok(),
+        }
+    }
+}
+
+/// Shared cloneable application state (state hub).
+///
+/// Readers get immutable `State` snapshots which are replaced as a whole on every
+/// update (copy on write), so reading never waits for an update in progress and a
+/// snapshot is never partially updated. Subscribers receive sensor updates as a stream.
 #[derive(Clone)]
 pub struct SharedState {
-    state: Arc<Mutex<State>>,
+    state: Arc<RwLock<Arc<State>>>,
+    /// Serialises state updates.
+    writer: Arc<Mutex<()>>,
+    subscribers: Arc<Mutex<Vec<Subscriber>>>,
+    history: Arc<Mutex<History>>,
+    storage: Option<Arc<Mutex<Storage>>>,
+    virtual_sensors: Arc<Vec<VirtualSensor>>,
+    /// Readings taken while the clock is not synchronised, with their monotonic time.
+    held: Arc<Mutex<VecDeque<(Location, Reading, Instant)>>>,
+    history_size: usize,
+    /// Incremented whenever the state changes.
+    version: Arc<AtomicUsize>,
 }
 
 impl SharedState {
     /// Create new `SharedState`.
-    pub fn new() -> SharedState {
+    ///
+    /// # Arguments
+    ///
+    /// * `history_size` - max number of readings kept in history per location
+    /// * `settings` - user settings
+    /// * `storage` - persistent storage for readings & settings or `None` if they shouldn't be persisted
+    /// * `virtual_sensors` - virtual sensors evaluated whenever the sensor readings change
+    pub fn new(
+        history_size: usize,
+        settings: Settings,
+        storage: Option<Storage>,
+        virtual_sensors: Vec<VirtualSensor>,
+    ) -> SharedState {
         SharedState {
-            state: Arc::new(Mutex::new(State::default())),
+            state: Arc::new(RwLock::new(Arc::new(State::new(settings)))),
+            writer: Arc::new(Mutex::new(())),
+            subscribers: Arc::new(Mutex::new(vec![])),
+            history: Arc::new(Mutex::new(History::new(history_size))),
+            storage: storage.map(|s| Arc::new(Mutex::new(s))),
+            virtual_sensors: Arc::new(virtual_sensors),
+            held: Arc::new(Mutex::new(VecDeque::new())),
+            history_size,
+            version: Arc::new(AtomicUsize::new(0)),
         }
     }
 
-    /// Set inside temperature.
+    /// Set sensor temperature, virtual sensors are evaluated again.
+    ///
+    /// State is always updated, error is returned if the reading can't be persisted.
     ///
     /// # Arguments
     ///
-    /// * `value` - new inside temperature
-    pub fn set_inside_temperature(&self, value: Temperature) {
-        self.state.lock().unwrap().inside_temperature = Some(value);
+    /// * `location` - sensor location
+    /// * `value` - new temperature
+    pub fn set_temperature(&self, location: Location, value: Reading) -> Result<(), Error> {
+        self.update(|state| match location {
+            Location::Inside => state.inside_temperature = Some(value),
+            Location::Outside => state.outside_temperature = Some(value),
+            Location::Named(_) => {
+                state.named_temperatures.insert(location, value);
+            }
+        });
+        self.publish(location, value);
+        self.record(location, value)?;
+        self.update_virtual_sensors()
     }
 
-    /// Set outside temperature
+    /// Evaluate virtual sensors (in the order in which they are defined).
+    ///
+    /// Virtual sensor readings are recorded only when all their inputs were updated
+    /// since the last recorded reading, state is always updated.
+    fn update_virtual_sensors(&self) -> Result<(), Error> {
+        if self.virtual_sensors.is_empty() {
+            return Ok(());
+        }
+
+        let mut evaluated = vec![];
+        let mut updated = vec![];
+        self.update(|state| {
+            for sensor in self.virtual_sensors.iter() {
+                let reading = match sensor.expression.evaluate(|l| state.temperature(l)) {
+                    Some(reading) => reading,
+                    None => continue,
+                };
+
+                let previous = state.named_temperatures.insert(sensor.location, reading);
+                if previous.map(|p| reading.timestamp > p.timestamp).unwrap_or(true) {
+                    updated.push((sensor.location, reading));
+                }
+                evaluated.push((sensor.location, reading));
+            }
+        });
+
+        for (location, reading) in evaluated {
+            self.publish(location, reading);
+        }
+        updated
+            .into_iter()
+            .try_for_each(|(location, reading)| self.record(location, reading))
+    }
+
+    /// Append reading to history and persist it.
+    ///
+    /// Readings are held back while the clock is not synchronised, at most history size
+    /// readings are held.
+    fn record(&self, location: Location, value: Reading) -> Result<(), Error> {
+        if !self.state().clock_synchronized {
+            let mut held = lock(&self.held);
+            if held.len() == self.history_size {
+                held.pop_front();
+            }
+            held.push_back((location, value, Instant::now()));
+            return Ok(());
+        }
+
+        lock(&self.history).push(location, value);
+
+        match self.storage {
+            Some(ref storage) => lock(storage).append(location, &value),
+            None => Ok(()),
+        }
+    }
+
+    /// Set system clock synchronisation state.
+    ///
+    /// Held back readings are recorded once the clock is synchronised, their timestamps
+    /// are corrected using the time elapsed since they were taken.
+    ///
+    /// # Arguments
+    ///
+    /// * `synchronized` - clock is synchronised
+    /// * `now` - current (synchronised) time
+    pub fn set_clock_synchronized(&self, synchronized: bool, now: DateTime<Utc>) -> Result<(), Error> {
+        if self.state().clock_synchronized != synchronized {
+            self.update(|state| state.clock_synchronized = synchronized);
+        }
+        if !synchronized {
+            return Ok(());
+        }
+
+        let held: Vec<(Location, Reading, Instant)> = lock(&self.held).drain(..).collect();
+        held.into_iter().try_for_each(|(location, reading, taken)| {
+            let elapsed = Duration::from_std(taken.elapsed()).unwrap_or_else(|_| Duration::zero());
+            let timestamp = now - elapsed;
+            self.record(location, Reading { timestamp, ..reading })
+        })
+    }
+
+    /// Write buffered readings to the storage.
+    pub fn flush_storage(&self) -> Result<(), Error> {
+        match self.storage {
+            Some(ref storage) => lock(storage).flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Compact stored readings, nothing is compacted if the readings are not persisted.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - current time
+    /// * `retention` - retention periods
+    pub fn compact_storage(&self, now: DateTime<Utc>, retention: &Retention) -> Result<Compaction, Error> {
+        match self.storage {
+            Some(ref storage) => lock(storage).compact(now, retention),
+            None => Ok(Compaction::default()),
+        }
+    }
+
+    /// Set temperature units.
+    ///
+    /// State is always updated, error is returned if settings can't be persisted.
+    ///
+    /// # Arguments
+    ///
+    /// * `units` - new temperature units
+    pub fn set_units(&self, units: Units) -> Result<(), Error> {
+        let settings = self.update(|state| {
+            state.settings.units = units;
+            state.settings
+        });
+
+        match self.storage {
+            Some(ref storage) => settings.save(lock(storage).folder()),
+            None => Ok(()),
+        }
+    }
+
+    /// Set today's weather forecast.
+    ///
+    /// # Arguments
+    ///
+    /// * `forecast` - forecast or `None` if there's no forecast for today
+    pub fn set_forecast(&self, forecast: Option<Forecast>) {
+        self.update(|state| state.forecast = forecast);
+    }
+
+    /// History snapshot, oldest readings first.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - thermometer location
+    pub fn history(&self, location: Location) -> Vec<Reading> {
+        lock(&self.history).readings(location)
+    }
+
+    /// Recent history snapshot, oldest readings first.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - thermometer location
+    /// * `since` - oldest reading time
+    pub fn history_since(&self, location: Location, since: DateTime<Utc>) -> Vec<Reading> {
+        lock(&self.history).readings_since(location, since)
+    }
+
+    /// State version, the version changes whenever the `State` changes.
+    ///
+    /// Cheap to check, e.g. to redraw the UI only when something changed.
+    pub fn version(&self) -> usize {
+        self.version.load(Ordering::SeqCst)
+    }
+
+    /// `State` snapshot, snapshot is not copied and it doesn't change.
+    pub fn state(&self) -> Arc<State> {
+        self.state.read().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Subscribe to sensor updates, subscription ends when the stream is dropped.
+    ///
+    /// # Arguments
+    ///
+    /// * `locations` - subscribed sensors, `None` for all sensors (virtual sensors included)
+    pub fn subscribe(&self, locations: Option<Vec<Location>>) -> UnboundedReceiver<Update> {
+        let (sender, receiver) = mpsc::unbounded();
+        lock(&self.subscribers).push(Subscriber { locations, sender });
+        receiver
+    }
+
+    /// Send sensor update to subscribers, dropped subscriptions are removed.
     ///
     /// # Arguments
     ///
-    /// * `value` - new outside temperature
-    pub fn set_outside_temperature(&self, value: Temperature) {
-        self.state.lock().unwrap().outside_temperature = Some(value);
+    /// * `location` - updated sensor
+    /// * `reading` - new reading
+    fn publish(&self, location: Location, reading: Reading) {
+        let update = Update { location, reading };
+        lock(&self.subscribers).retain(|s| s.send(update));
+    }
+
+    /// Update state, new snapshot replaces the current one.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - updates a copy of the current snapshot
+    fn update<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut State) -> R,
+    {
+        let _writer = lock(&self.writer);
+        let mut state = State::clone(&self.state());
+        let result = f(&mut state);
+
+        *self.state.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(state);
+        self.version.fetch_add(1, Ordering::SeqCst);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{History, Location, Reading, SharedState, Status, Update};
+    use chrono::{Duration, TimeZone, Utc};
+    use derived::parse_virtual_sensors;
+    use futures::Stream;
+    use settings::Settings;
+    use w1::thermometer::{Temperature, Units};
+
+    fn reading(value: i64) -> Reading {
+        Reading {
+            timestamp: Utc::now(),
+            raw: Temperature::new(value),
+            filtered: Temperature::new(value),
+            status: Status::Ok,
+        }
+    }
+
+    #[test]
+    fn test_history_drops_oldest_readings() {
+        let mut history = History::new(2);
+        history.push(Location::Inside, reading(1));
+        history.push(Location::Inside, reading(2));
+        history.push(Location::Inside, reading(3));
+        history.push(Location::Outside, reading(4));
+
+        let inside: Vec<i64> = history.readings[&Location::Inside]
+            .iter()
+            .map(|r| r.raw.value())
+            .collect();
+        assert_eq!(inside, vec![2, 3]);
+        assert_eq!(history.readings[&Location::Outside].len(), 1);
     }
 
-    /// `State` snapshot.
-    pub fn state(&self) -> State {
-        self.state.lock().unwrap().clone()
+    #[test]
+    fn test_history_readings_since() {
+        let mut history = History::new(10);
+        let mut first = reading(1);
+        first.timestamp = first.timestamp - Duration::minutes(10);
+        history.push(Location::Inside, first);
+        history.push(Location::Inside, reading(2));
+        history.push(Location::Inside, reading(3));
+
+        let since = Utc::now() - Duration::minutes(5);
+        let values: Vec<i64> = history
+            .readings_since(Location::Inside, since)
+            .iter()
+            .map(|r| r.raw.value())
+            .collect();
+        assert_eq!(values, vec![2, 3]);
+        assert!(history.readings_since(Location::Outside, since).is_empty());
+    }
+
+    #[test]
+    fn test_location() {
+        assert_eq!("inside".parse::<Location>().unwrap(), Location::Inside);
+        assert_eq!("delta_1".parse::<Location>().unwrap().as_ref(), "delta_1");
+        assert_eq!("room".parse::<Location>().unwrap(), "room".parse::<Location>().unwrap());
+        assert!("".parse::<Location>().is_err());
+        assert!("1st".parse::<Location>().is_err());
+        assert!("Room".parse::<Location>().is_err());
+        assert!("a-b".parse::<Location>().is_err());
+    }
+
+    #[test]
+    fn test_virtual_sensors() {
+        let sensors = parse_virtual_sensors("delta = inside - outside").unwrap();
+        let delta = sensors[0].location;
+        let state = SharedState::new(10, Settings::new(Units::Celsius), None, sensors);
+
+        state.set_temperature(Location::Inside, reading(21_000)).unwrap();
+        assert!(state.state().temperature(delta).is_none());
+
+        let mut outside = reading(5_000);
+        outside.timestamp = outside.timestamp - Duration::seconds(1);
+        state.set_temperature(Location::Outside, outside).unwrap();
+        assert_eq!(state.state().temperature(delta).unwrap().filtered.value(), 16_000);
+        assert_eq!(state.history(delta).len(), 1);
+
+        // Inside updated again, outside reading is the same, nothing new to record
+        state.set_temperature(Location::Inside, reading(22_000)).unwrap();
+        assert_eq!(state.state().temperature(delta).unwrap().filtered.value(), 17_000);
+        assert_eq!(state.history(delta).len(), 1);
+
+        state.set_temperature(Location::Outside, reading(4_000)).unwrap();
+        assert_eq!(state.history(delta).len(), 2);
+    }
+
+    #[test]
+    fn test_readings_held_until_clock_synchronized() {
+        let state = SharedState::new(2, Settings::new(Units::Celsius), None, vec![]);
+        let unsynced = Utc.ymd(1970, 1, 1).and_hms(0, 0, 42);
+        state.set_clock_synchronized(false, unsynced).unwrap();
+
+        for value in 1..4 {
+            let mut reading = reading(value);
+            reading.timestamp = unsynced;
+            state.set_temperature(Location::Inside, reading).unwrap();
+        }
+        assert_eq!(state.state().temperature(Location::Inside).unwrap().raw.value(), 3);
+        assert!(state.history(Location::Inside).is_empty());
+
+        let now = Utc::now();
+        state.set_clock_synchronized(true, now).unwrap();
+        let history = state.history(Location::Inside);
+        let values: Vec<i64> = history.iter().map(|r| r.raw.value()).collect();
+        assert_eq!(values, vec![2, 3]);
+        assert!(history
+            .iter()
+            .all(|r| r.timestamp <= now && r.timestamp > now - Duration::seconds(5)));
+    }
+
+    #[test]
+    fn test_version() {
+        let state = SharedState::new(10, Settings::new(Units::Celsius), None, vec![]);
+        let initial = state.version();
+
+        state.set_temperature(Location::Inside, reading(21_000)).unwrap();
+        let updated = state.version();
+        assert_ne!(updated, initial);
+        assert_eq!(state.clone().version(), updated);
+
+        // Clock state didn't change
+        state.set_clock_synchronized(true, Utc::now()).unwrap();
+        assert_eq!(state.version(), updated);
+
+        state.set_units(Units::Fahrenheit).unwrap();
+        assert_ne!(state.version(), updated);
+    }
+
+    #[test]
+    fn test_subscriptions() {
+        let sensors = parse_virtual_sensors("delta = inside - outside").unwrap();
+        let delta = sensors[0].location;
+        let state = SharedState::new(10, Settings::new(Units::Celsius), None, sensors);
+        let all = state.subscribe(None);
+        let outside = state.subscribe(Some(vec![Location::Outside]));
+        let dropped = state.subscribe(Some(vec![Location::Inside]));
+        drop(dropped);
+
+        let snapshot = state.state();
+        state.set_temperature(Location::Inside, reading(21_000)).unwrap();
+        state.set_temperature(Location::Outside, reading(5_000)).unwrap();
+        // Snapshot taken before the updates doesn't change
+        assert!(snapshot.temperature(Location::Inside).is_none());
+        assert_eq!(state.subscribers.lock().unwrap().len(), 2);
+        drop(state);
+
+        let locations: Vec<Location> = all.wait().map(|u| u.unwrap().location).collect();
+        assert_eq!(locations, vec![Location::Inside, Location::Outside, delta]);
+        let updates: Vec<Update> = outside.wait().map(|u| u.unwrap()).collect();
+        assert_eq!(updates.len(), 1);
+        assert_eq!(updates[0].reading.raw.value(), 5_000);
     }
 }
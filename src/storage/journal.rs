@@ -0,0 +1,247 @@
+//! Write-ahead journal for replacing several storage files at once.
+//!
+//! New contents are written to temporary files first. Then the journal file lists
+//! the replacements followed by the `commit` line and it's synced to the disk. Files
+//! are renamed afterwards and the journal is removed. Power loss in any step leaves
+//! either the old or the new content of all files, `recover` finishes the committed
+//! replacements and discards the uncommitted ones.
+use error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+/// Journal file name inside the data folder.
+const JOURNAL_FILE_NAME: &str = "journal";
+
+/// Last journal line marking complete journal.
+const COMMIT: &str = "commit";
+
+/// Temporary file path for the new content of the file.
+///
+/// # Arguments
+///
+/// * `path` - file path
+fn temporary(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    path.with_file_name(name)
+}
+
+/// Sync folder so that renames & removals are persisted.
+///
+/// # Arguments
+///
+/// * `folder` - folder path
+fn sync_folder(folder: &Path) -> Result<(), Error> {
+    File::open(folder)?.sync_all()?;
+    Ok(())
+}
+
+/// Create file, write its content and sync it to the disk.
+///
+/// # Arguments
+///
+/// * `path` - file path
+/// * `write` - writes the file content
+fn create_synced<F>(path: &Path, write: F) -> Result<(), Error>
+where
+    F: FnOnce(&mut dyn Write) -> Result<(), Error>,
+{
+    let file =
+        File::create(path).map_err(|e| Error::from(e).context(format!("Unable to create {}", path.display())))?;
+    let mut writer = BufWriter::new(file);
+    write(&mut writer)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+    Ok(())
+}
+
+/// Write file with lines and sync it to the disk.
+///
+/// # Arguments
+///
+/// * `path` - file path
+/// * `lines` - file content
+fn write_synced(path: &Path, lines: &[String]) -> Result<(), Error> {
+    create_synced(path, |writer| {
+        for line in lines {
+            writeln!(writer, "{}", line)?;
+        }
+        Ok(())
+    })
+}
+
+/// Rename temporary files to the target files & remove the journal.
+///
+/// # Arguments
+///
+/// * `folder` - data folder
+/// * `paths` - target file paths
+fn apply(folder: &Path, paths: &[PathBuf]) -> Result<(), Error> {
+    for path in paths {
+        let temporary = temporary(path);
+        match fs::rename(&temporary, path) {
+            // Already renamed before the crash
+            Err(ref e) if e.kind() == ErrorKind::NotFound => {}
+            result => result.map_err(|e| Error::from(e).context(format!("Unable to replace {}", path.display())))?,
+        };
+    }
+    sync_folder(folder)?;
+
+    fs::remove_file(folder.join(JOURNAL_FILE_NAME))?;
+    sync_folder(folder)
+}
+
+/// Replace contents of the files in the data folder atomically.
+///
+/// # Arguments
+///
+/// * `folder` - data folder
+/// * `paths` - replaced file paths
+/// * `write` - writes the new content of the file with the given path, new contents
+///   are written before any file is replaced so the old files can be read
+pub fn replace<F>(folder: &Path, paths: &[PathBuf], mut write: F) -> Result<(), Error>
+where
+    F: FnMut(&Path, &mut dyn Write) -> Result<(), Error>,
+{
+    for (index, path) in paths.iter().enumerate() {
+        if let Err(e) = create_synced(&temporary(path), |writer| write(path, writer)) {
+            // Nothing was committed, old contents are still in place
+            for path in &paths[..=index] {
+                let _ = fs::remove_file(temporary(path));
+            }
+            return Err(e);
+        }
+    }
+
+    let mut journal: Vec<String> = paths.iter().map(|path| path.display().to_string()).collect();
+    journal.push(COMMIT.to_string());
+    write_synced(&folder.join(JOURNAL_FILE_NAME), &journal)?;
+    sync_folder(folder)?;
+
+    apply(folder, paths)
+}
+
+/// Finish or discard interrupted replacement.
+///
+/// Returns `true` if committed replacement was finished.
+///
+/// # Arguments
+///
+/// * `folder` - data folder
+pub fn recover(folder: &Path) -> Result<bool, Error> {
+    let path = folder.join(JOURNAL_FILE_NAME);
+    let journal = match fs::read_to_string(&path) {
+        Ok(journal) => journal,
+        Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(Error::from(e).context(format!("Unable to read {}", path.display()))),
+    };
+
+    let mut lines: Vec<&str> = journal.lines().collect();
+    let committed = journal.ends_with('\n') && lines.pop() == Some(COMMIT);
+    let paths: Vec<PathBuf> = lines.iter().map(PathBuf::from).collect();
+
+    if committed {
+        apply(folder, &paths)?;
+    } else {
+        // Journal was not complete, old contents are still in place
+        for path in &paths {
+            let _ = fs::remove_file(temporary(path));
+        }
+        fs::remove_file(&path)?;
+        sync_folder(folder)?;
+    }
+
+    Ok(committed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{recover, replace, temporary, write_synced, JOURNAL_FILE_NAME};
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn test_replace_and_recover() {
+        let folder = env::temp_dir().join("thermometer-journal-test");
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        let a = folder.join("a.csv");
+        let b = folder.join("b.csv");
+
+        replace(&folder, &[a.clone(), b.clone()], |path, writer| {
+            if path == a {
+                writeln!(writer, "1")?;
+            }
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(fs::read_to_string(&a).unwrap(), "1\n");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "");
+        assert!(!folder.join(JOURNAL_FILE_NAME).exists());
+        assert!(!recover(&folder).unwrap());
+
+        // Crash after the commit, `a` was already renamed
+        let journal = vec![a.display().to_string(), b.display().to_string(), "commit".to_string()];
+        fs::write(&a, "2\n").unwrap();
+        write_synced(&temporary(&b), &["3".to_string()]).unwrap();
+        write_synced(&folder.join(JOURNAL_FILE_NAME), &journal).unwrap();
+        assert!(recover(&folder).unwrap());
+        assert_eq!(fs::read_to_string(&a).unwrap(), "2\n");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "3\n");
+        assert!(!folder.join(JOURNAL_FILE_NAME).exists());
+
+        // Crash before the commit
+        write_synced(&temporary(&a), &["4".to_string()]).unwrap();
+        fs::write(folder.join(JOURNAL_FILE_NAME), a.display().to_string()).unwrap();
+        assert!(!recover(&folder).unwrap());
+        assert_eq!(fs::read_to_string(&a).unwrap(), "2\n");
+        assert!(!temporary(&a).exists());
+        assert!(!folder.join(JOURNAL_FILE_NAME).exists());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_interrupted_replace() {
+        let folder = env::temp_dir().join("thermometer-journal-interrupted-test");
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        let a = folder.join("a.csv");
+        fs::write(&a, "1\n").unwrap();
+
+        // Write of the second file fails after the first one was written
+        let b = folder.join("missing").join("b.csv");
+        let written = replace(&folder, &[a.clone(), b.clone()], |path, writer| {
+            if path == a {
+                writeln!(writer, "2")?;
+            }
+            Ok(())
+        });
+        assert!(written.is_err());
+        assert_eq!(fs::read_to_string(&a).unwrap(), "1\n");
+        assert!(!temporary(&a).exists());
+        assert!(!folder.join(JOURNAL_FILE_NAME).exists());
+        assert!(!recover(&folder).unwrap());
+
+        // Power loss while the journal was written, the commit line is incomplete
+        write_synced(&temporary(&a), &["3".to_string()]).unwrap();
+        fs::write(folder.join(JOURNAL_FILE_NAME), format!("{}\ncom", a.display())).unwrap();
+        assert!(!recover(&folder).unwrap());
+        assert_eq!(fs::read_to_string(&a).unwrap(), "1\n");
+        assert!(!temporary(&a).exists());
+
+        // Power loss before any rename
+        write_synced(&temporary(&a), &["4".to_string()]).unwrap();
+        write_synced(
+            &folder.join(JOURNAL_FILE_NAME),
+            &[a.display().to_string(), "commit".to_string()],
+        )
+        .unwrap();
+        assert!(recover(&folder).unwrap());
+        assert_eq!(fs::read_to_string(&a).unwrap(), "4\n");
+        assert!(!folder.join(JOURNAL_FILE_NAME).exists());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -0,0 +1,59 @@
+//! Exclusive lock of the data folder.
+//!
+//! Only one process can write to the data folder. Writable storage holds the `lock` file
+//! locked (`flock`), so `import` fails while the thermometer is running. The lock is
+//! released by the kernel when the file is closed or the process exits (crash included).
+use error::Error;
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::Path;
+
+/// Lock file name inside the data folder.
+const LOCK_FILE_NAME: &str = "lock";
+
+/// Lock the data folder, the folder is locked until the returned file is dropped.
+///
+/// # Arguments
+///
+/// * `folder` - data folder
+pub fn lock(folder: &Path) -> Result<File, Error> {
+    let path = folder.join(LOCK_FILE_NAME);
+    let file = OpenOptions::new()
+        .create(true)
+        .write(true)
+        .truncate(false)
+        .open(&path)
+        .map_err(|e| Error::from(e).context(format!("Unable to open {}", path.display())))?;
+
+    match try_lock(&file) {
+        Ok(()) => Ok(file),
+        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Err(Error::Other(format!(
+            "Data folder {} is used by another process",
+            folder.display()
+        ))),
+        Err(e) => Err(Error::from(e).context(format!("Unable to lock {}", path.display()))),
+    }
+}
+
+/// Lock the file exclusively without waiting.
+///
+/// # Arguments
+///
+/// * `file` - file to lock
+#[cfg(unix)]
+fn try_lock(file: &File) -> io::Result<()> {
+    use libc;
+    use std::os::unix::io::AsRawFd;
+
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+/// Lock the file exclusively without waiting.
+#[cfg(not(unix))]
+fn try_lock(_file: &File) -> io::Result<()> {
+    Ok(())
+}
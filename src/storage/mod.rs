@@ -0,0 +1,1060 @@
+//! Persistent readings storage.
+//!
+//! Readings are appended to the `readings.csv` file in the data folder, one reading
+//! per line: `location,timestamp,raw,filtered,status`. Timestamp is in milliseconds
+//! since the Unix epoch, temperatures are in degrees celsius multiplied by 1_000.0
+//!
+//! Readings are written in batches, incomplete last record left by a crash is removed
+//! when the storage is opened.
+//!
+//! Old readings are compacted into `readings-minute.csv` & `readings-hour.csv`
+//! buckets (see `rollup`). Records are not necessarily in time order (imported readings,
+//! readings held back until the clock is synchronised or late remote readings), so the
+//! expired records are selected from the whole file by their timestamps. The file is
+//! rewritten only once they take at least a quarter of it. Compacted files are streamed
+//! into their new versions which replace them using the write-ahead journal (see
+//! `journal`).
+//!
+//! Only one process can write to the data folder at a time (see `lock`), readings can
+//! be read by other processes at any time.
+mod journal;
+mod lock;
+
+use chrono::{DateTime, Duration as OldDuration, TimeZone, Utc};
+use error::Error;
+use rollup::{aggregate, format_bucket, parse_bucket, Bucket, Resolution, Retention};
+use state::{Location, Reading};
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use w1::thermometer::Temperature;
+
+/// Readings file name inside the data folder.
+const READINGS_FILE_NAME: &str = "readings.csv";
+
+/// Minute buckets file name inside the data folder.
+const MINUTE_FILE_NAME: &str = "readings-minute.csv";
+
+/// Hour buckets file name inside the data folder.
+const HOUR_FILE_NAME: &str = "readings-hour.csv";
+
+/// File is compacted when its expired records take at least 1 / `COMPACTION_THRESHOLD`
+/// of it, files are not rewritten for a few expired records.
+const COMPACTION_THRESHOLD: u64 = 4;
+
+/// Format reading as a storage record (line without new line character).
+///
+/// # Arguments
+///
+/// * `location` - thermometer location
+/// * `reading` - reading
+fn format_record(location: Location, reading: &Reading) -> String {
+    format!(
+        "{},{},{},{},{}",
+        location.as_ref(),
+        reading.timestamp.timestamp_millis(),
+        reading.raw.value(),
+        reading.filtered.value(),
+        reading.status.as_ref()
+    )
+}
+
+/// Parse storage record.
+///
+/// # Arguments
+///
+/// * `line` - record line
+fn parse_record(line: &str) -> Result<(Location, Reading), Error> {
+    let columns: Vec<&str> = line.trim().split(',').collect();
+    if columns.len() != 5 {
+        return Err(Error::Parse(format!("invalid record: {}", line)));
+    }
+
+    let parse_i64 = |s: &str| {
+        s.parse::<i64>()
+            .map_err(|e| Error::Parse(format!("invalid record value {}: {}", s, e)))
+    };
+
+    let location = columns[0].parse::<Location>()?;
+    let millis = parse_i64(columns[1])?;
+    let reading = Reading {
+        timestamp: Utc.timestamp(0, 0) + OldDuration::milliseconds(millis),
+        raw: Temperature::new(parse_i64(columns[2])?),
+        filtered: Temperature::new(parse_i64(columns[3])?),
+        status: columns[4].parse()?,
+    };
+
+    Ok((location, reading))
+}
+
+/// Open file for reading, missing file is `None`.
+///
+/// # Arguments
+///
+/// * `path` - file path
+fn open(path: &Path) -> Result<Option<File>, Error> {
+    match File::open(path) {
+        Ok(file) => Ok(Some(file)),
+        Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(Error::from(e).context(format!("Unable to open {}", path.display()))),
+    }
+}
+
+/// Read file lines, missing file has no lines.
+///
+/// # Arguments
+///
+/// * `path` - file path
+fn read_lines(path: &Path) -> Result<Vec<String>, Error> {
+    match open(path)? {
+        Some(file) => BufReader::new(file).lines().map(|l| l.map_err(Error::from)).collect(),
+        None => Ok(vec![]),
+    }
+}
+
+/// Copy file lines to the writer line by line, missing file has no lines.
+///
+/// # Arguments
+///
+/// * `path` - file path
+/// * `writer` - writer
+/// * `keep` - checks if the line is copied
+fn copy_lines<F>(path: &Path, writer: &mut dyn Write, keep: F) -> Result<(), Error>
+where
+    F: Fn(&str) -> bool,
+{
+    if let Some(file) = open(path)? {
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if keep(&line) {
+                writeln!(writer, "{}", line)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Compact expired records of the file into buckets appended to the target file.
+///
+/// Records don't have to be in time order (imported or late readings), expired ones are
+/// selected from the whole file. Corrupted records are considered expired and they're
+/// dropped. Files are streamed, only the expired records are kept in memory.
+///
+/// Returns number of compacted records or `None` if the file was not rewritten because
+/// the expired records take less than 1 / `COMPACTION_THRESHOLD` of it.
+///
+/// # Arguments
+///
+/// * `folder` - data folder
+/// * `path` - compacted file path
+/// * `target` - path of the file with coarser buckets
+/// * `parse` - parses the record as a bucket
+/// * `cutoff` - records starting before this time are expired
+/// * `resolution` - resolution of the target buckets
+fn compact_file<F>(
+    folder: &Path,
+    path: &Path,
+    target: &Path,
+    parse: F,
+    cutoff: DateTime<Utc>,
+    resolution: Resolution,
+) -> Result<Option<usize>, Error>
+where
+    F: Fn(&str) -> Result<(Location, Bucket), Error>,
+{
+    let expired = |line: &str| parse(line).map(|(_, b)| b.start < cutoff).unwrap_or(true);
+
+    let file = match open(path)? {
+        Some(file) => file,
+        None => return Ok(None),
+    };
+    let size = file.metadata()?.len();
+
+    let mut length = 0;
+    let mut old = vec![];
+    for line in BufReader::new(file).lines() {
+        let line = line?;
+        if expired(&line) {
+            length += line.len() as u64 + 1;
+            if let Ok(bucket) = parse(&line) {
+                old.push(bucket);
+            }
+        }
+    }
+    if length == 0 || length * COMPACTION_THRESHOLD < size {
+        return Ok(None);
+    }
+
+    let compacted = old.len();
+    let buckets = aggregate(old, resolution);
+    journal::replace(
+        folder,
+        &[target.to_path_buf(), path.to_path_buf()],
+        |replaced, writer| {
+            if replaced == target {
+                copy_lines(target, writer, |_| true)?;
+                for (location, bucket) in &buckets {
+                    writeln!(writer, "{}", format_bucket(*location, bucket))?;
+                }
+                Ok(())
+            } else {
+                copy_lines(path, writer, |line| !expired(line))
+            }
+        },
+    )?;
+    Ok(Some(compacted))
+}
+
+/// Remove incomplete last line (without the new line character) left by a crash
+/// in the middle of the write.
+///
+/// Returns number of removed bytes.
+///
+/// # Arguments
+///
+/// * `path` - file path
+fn truncate_tail(path: &Path) -> Result<u64, Error> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(Error::from(e).context(format!("Unable to read {}", path.display()))),
+    };
+
+    let length = content.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+    let truncated = (content.len() - length) as u64;
+    if truncated > 0 {
+        let file = OpenOptions::new().write(true).open(path)?;
+        file.set_len(length as u64)?;
+        file.sync_all()?;
+    }
+    Ok(truncated)
+}
+
+/// Check if the bucket belongs to the range, the whole bucket of the given resolution in
+/// which the range starts is included.
+///
+/// # Arguments
+///
+/// * `l` - bucket location
+/// * `bucket` - bucket
+/// * `location` - range location or `None` for all locations
+/// * `from` - range start or `None` if the range is unbounded
+/// * `to` - range end or `None` if the range is unbounded
+/// * `resolution` - resolution of the range buckets
+fn bucket_in_range(
+    l: Location,
+    bucket: &Bucket,
+    location: Option<Location>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    resolution: Resolution,
+) -> bool {
+    location.map_or(true, |location| location == l)
+        && from.map_or(true, |from| bucket.start >= resolution.bucket_start(from))
+        && to.map_or(true, |to| bucket.start <= to)
+}
+
+/// Error returned by the write operations of the read-only storage.
+///
+/// # Arguments
+///
+/// * `folder` - data folder
+fn read_only(folder: &Path) -> Error {
+    Error::Other(format!("Storage {} is opened read-only", folder.display()))
+}
+
+/// Write batching.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Batch {
+    /// Buffered readings are written when there's this number of them.
+    pub size: usize,
+    /// Buffered readings are written at least this often.
+    pub interval: Duration,
+}
+
+impl Default for Batch {
+    fn default() -> Batch {
+        Batch {
+            size: 1,
+            interval: Duration::from_secs(0),
+        }
+    }
+}
+
+/// Storage recovery done when it was opened.
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct Recovery {
+    /// Interrupted compaction was finished.
+    pub journal: bool,
+    /// Number of bytes of incomplete records removed.
+    pub truncated: u64,
+}
+
+/// Compaction result.
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct Compaction {
+    /// Number of raw readings compacted into minute buckets.
+    pub readings: usize,
+    /// Number of minute buckets compacted into hour buckets.
+    pub minutes: usize,
+}
+
+/// Persistent readings storage.
+///
+/// Appended readings are buffered in memory and written in batches to reduce SD card
+/// wear. Buffered readings are written when the storage is dropped.
+pub struct Storage {
+    folder: PathBuf,
+    path: PathBuf,
+    /// Readings file opened for appending, `None` if the storage is read-only.
+    file: Option<File>,
+    /// Data folder lock held while the storage is open, `None` if it's read-only.
+    _lock: Option<File>,
+    batch: Batch,
+    pending: Vec<String>,
+    flushed: Instant,
+    recovery: Recovery,
+}
+
+impl Storage {
+    /// Open storage in the given folder, folder is created if it doesn't exist.
+    ///
+    /// Data folder is locked, opening fails if it's locked by another process already.
+    /// Interrupted compaction is finished or discarded and incomplete records left
+    /// by a crash are removed.
+    ///
+    /// # Arguments
+    ///
+    /// * `folder` - data folder
+    /// * `batch` - write batching
+    pub fn open<P>(folder: P, batch: Batch) -> Result<Storage, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let folder = folder.as_ref();
+        fs::create_dir_all(folder)
+            .map_err(|e| Error::from(e).context(format!("Unable to create {}", folder.display())))?;
+        let lock = lock::lock(folder)?;
+
+        let mut recovery = Recovery {
+            journal: journal::recover(folder)?,
+            truncated: 0,
+        };
+        for name in &[READINGS_FILE_NAME, MINUTE_FILE_NAME, HOUR_FILE_NAME] {
+            recovery.truncated += truncate_tail(&folder.join(name))?;
+        }
+
+        let path = folder.join(READINGS_FILE_NAME);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| Error::from(e).context(format!("Unable to open {}", path.display())))?;
+
+        Ok(Storage {
+            folder: folder.to_path_buf(),
+            path,
+            file: Some(file),
+            _lock: Some(lock),
+            batch,
+            pending: vec![],
+            flushed: Instant::now(),
+            recovery,
+        })
+    }
+
+    /// Open storage in the given folder for reading only, e.g. while another process
+    /// writes to it.
+    ///
+    /// Data folder is neither locked nor recovered, corrupted records are skipped by
+    /// the queries. Appending, compaction & import fail.
+    ///
+    /// # Arguments
+    ///
+    /// * `folder` - data folder
+    pub fn open_read_only<P>(folder: P) -> Storage
+    where
+        P: AsRef<Path>,
+    {
+        let folder = folder.as_ref();
+
+        Storage {
+            folder: folder.to_path_buf(),
+            path: folder.join(READINGS_FILE_NAME),
+            file: None,
+            _lock: None,
+            batch: Batch::default(),
+            pending: vec![],
+            flushed: Instant::now(),
+            recovery: Recovery::default(),
+        }
+    }
+
+    /// Data folder.
+    pub fn folder(&self) -> &Path {
+        &self.folder
+    }
+
+    /// Recovery done when the storage was opened.
+    pub fn recovery(&self) -> &Recovery {
+        &self.recovery
+    }
+
+    /// Append reading.
+    ///
+    /// Reading is buffered, buffered readings are written when the batch size is reached
+    /// or the batch interval elapsed.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - thermometer location
+    /// * `reading` - reading
+    pub fn append(&mut self, location: Location, reading: &Reading) -> Result<(), Error> {
+        self.pending.push(format_record(location, reading));
+
+        if self.pending.len() >= self.batch.size || self.flushed.elapsed() >= self.batch.interval {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Write buffered readings and sync them to the disk.
+    ///
+    /// Readings stay buffered if the write fails, partially written batch is removed
+    /// so the next flush can write it again.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.flushed = Instant::now();
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+
+        let mut content = String::new();
+        for line in &self.pending {
+            content.push_str(line);
+            content.push('\n');
+        }
+
+        let file = match self.file {
+            Some(ref mut file) => file,
+            None => return Err(read_only(&self.folder)),
+        };
+
+        let length = file.metadata()?.len();
+        if let Err(e) = file.write_all(content.as_bytes()).and_then(|_| file.sync_data()) {
+            let _ = file.set_len(length);
+            return Err(Error::from(e).context(format!("Unable to write {}", self.path.display())));
+        }
+
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Stored readings ordered by the time in which they were stored, buffered
+    /// readings included.
+    ///
+    /// Corrupted records are skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - thermometer location or `None` for all locations
+    /// * `from` - include readings taken at or after this time
+    /// * `to` - include readings taken at or before this time
+    pub fn readings(
+        &self,
+        location: Option<Location>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<(Location, Reading)>, Error> {
+        let mut readings = vec![];
+
+        let lines = read_lines(&self.path)?.into_iter().chain(self.pending.iter().cloned());
+        for line in lines {
+            let (l, reading) = match parse_record(&line) {
+                Ok(record) => record,
+                Err(_) => continue,
+            };
+
+            if location.map_or(true, |location| location == l)
+                && from.map_or(true, |from| reading.timestamp >= from)
+                && to.map_or(true, |to| reading.timestamp <= to)
+            {
+                readings.push((l, reading));
+            }
+        }
+
+        Ok(readings)
+    }
+
+    /// Finest resolution in which the stored readings of the whole range are available,
+    /// i.e. the resolution of the coarsest stored records in the range.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - thermometer location or `None` for all locations
+    /// * `from` - range start or `None` if the range is unbounded
+    /// * `to` - range end or `None` if the range is unbounded
+    pub fn resolution(
+        &self,
+        location: Option<Location>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Resolution, Error> {
+        for &(name, resolution) in &[
+            (HOUR_FILE_NAME, Resolution::Hour),
+            (MINUTE_FILE_NAME, Resolution::Minute),
+        ] {
+            let lines = read_lines(&self.folder.join(name))?;
+            let stored = lines
+                .iter()
+                .filter_map(|line| parse_bucket(line).ok())
+                .any(|(l, bucket)| bucket_in_range(l, &bucket, location, from, to, resolution));
+            if stored {
+                return Ok(resolution);
+            }
+        }
+        Ok(Resolution::Raw)
+    }
+
+    /// Stored readings aggregated into buckets of the given resolution, ordered by
+    /// the bucket start.
+    ///
+    /// Raw readings & minute buckets which were not compacted yet are aggregated too,
+    /// so the whole range is covered. Corrupted records are skipped. Fails if some
+    /// readings of the range are stored only in coarser buckets (see `resolution`).
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - thermometer location or `None` for all locations
+    /// * `from` - include buckets starting at or after this time
+    /// * `to` - include buckets starting at or before this time
+    /// * `resolution` - bucket resolution
+    pub fn buckets(
+        &self,
+        location: Option<Location>,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        resolution: Resolution,
+    ) -> Result<Vec<(Location, Bucket)>, Error> {
+        let stored = self.resolution(location, from, to)?;
+        if stored > resolution {
+            return Err(Error::Other(format!(
+                "Readings are stored in {} buckets, they can't be aggregated into {} buckets",
+                stored.as_ref(),
+                resolution.as_ref()
+            )));
+        }
+
+        // Include the whole bucket in which the range starts
+        let readings = self.readings(location, from.map(|f| resolution.bucket_start(f)), to)?;
+        let mut buckets: Vec<(Location, Bucket)> =
+            readings.iter().map(|(l, r)| (*l, Bucket::from_reading(r))).collect();
+
+        for name in &[MINUTE_FILE_NAME, HOUR_FILE_NAME] {
+            for line in read_lines(&self.folder.join(name))? {
+                if let Ok((l, bucket)) = parse_bucket(&line) {
+                    if bucket_in_range(l, &bucket, location, from, to, resolution) {
+                        buckets.push((l, bucket));
+                    }
+                }
+            }
+        }
+
+        Ok(aggregate(buckets, resolution))
+    }
+
+    /// Compact raw readings & minute buckets older than the retention periods.
+    ///
+    /// Buffered readings are written first. Files are compacted only if their expired
+    /// records take at least 1 / `COMPACTION_THRESHOLD` of them, they're streamed into
+    /// the new files which replace them using the write-ahead journal.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - current time
+    /// * `retention` - retention periods
+    pub fn compact(&mut self, now: DateTime<Utc>, retention: &Retention) -> Result<Compaction, Error> {
+        if self.file.is_none() {
+            return Err(read_only(&self.folder));
+        }
+        self.flush()?;
+        let mut compaction = Compaction::default();
+
+        // Raw readings -> minute buckets
+        let minute_path = self.folder.join(MINUTE_FILE_NAME);
+        let raw = compact_file(
+            &self.folder,
+            &self.path,
+            &minute_path,
+            |line| parse_record(line).map(|(l, r)| (l, Bucket::from_reading(&r))),
+            retention.raw_cutoff(now),
+            Resolution::Minute,
+        )?;
+        if let Some(readings) = raw {
+            // Appended readings must go to the new file
+            self.file = Some(OpenOptions::new().append(true).open(&self.path)?);
+            compaction.readings = readings;
+        }
+
+        // Minute buckets -> hour buckets
+        let minutes = compact_file(
+            &self.folder,
+            &minute_path,
+            &self.folder.join(HOUR_FILE_NAME),
+            parse_bucket,
+            retention.minute_cutoff(now),
+            Resolution::Hour,
+        )?;
+        compaction.minutes = minutes.unwrap_or(0);
+
+        Ok(compaction)
+    }
+
+    /// Import readings, already stored readings (location & timestamp) are skipped.
+    ///
+    /// Readings in the ranges which were already compacted (location & minute or hour
+    /// bucket) are skipped too, stored buckets would count them twice.
+    ///
+    /// Returns number of imported readings.
+    ///
+    /// # Arguments
+    ///
+    /// * `readings` - readings to import
+    pub fn import(&mut self, mut readings: Vec<(Location, Reading)>) -> Result<usize, Error> {
+        if self.file.is_none() {
+            return Err(read_only(&self.folder));
+        }
+
+        let mut stored: HashSet<(Location, i64)> = self
+            .readings(None, None, None)?
+            .iter()
+            .map(|(l, r)| (*l, r.timestamp.timestamp_millis()))
+            .collect();
+        let mut compacted: HashSet<(Location, i64)> = HashSet::new();
+        for name in &[MINUTE_FILE_NAME, HOUR_FILE_NAME] {
+            for line in read_lines(&self.folder.join(name))? {
+                if let Ok((l, bucket)) = parse_bucket(&line) {
+                    compacted.insert((l, bucket.start.timestamp_millis()));
+                }
+            }
+        }
+        let is_compacted = |location: Location, reading: &Reading| {
+            [Resolution::Minute, Resolution::Hour].iter().any(|resolution| {
+                let start = resolution.bucket_start(reading.timestamp);
+                compacted.contains(&(location, start.timestamp_millis()))
+            })
+        };
+
+        readings.sort_by_key(|(_, r)| r.timestamp);
+
+        let mut imported = 0;
+        for (location, reading) in readings {
+            if !is_compacted(location, &reading) && stored.insert((location, reading.timestamp.timestamp_millis())) {
+                self.append(location, &reading)?;
+                imported += 1;
+            }
+        }
+
+        self.flush()?;
+        Ok(imported)
+    }
+}
+
+/// Buffered readings are written when the storage is dropped.
+impl Drop for Storage {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{format_record, parse_record, Batch, Compaction, Recovery, Storage, READINGS_FILE_NAME};
+    use chrono::{Duration, TimeZone, Utc};
+    use rollup::{Resolution, Retention};
+    use state::{Location, Reading, Status};
+    use std::env;
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use std::time::Duration as StdDuration;
+    use w1::thermometer::Temperature;
+
+    fn reading(millis: i64, value: i64) -> Reading {
+        Reading {
+            timestamp: Utc.timestamp(millis / 1_000, (millis % 1_000) as u32 * 1_000_000),
+            raw: Temperature::new(value),
+            filtered: Temperature::new(value - 100),
+            status: Status::Rejected,
+        }
+    }
+
+    #[test]
+    fn test_record_round_trip() {
+        let line = format_record(Location::Outside, &reading(1_530_446_400_123, -2_500));
+        assert_eq!(line, "outside,1530446400123,-2500,-2600,rejected");
+
+        let (location, parsed) = parse_record(&line).unwrap();
+        assert_eq!(location, Location::Outside);
+        assert_eq!(parsed.timestamp.timestamp_millis(), 1_530_446_400_123);
+        assert_eq!(parsed.raw.value(), -2_500);
+        assert_eq!(parsed.filtered.value(), -2_600);
+        assert_eq!(parsed.status, Status::Rejected);
+
+        assert!(parse_record("outside,1530446400123,-2500").is_err());
+        assert!(parse_record("Garage,1530446400123,-2500,-2600,ok").is_err());
+    }
+
+    #[test]
+    fn test_append_query_and_import() {
+        let folder = env::temp_dir().join("thermometer-storage-test");
+        let _ = fs::remove_dir_all(&folder);
+
+        let mut storage = Storage::open(&folder, Batch::default()).unwrap();
+        storage.append(Location::Inside, &reading(1_000, 20_000)).unwrap();
+        storage.append(Location::Outside, &reading(2_000, 10_000)).unwrap();
+        storage.append(Location::Inside, &reading(3_000, 21_000)).unwrap();
+
+        let inside = storage
+            .readings(Some(Location::Inside), Some(Utc.timestamp(2, 0)), None)
+            .unwrap();
+        assert_eq!(inside.len(), 1);
+        assert_eq!(inside[0].1.raw.value(), 21_000);
+
+        let imported = storage
+            .import(vec![
+                (Location::Inside, reading(3_000, 30_000)),
+                (Location::Inside, reading(4_000, 22_000)),
+                (Location::Inside, reading(4_000, 22_000)),
+                (Location::Outside, reading(1_000, 9_000)),
+            ])
+            .unwrap();
+        assert_eq!(imported, 2);
+        assert_eq!(storage.readings(None, None, None).unwrap().len(), 5);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_compact() {
+        let folder = env::temp_dir().join("thermometer-storage-compact-test");
+        let _ = fs::remove_dir_all(&folder);
+        let mut storage = Storage::open(&folder, Batch::default()).unwrap();
+
+        // 2018-07-01 12:00:00, 12:00:30, 12:01:00 & 13:00:00
+        for (millis, value) in &[
+            (1_530_446_400_000, 20_000),
+            (1_530_446_430_000, 21_000),
+            (1_530_446_460_000, 22_000),
+            (1_530_450_000_000, 23_000),
+        ] {
+            storage.append(Location::Inside, &reading(*millis, *value)).unwrap();
+        }
+
+        let retention = Retention {
+            raw: Duration::days(1),
+            minute: Duration::days(2),
+        };
+        let now = Utc.ymd(2018, 7, 2).and_hms(12, 30, 0);
+        assert_eq!(
+            storage.compact(now, &retention).unwrap(),
+            Compaction {
+                readings: 3,
+                minutes: 0
+            }
+        );
+        assert_eq!(storage.readings(None, None, None).unwrap().len(), 1);
+
+        // New readings go to the rewritten file
+        storage
+            .append(Location::Inside, &reading(1_530_536_400_000, 24_000))
+            .unwrap();
+        assert_eq!(storage.readings(None, None, None).unwrap().len(), 2);
+
+        let minutes = storage.buckets(None, None, None, Resolution::Minute).unwrap();
+        assert_eq!(minutes.len(), 4);
+        assert_eq!(minutes[0].1.count, 2);
+        assert_eq!(minutes[0].1.min.value(), 19_900);
+        assert_eq!(minutes[0].1.max.value(), 20_900);
+
+        let hours = storage.buckets(None, None, None, Resolution::Hour).unwrap();
+        assert_eq!(hours.len(), 3);
+        assert_eq!(hours[0].1.count, 3);
+        assert_eq!(hours[0].1.mean().value(), 20_900);
+
+        // Nothing to compact
+        assert_eq!(storage.compact(now, &retention).unwrap(), Compaction::default());
+
+        let now = Utc.ymd(2018, 7, 3).and_hms(14, 0, 0);
+        assert_eq!(storage.compact(now, &retention).unwrap().minutes, 3);
+        assert_eq!(storage.buckets(None, None, None, Resolution::Hour).unwrap(), hours);
+        assert_eq!(
+            storage
+                .buckets(
+                    Some(Location::Inside),
+                    Some(Utc.ymd(2018, 7, 1).and_hms(12, 30, 0)),
+                    None,
+                    Resolution::Hour
+                )
+                .unwrap()
+                .len(),
+            3
+        );
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_stored_resolution() {
+        let folder = env::temp_dir().join("thermometer-storage-resolution-test");
+        let _ = fs::remove_dir_all(&folder);
+        let mut storage = Storage::open(&folder, Batch::default()).unwrap();
+
+        // 2018-07-01 12:00:00 & 2018-07-02 12:00:00
+        storage
+            .append(Location::Inside, &reading(1_530_446_400_000, 20_000))
+            .unwrap();
+        storage
+            .append(Location::Inside, &reading(1_530_532_800_000, 21_000))
+            .unwrap();
+        let old = Some(Utc.ymd(2018, 7, 1).and_hms(0, 0, 0));
+        let new = Some(Utc.ymd(2018, 7, 2).and_hms(0, 0, 0));
+        assert_eq!(storage.resolution(None, old, None).unwrap(), Resolution::Raw);
+
+        let retention = Retention {
+            raw: Duration::days(1),
+            minute: Duration::days(2),
+        };
+
+        // Raw readings of the old range are compacted into minutes
+        let now = Utc.ymd(2018, 7, 2).and_hms(12, 30, 0);
+        assert_eq!(storage.compact(now, &retention).unwrap().readings, 1);
+        assert_eq!(storage.resolution(None, old, None).unwrap(), Resolution::Minute);
+        assert_eq!(storage.resolution(None, new, None).unwrap(), Resolution::Raw);
+        assert_eq!(storage.resolution(None, old, new).unwrap(), Resolution::Minute);
+        assert_eq!(storage.readings(None, old, new).unwrap().len(), 0);
+
+        // Minute buckets of the old range are compacted into hours
+        let now = Utc.ymd(2018, 7, 3).and_hms(14, 0, 0);
+        assert_eq!(storage.compact(now, &retention).unwrap().minutes, 1);
+        assert_eq!(storage.resolution(None, old, None).unwrap(), Resolution::Hour);
+        assert_eq!(
+            storage.resolution(Some(Location::Outside), old, None).unwrap(),
+            Resolution::Raw
+        );
+        assert_eq!(
+            storage
+                .buckets(None, old, None, Resolution::Minute)
+                .err()
+                .unwrap()
+                .to_string(),
+            "Readings are stored in hour buckets, they can't be aggregated into minute buckets"
+        );
+        assert_eq!(storage.buckets(None, old, None, Resolution::Hour).unwrap().len(), 2);
+        assert_eq!(storage.buckets(None, new, None, Resolution::Minute).unwrap().len(), 1);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_import_compacted() {
+        let folder = env::temp_dir().join("thermometer-storage-import-compacted-test");
+        let _ = fs::remove_dir_all(&folder);
+        let mut storage = Storage::open(&folder, Batch::default()).unwrap();
+
+        // 2018-07-01 12:00:00 & 2018-07-02 12:00:00
+        let old = reading(1_530_446_400_000, 20_000);
+        let new = reading(1_530_532_800_000, 21_000);
+        storage.append(Location::Inside, &old).unwrap();
+        storage.append(Location::Inside, &new).unwrap();
+
+        let retention = Retention {
+            raw: Duration::days(1),
+            minute: Duration::days(2),
+        };
+        let now = Utc.ymd(2018, 7, 2).and_hms(12, 30, 0);
+        assert_eq!(storage.compact(now, &retention).unwrap().readings, 1);
+
+        // Backup contains the compacted reading, another one in the same minute & new one
+        let imported = storage
+            .import(vec![
+                (Location::Inside, old),
+                (Location::Inside, reading(1_530_446_430_000, 30_000)),
+                (Location::Outside, old),
+                (Location::Inside, new),
+            ])
+            .unwrap();
+        assert_eq!(imported, 1);
+
+        let now = Utc.ymd(2018, 7, 3).and_hms(14, 0, 0);
+        storage.compact(now, &retention).unwrap();
+        let hours = storage
+            .buckets(Some(Location::Inside), None, None, Resolution::Hour)
+            .unwrap();
+        assert_eq!(hours.len(), 2);
+        assert_eq!(hours[0].1.count, 1);
+        assert_eq!(hours[0].1.max.value(), 19_900);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_compaction_threshold() {
+        let folder = env::temp_dir().join("thermometer-storage-threshold-test");
+        let _ = fs::remove_dir_all(&folder);
+        let mut storage = Storage::open(&folder, Batch::default()).unwrap();
+
+        // 2018-07-01 12:00:00 & 2018-07-02 12:00:00 - 12:04:00
+        storage
+            .append(Location::Inside, &reading(1_530_446_400_000, 20_000))
+            .unwrap();
+        for minute in 0..5 {
+            storage
+                .append(Location::Inside, &reading(1_530_532_800_000 + minute * 60_000, 21_000))
+                .unwrap();
+        }
+
+        let retention = Retention {
+            raw: Duration::days(1),
+            minute: Duration::days(2),
+        };
+        let now = Utc.ymd(2018, 7, 2).and_hms(13, 0, 0);
+        assert_eq!(storage.compact(now, &retention).unwrap(), Compaction::default());
+        assert_eq!(storage.readings(None, None, None).unwrap().len(), 6);
+
+        // Expired records take more than a quarter of the file
+        let now = Utc.ymd(2018, 7, 3).and_hms(12, 2, 30);
+        assert_eq!(storage.compact(now, &retention).unwrap().readings, 3);
+        assert_eq!(storage.readings(None, None, None).unwrap().len(), 3);
+        assert_eq!(storage.buckets(None, None, None, Resolution::Minute).unwrap().len(), 6);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_compact_out_of_order() {
+        let folder = env::temp_dir().join("thermometer-storage-order-test");
+        let _ = fs::remove_dir_all(&folder);
+        let mut storage = Storage::open(&folder, Batch::default()).unwrap();
+
+        // 2018-07-02 12:00:00, then 2018-07-01 12:00:00 & 12:00:30 imported or held back
+        for (millis, value) in &[
+            (1_530_532_800_000, 22_000),
+            (1_530_446_400_000, 20_000),
+            (1_530_446_430_000, 21_000),
+        ] {
+            storage.append(Location::Inside, &reading(*millis, *value)).unwrap();
+        }
+
+        let retention = Retention {
+            raw: Duration::days(1),
+            minute: Duration::days(2),
+        };
+        let now = Utc.ymd(2018, 7, 2).and_hms(12, 30, 0);
+        assert_eq!(storage.compact(now, &retention).unwrap().readings, 2);
+
+        let readings = storage.readings(None, None, None).unwrap();
+        assert_eq!(readings.len(), 1);
+        assert_eq!(readings[0].1.raw.value(), 22_000);
+        let minutes = storage.buckets(None, None, None, Resolution::Minute).unwrap();
+        assert_eq!(minutes.len(), 2);
+        assert_eq!(minutes[0].1.count, 2);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_lock_and_read_only() {
+        let folder = env::temp_dir().join("thermometer-storage-lock-test");
+        let _ = fs::remove_dir_all(&folder);
+
+        let mut storage = Storage::open(&folder, Batch::default()).unwrap();
+        storage.append(Location::Inside, &reading(1_000, 20_000)).unwrap();
+        assert_eq!(
+            Storage::open(&folder, Batch::default()).err().unwrap().to_string(),
+            format!("Data folder {} is used by another process", folder.display())
+        );
+
+        // Read-only storage can be used while the folder is locked
+        let mut read_only = Storage::open_read_only(&folder);
+        assert_eq!(read_only.readings(None, None, None).unwrap().len(), 1);
+        assert!(read_only.append(Location::Inside, &reading(2_000, 20_000)).is_err());
+        assert!(read_only.import(vec![]).is_err());
+        assert!(read_only
+            .compact(
+                Utc::now(),
+                &Retention {
+                    raw: Duration::days(1),
+                    minute: Duration::days(2),
+                }
+            )
+            .is_err());
+        drop(read_only);
+
+        // Lock is released when the storage is dropped
+        drop(storage);
+        assert!(Storage::open(&folder, Batch::default()).is_ok());
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_batch() {
+        let folder = env::temp_dir().join("thermometer-storage-batch-test");
+        let _ = fs::remove_dir_all(&folder);
+        let path = folder.join(READINGS_FILE_NAME);
+        let batch = Batch {
+            size: 3,
+            interval: StdDuration::from_secs(3_600),
+        };
+
+        let mut storage = Storage::open(&folder, batch).unwrap();
+        storage.append(Location::Inside, &reading(1_000, 20_000)).unwrap();
+        storage.append(Location::Inside, &reading(2_000, 20_000)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "");
+        assert_eq!(storage.readings(None, None, None).unwrap().len(), 2);
+
+        storage.append(Location::Inside, &reading(3_000, 20_000)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 3);
+
+        // Dropped storage writes buffered readings
+        storage.append(Location::Inside, &reading(4_000, 20_000)).unwrap();
+        drop(storage);
+        assert_eq!(fs::read_to_string(&path).unwrap().lines().count(), 4);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn test_truncated_tail_recovery() {
+        let folder = env::temp_dir().join("thermometer-storage-recovery-test");
+        let _ = fs::remove_dir_all(&folder);
+
+        let mut storage = Storage::open(&folder, Batch::default()).unwrap();
+        assert_eq!(storage.recovery(), &Recovery::default());
+        storage.append(Location::Inside, &reading(1_000, 20_000)).unwrap();
+        drop(storage);
+
+        // Crash in the middle of the write
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(folder.join(READINGS_FILE_NAME))
+            .unwrap();
+        write!(file, "inside,2000,2").unwrap();
+
+        let mut storage = Storage::open(&folder, Batch::default()).unwrap();
+        assert_eq!(
+            storage.recovery(),
+            &Recovery {
+                journal: false,
+                truncated: 13
+            }
+        );
+        storage.append(Location::Inside, &reading(3_000, 21_000)).unwrap();
+        let readings = storage.readings(None, None, None).unwrap();
+        assert_eq!(readings.len(), 2);
+        assert_eq!(readings[1].1.raw.value(), 21_000);
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -0,0 +1,121 @@
+//! Temperature trend (rate of change) computed from recent readings.
+use state::Reading;
+
+/// Temperature trend.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Trend {
+    Rising,
+    Falling,
+    Steady,
+}
+
+impl Trend {
+    /// Trend for the given rate of change.
+    ///
+    /// # Arguments
+    ///
+    /// * `rate` - rate of change (degrees celsius per hour)
+    /// * `threshold` - rates lower than this (absolute value) are considered steady
+    pub fn from_rate(rate: f64, threshold: f64) -> Trend {
+        if rate >= threshold && rate > 0.0 {
+            Trend::Rising
+        } else if rate <= -threshold && rate < 0.0 {
+            Trend::Falling
+        } else {
+            Trend::Steady
+        }
+    }
+}
+
+impl AsRef<str> for Trend {
+    fn as_ref(&self) -> &str {
+        match self {
+            Trend::Rising => "rising",
+            Trend::Falling => "falling",
+            Trend::Steady => "steady",
+        }
+    }
+}
+
+/// Rate of change in degrees celsius per hour.
+///
+/// Rate is the slope of the least squares linear regression line of filtered
+/// temperatures. Returns `None` if there are less than two readings or all
+/// readings have the same timestamp.
+///
+/// # Arguments
+///
+/// * `readings` - readings within the trend window, oldest first
+pub fn rate(readings: &[Reading]) -> Option<f64> {
+    let first = readings.first()?;
+    if readings.len() < 2 {
+        return None;
+    }
+
+    // Hours since the first reading & celsius degrees
+    let points: Vec<(f64, f64)> = readings
+        .iter()
+        .map(|r| {
+            let millis = r.timestamp.signed_duration_since(first.timestamp).num_milliseconds();
+            (millis as f64 / 3_600_000.0, r.filtered.celsius())
+        })
+        .collect();
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+
+    let covariance: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    let variance: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+
+    if variance > 0.0 {
+        Some(covariance / variance)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{rate, Trend};
+    use chrono::{Duration, TimeZone, Utc};
+    use state::{Reading, Status};
+    use w1::thermometer::Temperature;
+
+    fn reading(minutes: i64, value: i64) -> Reading {
+        Reading {
+            timestamp: Utc.ymd(2018, 7, 1).and_hms(12, 0, 0) + Duration::minutes(minutes),
+            raw: Temperature::new(value),
+            filtered: Temperature::new(value),
+            status: Status::Ok,
+        }
+    }
+
+    #[test]
+    fn test_rate() {
+        assert_eq!(rate(&[]), None);
+        assert_eq!(rate(&[reading(0, 20_000)]), None);
+        assert_eq!(rate(&[reading(0, 20_000), reading(0, 21_000)]), None);
+
+        let readings = vec![reading(0, 20_000), reading(30, 20_500), reading(60, 21_000)];
+        assert!((rate(&readings).unwrap() - 1.0).abs() < 1e-9);
+
+        // Noise is smoothed out
+        let readings = vec![
+            reading(0, 10_000),
+            reading(10, 9_900),
+            reading(20, 9_950),
+            reading(30, 9_700),
+            reading(40, 9_750),
+        ];
+        assert!((rate(&readings).unwrap() - -0.42).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_trend_from_rate() {
+        assert_eq!(Trend::from_rate(0.5, 0.2), Trend::Rising);
+        assert_eq!(Trend::from_rate(-0.5, 0.2), Trend::Falling);
+        assert_eq!(Trend::from_rate(0.1, 0.2), Trend::Steady);
+        assert_eq!(Trend::from_rate(0.0, 0.0), Trend::Steady);
+    }
+}
@@ -1,5 +1,6 @@
 use error::Error;
 use futures::{Future, Stream};
+use std::io::ErrorKind;
 use tokio_codec::{FramedRead, LinesCodec};
 use tokio_fs::OpenOptions;
 
@@ -24,10 +25,13 @@ pub trait SlaveDevice: Device {
         Box::new(
             OpenOptions::new()
                 .read(true)
-                .open(path)
+                .open(path.clone())
                 .and_then(|f| Ok(FramedRead::new(f, LinesCodec::new())))
                 .flatten_stream()
-                .map_err(Error::from),
+                .map_err(move |e| match e.kind() {
+                    ErrorKind::NotFound => Error::DeviceNotFound(path.clone()),
+                    _ => Error::from(e),
+                }),
         )
     }
 }
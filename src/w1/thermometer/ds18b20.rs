@@ -42,34 +42,34 @@ impl SlaveDevice for DS18B20 {}
 /// # Arguments
 ///
 /// * `lines` - strings (lines) read from w1_slave device
-fn parse_temperature<S>(lines: &[S]) -> Result<Temperature, Error>
+pub fn parse_temperature<S>(lines: &[S]) -> Result<Temperature, Error>
 where
     S: AsRef<str>,
 {
     let first_line = lines
         .first()
-        .ok_or_else(|| Error::from("Unable to get first temperature line"))?;
+        .ok_or_else(|| Error::Parse("unable to get first temperature line".to_string()))?;
 
     if !first_line.as_ref().ends_with("YES") {
-        return Err(Error::from("Invalid temperature CRC"));
+        return Err(Error::CrcMismatch);
     }
 
     let value = lines
         .get(1)
-        .ok_or_else(|| Error::from("Unable to get second temperature line"))
+        .ok_or_else(|| Error::Parse("unable to get second temperature line".to_string()))
         .and_then(|l| {
             l.as_ref()
                 .split("t=")
                 .nth(1)
-                .ok_or_else(|| Error::from("Missing t= separator"))
+                .ok_or_else(|| Error::Parse("missing t= separator".to_string()))
         })
         .and_then(|v| {
             v.parse::<i64>()
-                .map_err(|e| Error::from(format!("Unable to parse temperature: {}", e)))
+                .map_err(|e| Error::Parse(format!("unable to parse temperature: {}", e)))
         })
         .and_then(|v| match v {
-            -1 => Err(Error::from("Invalid temperature value (-1)")),
-            85_000 => Err(Error::from("Sensor error (t=85000)")),
+            -1 => Err(Error::InvalidReading(v)),
+            85_000 => Err(Error::PowerOnReset),
             _ => Ok(v),
         })?;
 
@@ -143,4 +143,14 @@ mod tests {
         let temp = parse_temperature(&vec!["YES"]);
         assert!(temp.is_err());
     }
+
+    #[test]
+    fn test_parser_error_kinds() {
+        let kind = |first: &str, second: &str| parse_temperature(&[first, second]).unwrap_err().kind();
+
+        assert_eq!(kind("crc=8c NO", "t=27125"), "crc_mismatch");
+        assert_eq!(kind("crc=8c YES", "t=85000"), "power_on_reset");
+        assert_eq!(kind("crc=8c YES", "t=-1"), "invalid_reading");
+        assert_eq!(kind("crc=8c YES", "t=hallo"), "parse");
+    }
 }
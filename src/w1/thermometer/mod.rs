@@ -1,5 +1,10 @@
 pub mod ds18b20;
+pub mod remote;
+pub mod simulation;
 
+use self::ds18b20::DS18B20;
+use self::remote::{Remote, RemoteThermometer, DEVICE_PREFIX as REMOTE_DEVICE_PREFIX};
+use self::simulation::{Simulated, Simulation, DEVICE_PREFIX};
 use error::Error;
 use futures::Future;
 use std::str::FromStr;
@@ -10,11 +15,78 @@ pub trait Thermometer: SlaveDevice {
     fn temperature(&self) -> Box<dyn Future<Item = Temperature, Error = Error> + Send>;
 }
 
+/// Create thermometer for given device.
+///
+/// Devices prefixed with `simulated:` are simulated, devices prefixed with `remote:` are
+/// read from another thermometer instance, all others are `DS18B20`.
+///
+/// # Arguments
+///
+/// * `device` - device name (folder name inside /sys/bus/w1/devices, simulation or remote definition)
+pub fn create(device: &str) -> Result<Box<dyn Thermometer + Send>, Error> {
+    if device.starts_with(DEVICE_PREFIX) {
+        Ok(Box::new(Simulated::new(device)?))
+    } else if device.starts_with(REMOTE_DEVICE_PREFIX) {
+        Ok(Box::new(RemoteThermometer::new(device)?))
+    } else {
+        Ok(Box::new(DS18B20::new(device)))
+    }
+}
+
+/// Validate device name (simulation & remote definitions).
+///
+/// # Arguments
+///
+/// * `device` - device name
+pub fn validate(device: &str) -> Result<(), Error> {
+    if device.starts_with(DEVICE_PREFIX) {
+        device.parse::<Simulation>().map(|_| ())
+    } else if device.starts_with(REMOTE_DEVICE_PREFIX) {
+        device.parse::<Remote>().map(|_| ())
+    } else {
+        Ok(())
+    }
+}
+
 /// Temperature unit.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Units {
     Celsius,
     Fahrenheit,
+    Kelvin,
+}
+
+impl Units {
+    /// Next units (celsius -> fahrenheit -> kelvin -> celsius).
+    pub fn next(self) -> Units {
+        match self {
+            Units::Celsius => Units::Fahrenheit,
+            Units::Fahrenheit => Units::Kelvin,
+            Units::Kelvin => Units::Celsius,
+        }
+    }
+
+    /// Temperature difference in these units.
+    ///
+    /// # Arguments
+    ///
+    /// * `celsius` - temperature difference in celsius degrees
+    pub fn delta(self, celsius: f64) -> f64 {
+        match self {
+            Units::Celsius | Units::Kelvin => celsius,
+            Units::Fahrenheit => celsius * 1.8,
+        }
+    }
+
+    /// Units symbol.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Units::Celsius => "°C",
+            Units::Fahrenheit => "°F",
+            Units::Kelvin => "K",
+        }
+    }
 }
 
 impl AsRef<str> for Units {
@@ -22,6 +94,7 @@ impl AsRef<str> for Units {
         match self {
             Units::Celsius => "celsius",
             Units::Fahrenheit => "fahrenheit",
+            Units::Kelvin => "kelvin",
         }
     }
 }
@@ -33,13 +106,14 @@ impl FromStr for Units {
         match s {
             "celsius" => Ok(Units::Celsius),
             "fahrenheit" => Ok(Units::Fahrenheit),
+            "kelvin" => Ok(Units::Kelvin),
             _ => Err(Error::from(format!("Invalid temperature units: {}", s))),
         }
     }
 }
 
 /// Temperature.
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Temperature {
     /// Value in celsius degrees multiplied by 1_000.0
     value: i64,
@@ -55,6 +129,11 @@ impl Temperature {
         Temperature { value }
     }
 
+    /// Degrees celsius multiplied by 1_000.0
+    pub fn value(&self) -> i64 {
+        self.value
+    }
+
     /// Temperature in celsius degrees.
     pub fn celsius(&self) -> f64 {
         (self.value as f64) / 1_000.0
@@ -65,17 +144,48 @@ impl Temperature {
         self.celsius() * 1.8 + 32.0
     }
 
-    /// Temperature formatted as `String`.
+    /// Temperature in kelvins.
+    pub fn kelvin(&self) -> f64 {
+        self.celsius() + 273.15
+    }
+
+    /// Create new `Temperature` from value in given units.
     ///
     /// # Arguments
     ///
+    /// * `value` - temperature
     /// * `units` - temperature units
-    pub fn to_string(&self, units: Units) -> String {
+    pub fn from_units(value: f64, units: Units) -> Temperature {
+        let celsius = match units {
+            Units::Celsius => value,
+            Units::Fahrenheit => (value - 32.0) / 1.8,
+            Units::Kelvin => value - 273.15,
+        };
+        Temperature::new((celsius * 1_000.0).round() as i64)
+    }
+
+    /// Temperature in given units.
+    ///
+    /// # Arguments
+    ///
+    /// * `units` - temperature units
+    pub fn in_units(self, units: Units) -> f64 {
         match units {
-            Units::Celsius => format!("{:.1} °C", self.celsius()),
-            Units::Fahrenheit => format!("{:.1} °F", self.fahrenheit()),
+            Units::Celsius => self.celsius(),
+            Units::Fahrenheit => self.fahrenheit(),
+            Units::Kelvin => self.kelvin(),
         }
     }
+
+    /// Temperature formatted as `String`.
+    ///
+    /// # Arguments
+    ///
+    /// * `units` - temperature units
+    /// * `precision` - number of decimal places
+    pub fn to_string(self, units: Units, precision: usize) -> String {
+        format!("{:.*} {}", precision, self.in_units(units), units.symbol())
+    }
 }
 
 #[cfg(test)]
@@ -93,9 +203,46 @@ mod tests {
         assert_eq!(Temperature::new(-5_000).fahrenheit(), 23.0);
     }
 
+    #[test]
+    fn test_temperature_from_units() {
+        assert_eq!(Temperature::from_units(-58.0, Units::Fahrenheit).value(), -50_000);
+        assert_eq!(
+            Temperature::from_units(21.5, Units::Celsius).in_units(Units::Celsius),
+            21.5
+        );
+    }
+
     #[test]
     fn test_units_conversion() {
         assert_eq!(Units::Celsius.as_ref().parse::<Units>().unwrap(), Units::Celsius);
         assert_eq!(Units::Fahrenheit.as_ref().parse::<Units>().unwrap(), Units::Fahrenheit);
+        assert_eq!(Units::Kelvin.as_ref().parse::<Units>().unwrap(), Units::Kelvin);
+    }
+
+    #[test]
+    fn test_temperature_kelvin() {
+        assert_eq!(Temperature::new(-273_150).kelvin(), 0.0);
+        assert_eq!(Temperature::from_units(300.0, Units::Kelvin).value(), 26_850);
+    }
+
+    #[test]
+    fn test_temperature_to_string() {
+        let t = Temperature::new(21_456);
+        assert_eq!(t.to_string(Units::Celsius, 0), "21 °C");
+        assert_eq!(t.to_string(Units::Celsius, 3), "21.456 °C");
+        assert_eq!(t.to_string(Units::Fahrenheit, 1), "70.6 °F");
+        assert_eq!(t.to_string(Units::Kelvin, 2), "294.61 K");
+    }
+
+    #[test]
+    fn test_units_cycle() {
+        assert_eq!(Units::Celsius.next().next().next(), Units::Celsius);
+    }
+
+    #[test]
+    fn test_units_delta() {
+        assert_eq!(Units::Celsius.delta(2.0), 2.0);
+        assert_eq!(Units::Fahrenheit.delta(2.0), 3.6);
+        assert_eq!(Units::Kelvin.delta(-2.0), -2.0);
     }
 }
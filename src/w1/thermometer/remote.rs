@@ -0,0 +1,358 @@
+//! Remote thermometer.
+//!
+//! Remote thermometer polls readings of another thermometer instance over HTTP. Device
+//! name format is `remote:URL[,sensor:NAME][,interval:SECONDS][,timeout:SECONDS]` where:
+//!
+//! * `URL` - `http://` URL returning readings in the JSON Lines export format, the request
+//!   asks only for the recent readings of the sensor (`sensor` & `from` query parameters)
+//! * `sensor` - remote sensor name (`inside` by default), its last reading is used
+//! * `interval` - poll interval (5 seconds by default)
+//! * `timeout` - request timeout & max age of the remote reading (30 seconds by default)
+//!
+//! Remote is polled in the background thread, reading the thermometer returns the last
+//! polled temperature or an error if it's older than the timeout.
+use chrono::{DateTime, Duration as OldDuration, SecondsFormat, Utc};
+use error::Error;
+use futures::{future, Future};
+use http_client;
+use serde_json;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, Weak};
+use std::thread;
+use std::time::Duration;
+use w1::device::{Device, SlaveDevice};
+use w1::thermometer::{Temperature, Thermometer, Units};
+
+/// Remote devices prefix.
+pub const DEVICE_PREFIX: &str = "remote:";
+
+/// Remote definition.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Remote {
+    url: String,
+    sensor: String,
+    interval: Duration,
+    timeout: Duration,
+}
+
+impl FromStr for Remote {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = if s.starts_with(DEVICE_PREFIX) {
+            &s[DEVICE_PREFIX.len()..]
+        } else {
+            s
+        };
+        let mut parts = s.split(',');
+
+        let url = parts.next().unwrap_or("").trim();
+        http_client::parse_url(url)?;
+
+        let mut remote = Remote {
+            url: url.to_string(),
+            sensor: "inside".to_string(),
+            interval: Duration::from_secs(5),
+            timeout: Duration::from_secs(30),
+        };
+
+        let seconds = |v: &str| match v.parse::<u64>() {
+            Ok(seconds) if seconds > 0 => Ok(Duration::from_secs(seconds)),
+            _ => Err(Error::from(format!("Invalid remote option value: {}", v))),
+        };
+
+        for option in parts {
+            let mut option = option.trim().splitn(2, ':');
+
+            match (option.next(), option.next()) {
+                (Some("sensor"), Some(sensor)) if !sensor.is_empty() => remote.sensor = sensor.to_string(),
+                (Some("interval"), Some(value)) => remote.interval = seconds(value)?,
+                (Some("timeout"), Some(value)) => remote.timeout = seconds(value)?,
+                _ => return Err(Error::from(format!("Invalid remote option: {}", s))),
+            }
+        }
+
+        Ok(remote)
+    }
+}
+
+/// Exported reading (only the fields needed).
+#[derive(Deserialize)]
+struct Record {
+    sensor: String,
+    timestamp: String,
+    filtered: f64,
+    units: Units,
+}
+
+/// Last reading of the sensor in JSON Lines export.
+///
+/// # Arguments
+///
+/// * `body` - JSON Lines export
+/// * `sensor` - sensor name
+fn parse_last_reading(body: &str, sensor: &str) -> Result<(Temperature, DateTime<Utc>), Error> {
+    let mut last = None;
+
+    for line in body.lines().filter(|l| !l.trim().is_empty()) {
+        let record: Record =
+            serde_json::from_str(line).map_err(|e| Error::Parse(format!("invalid remote reading: {}", e)))?;
+        if record.sensor != sensor {
+            continue;
+        }
+
+        let timestamp = DateTime::parse_from_rfc3339(&record.timestamp)
+            .map_err(|e| Error::Parse(format!("invalid remote timestamp {}: {}", record.timestamp, e)))?
+            .with_timezone(&Utc);
+        last = Some((Temperature::from_units(record.filtered, record.units), timestamp));
+    }
+
+    last.ok_or_else(|| Error::Parse(format!("no remote reading of sensor {}", sensor)))
+}
+
+/// URL of the recent readings of the remote sensor.
+///
+/// Remote sensor name & the oldest useful reading time (`from`) are added to the query,
+/// so the remote can return only the latest readings. Static files ignore the query.
+///
+/// # Arguments
+///
+/// * `remote` - remote definition
+/// * `now` - current time
+fn query_url(remote: &Remote, now: DateTime<Utc>) -> String {
+    let from = now - OldDuration::from_std(remote.timeout).unwrap_or_else(|_| OldDuration::zero());
+
+    format!(
+        "{}{}sensor={}&from={}",
+        remote.url,
+        if remote.url.contains('?') { '&' } else { '?' },
+        remote.sensor,
+        from.to_rfc3339_opts(SecondsFormat::Secs, true)
+    )
+}
+
+/// Remote health (last poll results).
+#[derive(Default)]
+struct Health {
+    /// Last polled temperature and the remote reading time.
+    reading: Option<(Temperature, DateTime<Utc>)>,
+    /// Last poll error, `None` if the last poll succeeded.
+    error: Option<Error>,
+}
+
+impl Health {
+    /// Update health with the poll result.
+    ///
+    /// # Arguments
+    ///
+    /// * `result` - poll result
+    fn update(&mut self, result: Result<(Temperature, DateTime<Utc>), Error>) {
+        match result {
+            Ok(reading) => {
+                self.reading = Some(reading);
+                self.error = None;
+            }
+            Err(e) => self.error = Some(e),
+        };
+    }
+
+    /// Last temperature if it's not older than the timeout.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - max age of the reading
+    /// * `now` - current time
+    fn temperature(&self, timeout: Duration, now: DateTime<Utc>) -> Result<Temperature, Error> {
+        let max_age = OldDuration::from_std(timeout).unwrap_or_else(|_| OldDuration::max_value());
+
+        match (self.reading, &self.error) {
+            (Some((temperature, timestamp)), _) if now.signed_duration_since(timestamp) <= max_age => Ok(temperature),
+            (_, Some(e)) => Err(e.clone().context("Remote reading is outdated")),
+            _ => Err(Error::Timeout),
+        }
+    }
+}
+
+/// Poll the remote until the thermometer is dropped.
+///
+/// # Arguments
+///
+/// * `remote` - remote definition
+/// * `health` - remote health
+fn poll(remote: &Remote, health: &Weak<Mutex<Health>>) {
+    loop {
+        let result = http_client::get(&query_url(remote, Utc::now()), remote.timeout)
+            .and_then(|body| parse_last_reading(&body, &remote.sensor))
+            .map_err(|e| e.context(format!("Unable to poll {}", remote.url)));
+
+        match health.upgrade() {
+            Some(health) => health.lock().unwrap().update(result),
+            None => return,
+        };
+
+        thread::sleep(remote.interval);
+    }
+}
+
+/// Remote thermometer device.
+pub struct RemoteThermometer {
+    path: String,
+    timeout: Duration,
+    health: Arc<Mutex<Health>>,
+}
+
+impl RemoteThermometer {
+    /// Create new `RemoteThermometer` and start polling the remote.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - device name (`remote:URL...`)
+    pub fn new<S>(device: S) -> Result<RemoteThermometer, Error>
+    where
+        S: Into<String>,
+    {
+        let path = device.into();
+        let remote = path.parse::<Remote>()?;
+        let health = Arc::new(Mutex::new(Health::default()));
+
+        let weak = Arc::downgrade(&health);
+        let timeout = remote.timeout;
+        thread::spawn(move || poll(&remote, &weak));
+
+        Ok(RemoteThermometer { path, timeout, health })
+    }
+}
+
+/// `RemoteThermometer` is a device identified by its definition.
+impl Device for RemoteThermometer {
+    fn device_path(&self) -> &str {
+        &self.path
+    }
+}
+
+/// `RemoteThermometer` has no `w1_slave` output, last polled temperature is used.
+impl SlaveDevice for RemoteThermometer {}
+
+/// `RemoteThermometer` is temperature sensor.
+impl Thermometer for RemoteThermometer {
+    fn temperature(&self) -> Box<dyn Future<Item = Temperature, Error = Error> + Send> {
+        let result = self.health.lock().unwrap().temperature(self.timeout, Utc::now());
+        Box::new(future::result(result))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_last_reading, query_url, Health, Remote};
+    use chrono::{Duration, TimeZone, Utc};
+    use error::Error;
+    use http_client;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use std::time::Duration as StdDuration;
+    use w1::thermometer::Temperature;
+
+    const BODY: &str = r#"{"sensor":"inside","timestamp":"2018-07-01T12:00:00.000Z","raw":21.5,"filtered":21.5,"units":"celsius","status":"ok"}
+{"sensor":"outside","timestamp":"2018-07-01T12:00:00.000Z","raw":50.0,"filtered":50.0,"units":"fahrenheit","status":"ok"}
+{"sensor":"inside","timestamp":"2018-07-01T12:00:01.000Z","raw":22.0,"filtered":21.75,"units":"celsius","status":"ok"}
+"#;
+
+    #[test]
+    fn test_parse_remote() {
+        assert_eq!(
+            "remote:http://kitchen:8080/readings".parse::<Remote>().unwrap(),
+            Remote {
+                url: "http://kitchen:8080/readings".to_string(),
+                sensor: "inside".to_string(),
+                interval: StdDuration::from_secs(5),
+                timeout: StdDuration::from_secs(30),
+            }
+        );
+
+        let remote = "remote:http://kitchen/readings,sensor:outside,interval:10,timeout:60"
+            .parse::<Remote>()
+            .unwrap();
+        assert_eq!(remote.sensor, "outside");
+        assert_eq!(remote.interval, StdDuration::from_secs(10));
+        assert_eq!(remote.timeout, StdDuration::from_secs(60));
+
+        assert!("remote:https://kitchen/readings".parse::<Remote>().is_err());
+        assert!("remote:http://kitchen/readings,interval:0".parse::<Remote>().is_err());
+        assert!("remote:http://kitchen/readings,retries:3".parse::<Remote>().is_err());
+    }
+
+    #[test]
+    fn test_query_url() {
+        let now = Utc.ymd(2018, 7, 1).and_hms(12, 0, 0);
+        let remote = "remote:http://kitchen:8080/readings".parse::<Remote>().unwrap();
+        assert_eq!(
+            query_url(&remote, now),
+            "http://kitchen:8080/readings?sensor=inside&from=2018-07-01T11:59:30Z"
+        );
+
+        let remote = "remote:http://kitchen/readings?format=jsonl,sensor:outside,timeout:60"
+            .parse::<Remote>()
+            .unwrap();
+        assert_eq!(
+            query_url(&remote, now),
+            "http://kitchen/readings?format=jsonl&sensor=outside&from=2018-07-01T11:59:00Z"
+        );
+    }
+
+    #[test]
+    fn test_parse_last_reading() {
+        let (temperature, timestamp) = parse_last_reading(BODY, "inside").unwrap();
+        assert_eq!(temperature, Temperature::new(21_750));
+        assert_eq!(timestamp, Utc.ymd(2018, 7, 1).and_hms(12, 0, 1));
+
+        assert_eq!(parse_last_reading(BODY, "outside").unwrap().0, Temperature::new(10_000));
+        assert!(parse_last_reading(BODY, "garage").is_err());
+        assert!(parse_last_reading("inside,21.5", "inside").is_err());
+    }
+
+    #[test]
+    fn test_health() {
+        let mut health = Health::default();
+        let now = Utc.ymd(2018, 7, 1).and_hms(12, 0, 0);
+        let timeout = StdDuration::from_secs(30);
+        assert_eq!(health.temperature(timeout, now).unwrap_err().kind(), "timeout");
+
+        health.update(Ok((Temperature::new(21_000), now)));
+        health.update(Err(Error::from("connection refused")));
+        assert_eq!(
+            health
+                .temperature(timeout, now + Duration::seconds(30))
+                .unwrap()
+                .value(),
+            21_000
+        );
+
+        let error = health.temperature(timeout, now + Duration::seconds(31)).unwrap_err();
+        assert_eq!(error.to_string(), "Remote reading is outdated: connection refused");
+    }
+
+    #[test]
+    fn test_poll_local_stand_in() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/readings", listener.local_addr().unwrap());
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request).unwrap();
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                BODY.len(),
+                BODY
+            )
+            .unwrap();
+        });
+
+        let body = http_client::get(&url, StdDuration::from_secs(5)).unwrap();
+        server.join().unwrap();
+        assert_eq!(parse_last_reading(&body, "inside").unwrap().0.value(), 21_750);
+    }
+}
@@ -0,0 +1,532 @@
+//! Simulated thermometer.
+//!
+//! Simulated thermometer produces the same `w1_slave` output as the real DS18B20 sensor,
+//! thus the values go through the same parser including injected faults.
+//!
+//! Device name format is `simulated:PROFILE[:ARGUMENTS][,faults:PROBABILITY]` where profile is one of:
+//!
+//! * `sine[:MEAN:AMPLITUDE:PERIOD:NOISE]` - diurnal sine (°C, °C, seconds, °C) with peak at 15:00
+//! * `step[:LOW:HIGH:PERIOD]` - alternates between low and high value (°C, °C, seconds)
+//! * `walk[:START:STEP]` - random walk (°C, max °C per read)
+//! * `replay:PATH` - replays recorded CSV file, e.g. the `export` output (`filtered` column
+//!   converted from `units`) or a headerless file with °C in the last column, unparseable
+//!   lines are skipped
+//!
+//! `faults:PROBABILITY` injects CRC failure, power on reset value (85000) or missing device
+//! with the given probability per read.
+use chrono::{DateTime, Local, Timelike};
+use error::Error;
+use futures::{stream, Future, Stream};
+use rand::{self, Rng, XorShiftRng};
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::str::FromStr;
+use std::sync::Mutex;
+use w1::device::{Device, SlaveDevice};
+use w1::thermometer::ds18b20::parse_temperature;
+use w1::thermometer::{Temperature, Thermometer, Units};
+
+/// Simulated devices prefix.
+pub const DEVICE_PREFIX: &str = "simulated:";
+
+/// Time (seconds from midnight) at which the sine profile reaches its maximum.
+const SINE_PEAK_SECONDS: f64 = 15.0 * 3_600.0;
+
+/// DS18B20 measurement range (°C).
+const MIN_CELSIUS: f64 = -55.0;
+const MAX_CELSIUS: f64 = 125.0;
+
+/// Simulation profile.
+#[derive(Clone, Debug, PartialEq)]
+enum Profile {
+    Sine {
+        mean: f64,
+        amplitude: f64,
+        period: f64,
+        noise: f64,
+    },
+    Step {
+        low: f64,
+        high: f64,
+        period: i64,
+    },
+    Walk {
+        current: f64,
+        step: f64,
+    },
+    Replay {
+        values: Vec<f64>,
+        position: usize,
+    },
+}
+
+/// Parse optional positional profile argument.
+///
+/// # Arguments
+///
+/// * `arguments` - profile arguments
+/// * `index` - argument index
+/// * `default` - value used if the argument is not provided
+fn argument<T>(arguments: &[&str], index: usize, default: T) -> Result<T, Error>
+where
+    T: FromStr,
+{
+    match arguments.get(index) {
+        Some(value) => value
+            .parse::<T>()
+            .map_err(|_| Error::from(format!("Invalid simulation argument: {}", value))),
+        None => Ok(default),
+    }
+}
+
+/// Load values (°C) from recorded CSV file.
+///
+/// Files with a header must contain the `filtered` column, values are converted from the
+/// `units` column (°C if it's missing). The last column of headerless files must contain °C.
+///
+/// # Arguments
+///
+/// * `path` - CSV file path
+fn load_replay(path: &str) -> Result<Vec<f64>, Error> {
+    let file = File::open(path).map_err(|e| Error::from(e).context(format!("Unable to open {}", path)))?;
+    let mut lines = BufReader::new(file).lines().peekable();
+
+    // Header is the first line which doesn't end with a number
+    let header = match lines.peek() {
+        Some(Ok(line)) => line
+            .rsplit(',')
+            .next()
+            .map_or(false, |v| v.trim().parse::<f64>().is_err()),
+        _ => false,
+    };
+    let columns = if header {
+        let line = lines.next().unwrap_or_else(|| Ok(String::new()))?;
+        let columns: Vec<String> = line.split(',').map(|c| c.trim().to_string()).collect();
+        let filtered = columns
+            .iter()
+            .position(|c| c == "filtered")
+            .ok_or_else(|| Error::Parse(format!("missing CSV column filtered in {}", path)))?;
+        Some((filtered, columns.iter().position(|c| c == "units")))
+    } else {
+        None
+    };
+
+    let mut values = vec![];
+
+    for line in lines {
+        let line = line?;
+        let fields: Vec<&str> = line.split(',').map(|v| v.trim()).collect();
+        let value = match columns {
+            Some((filtered, units)) => {
+                let units = match units.map(|u| fields.get(u).map(|v| v.parse::<Units>())) {
+                    Some(Some(Ok(units))) => units,
+                    Some(_) => continue,
+                    None => Units::Celsius,
+                };
+                fields
+                    .get(filtered)
+                    .and_then(|v| v.parse::<f64>().ok())
+                    .map(|v| Temperature::from_units(v, units).celsius())
+            }
+            None => fields.last().and_then(|v| v.parse::<f64>().ok()),
+        };
+
+        if let Some(value) = value {
+            values.push(value);
+        }
+    }
+
+    if values.is_empty() {
+        return Err(Error::Parse(format!("no values to replay in {}", path)));
+    }
+
+    Ok(values)
+}
+
+impl FromStr for Profile {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(2, ':');
+        let name = parts.next().unwrap_or("");
+        let arguments: Vec<&str> = parts.next().map(|a| a.split(':').collect()).unwrap_or_default();
+
+        match name {
+            "sine" => Ok(Profile::Sine {
+                mean: argument(&arguments, 0, 20.0)?,
+                amplitude: argument(&arguments, 1, 5.0)?,
+                period: argument(&arguments, 2, 86_400.0)?,
+                noise: argument(&arguments, 3, 0.1)?,
+            }),
+            "step" => Ok(Profile::Step {
+                low: argument(&arguments, 0, 18.0)?,
+                high: argument(&arguments, 1, 22.0)?,
+                period: argument(&arguments, 2, 60)?,
+            }),
+            "walk" => Ok(Profile::Walk {
+                current: argument(&arguments, 0, 20.0)?,
+                step: argument(&arguments, 1, 0.1)?,
+            }),
+            "replay" => {
+                // Path can contain colons, do not split it
+                let path = s
+                    .splitn(2, ':')
+                    .nth(1)
+                    .ok_or_else(|| Error::from("Missing replay file path"))?;
+                Ok(Profile::Replay {
+                    values: load_replay(path)?,
+                    position: 0,
+                })
+            }
+            _ => Err(Error::from(format!("Invalid simulation profile: {}", name))),
+        }
+    }
+}
+
+/// Simulation definition.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Simulation {
+    profile: Profile,
+    faults: f64,
+}
+
+impl FromStr for Simulation {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let s = if s.starts_with(DEVICE_PREFIX) {
+            &s[DEVICE_PREFIX.len()..]
+        } else {
+            s
+        };
+        let mut parts = s.split(',');
+
+        let profile = parts.next().unwrap_or("").trim().parse::<Profile>()?;
+        let mut faults = 0.0;
+
+        for option in parts {
+            let mut option = option.trim().splitn(2, ':');
+            match (option.next(), option.next().map(|v| v.parse::<f64>())) {
+                (Some("faults"), Some(Ok(probability))) if probability >= 0.0 && probability <= 1.0 => {
+                    faults = probability
+                }
+                _ => return Err(Error::from(format!("Invalid simulation option: {}", s))),
+            }
+        }
+
+        Ok(Simulation { profile, faults })
+    }
+}
+
+/// Simulated sensor output.
+#[derive(Debug, PartialEq)]
+enum Sample {
+    /// Temperature in degrees celsius multiplied by 1_000.0
+    Value(i64),
+    /// CRC check failed.
+    CrcFailure,
+    /// Power on reset value (85000).
+    PowerOnReset,
+    /// Device is not present on the bus.
+    Missing,
+}
+
+/// Simulated values generator.
+struct Generator {
+    simulation: Simulation,
+    rng: XorShiftRng,
+}
+
+impl Generator {
+    /// Generate next sample.
+    ///
+    /// # Arguments
+    ///
+    /// * `now` - current time
+    fn next(&mut self, now: DateTime<Local>) -> Sample {
+        if self.simulation.faults > 0.0 && self.rng.gen::<f64>() < self.simulation.faults {
+            return match self.rng.gen_range(0, 3) {
+                0 => Sample::CrcFailure,
+                1 => Sample::PowerOnReset,
+                _ => Sample::Missing,
+            };
+        }
+
+        let celsius = match self.simulation.profile {
+            Profile::Sine {
+                mean,
+                amplitude,
+                period,
+                noise,
+            } => {
+                let seconds = f64::from(now.num_seconds_from_midnight());
+                let noise = if noise > 0.0 {
+                    self.rng.gen_range(-noise, noise)
+                } else {
+                    0.0
+                };
+                mean + amplitude * (2.0 * PI * (seconds - SINE_PEAK_SECONDS) / period).cos() + noise
+            }
+            Profile::Step { low, high, period } => {
+                if (now.timestamp() / period.max(1)) % 2 == 0 {
+                    low
+                } else {
+                    high
+                }
+            }
+            Profile::Walk { ref mut current, step } => {
+                if step > 0.0 {
+                    *current += self.rng.gen_range(-step, step);
+                }
+                *current = current.max(MIN_CELSIUS).min(MAX_CELSIUS);
+                *current
+            }
+            Profile::Replay {
+                ref values,
+                ref mut position,
+            } => {
+                let value = values[*position % values.len()];
+                *position = (*position + 1) % values.len();
+                value
+            }
+        };
+
+        Sample::Value((celsius * 1_000.0).round() as i64)
+    }
+}
+
+/// Simulated thermometer device.
+pub struct Simulated {
+    path: String,
+    generator: Mutex<Generator>,
+}
+
+impl Simulated {
+    /// Create new `Simulated` thermometer device.
+    ///
+    /// # Arguments
+    ///
+    /// * `device` - device name (`simulated:PROFILE...`)
+    pub fn new<S>(device: S) -> Result<Simulated, Error>
+    where
+        S: Into<String>,
+    {
+        let path = device.into();
+        let simulation = path.parse::<Simulation>()?;
+
+        Ok(Simulated {
+            path,
+            generator: Mutex::new(Generator {
+                simulation,
+                rng: rand::weak_rng(),
+            }),
+        })
+    }
+}
+
+/// `Simulated` is W1 device.
+impl Device for Simulated {
+    fn device_path(&self) -> &str {
+        &self.path
+    }
+}
+
+/// `Simulated` is W1 slave device producing DS18B20 `w1_slave` output.
+impl SlaveDevice for Simulated {
+    fn lines(&self) -> Box<dyn Stream<Item = String, Error = Error> + Send> {
+        let lines = match self.generator.lock().unwrap().next(Local::now()) {
+            Sample::Value(value) => vec![
+                "50 05 4b 46 7f ff 0c 10 1c : crc=1c YES".to_string(),
+                format!("50 05 4b 46 7f ff 0c 10 1c t={}", value),
+            ],
+            Sample::CrcFailure => vec![
+                "50 05 4b 46 7f ff 0c 10 1c : crc=1d NO".to_string(),
+                "50 05 4b 46 7f ff 0c 10 1c t=85000".to_string(),
+            ],
+            Sample::PowerOnReset => vec![
+                "50 05 4b 46 7f ff 0c 10 1c : crc=1c YES".to_string(),
+                "50 05 4b 46 7f ff 0c 10 1c t=85000".to_string(),
+            ],
+            Sample::Missing => {
+                return Box::new(stream::once(Err(Error::DeviceNotFound(self.path.clone()))));
+            }
+        };
+
+        Box::new(stream::iter_ok(lines))
+    }
+}
+
+/// `Simulated` is W1 temperature sensor.
+impl Thermometer for Simulated {
+    fn temperature(&self) -> Box<dyn Future<Item = Temperature, Error = Error> + Send> {
+        Box::new(self.lines().collect().and_then(|l| parse_temperature(&l)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Generator, Profile, Sample, Simulated, Simulation};
+    use chrono::{Local, TimeZone, Utc};
+    use export::{export, Format};
+    use futures::Future;
+    use rand::{SeedableRng, XorShiftRng};
+    use state::{Location, Reading, Status};
+    use std::env;
+    use std::fs::{self, File};
+    use std::io::Write;
+    use w1::thermometer::{Temperature, Thermometer, Units};
+
+    fn generator(s: &str) -> Generator {
+        Generator {
+            simulation: s.parse::<Simulation>().unwrap(),
+            rng: XorShiftRng::from_seed([1, 2, 3, 4]),
+        }
+    }
+
+    #[test]
+    fn test_parse_simulation() {
+        assert_eq!(
+            "simulated:sine".parse::<Simulation>().unwrap(),
+            Simulation {
+                profile: Profile::Sine {
+                    mean: 20.0,
+                    amplitude: 5.0,
+                    period: 86_400.0,
+                    noise: 0.1,
+                },
+                faults: 0.0,
+            }
+        );
+        assert_eq!(
+            "simulated:step:10:30:5,faults:0.5".parse::<Simulation>().unwrap(),
+            Simulation {
+                profile: Profile::Step {
+                    low: 10.0,
+                    high: 30.0,
+                    period: 5,
+                },
+                faults: 0.5,
+            }
+        );
+        assert!("simulated:sawtooth".parse::<Simulation>().is_err());
+        assert!("simulated:walk:x".parse::<Simulation>().is_err());
+        assert!("simulated:walk,faults:2".parse::<Simulation>().is_err());
+        assert!("simulated:replay:/does/not/exist.csv".parse::<Simulation>().is_err());
+    }
+
+    #[test]
+    fn test_sine_profile() {
+        let mut generator = generator("simulated:sine:20:5:86400:0");
+        assert_eq!(
+            generator.next(Local.ymd(2018, 7, 1).and_hms(15, 0, 0)),
+            Sample::Value(25_000)
+        );
+        assert_eq!(
+            generator.next(Local.ymd(2018, 7, 1).and_hms(3, 0, 0)),
+            Sample::Value(15_000)
+        );
+    }
+
+    #[test]
+    fn test_step_profile() {
+        let mut generator = generator("simulated:step:10:30:60");
+        let now = Local.timestamp(600, 0);
+        assert_eq!(generator.next(now), Sample::Value(10_000));
+        assert_eq!(generator.next(Local.timestamp(660, 0)), Sample::Value(30_000));
+    }
+
+    #[test]
+    fn test_walk_profile_stays_within_step() {
+        let mut generator = generator("simulated:walk:20:0.5");
+        let mut previous = 20_000;
+        for _ in 0..100 {
+            match generator.next(Local::now()) {
+                Sample::Value(value) => {
+                    assert!((value - previous).abs() <= 500);
+                    previous = value;
+                }
+                sample => panic!("Unexpected sample {:?}", sample),
+            }
+        }
+    }
+
+    #[test]
+    fn test_faults() {
+        let mut generator = generator("simulated:sine,faults:1");
+        for _ in 0..10 {
+            if let Sample::Value(value) = generator.next(Local::now()) {
+                panic!("Unexpected value {}", value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_injected_faults_fail_to_parse() {
+        let thermometer = Simulated::new("simulated:walk,faults:1").unwrap();
+        for _ in 0..10 {
+            assert!(thermometer.temperature().wait().is_err());
+        }
+
+        let thermometer = Simulated::new("simulated:step:21:21").unwrap();
+        assert_eq!(thermometer.temperature().wait().unwrap().value(), 21_000);
+    }
+
+    #[test]
+    fn test_replay_profile() {
+        let path = env::temp_dir().join("thermometer-simulation-replay.csv");
+        {
+            let mut file = File::create(&path).unwrap();
+            writeln!(file, "2018-07-01T12:00:00+00:00,21.5").unwrap();
+            writeln!(file, "2018-07-01T12:00:01+00:00,-3.25").unwrap();
+        }
+
+        let mut generator = generator(&format!("simulated:replay:{}", path.display()));
+        fs::remove_file(&path).unwrap();
+
+        let values: Vec<Sample> = (0..3).map(|_| generator.next(Local::now())).collect();
+        assert_eq!(
+            values,
+            vec![Sample::Value(21_500), Sample::Value(-3_250), Sample::Value(21_500)]
+        );
+    }
+
+    #[test]
+    fn test_replay_export() {
+        let reading = |filtered: i64, status: Status| Reading {
+            timestamp: Utc.ymd(2018, 7, 1).and_hms(12, 0, 0),
+            raw: Temperature::new(filtered + 500),
+            filtered: Temperature::new(filtered),
+            status,
+        };
+        let readings = vec![
+            (Location::Inside, reading(21_250, Status::Ok)),
+            (Location::Outside, reading(-5_000, Status::Rejected)),
+        ];
+
+        let path = env::temp_dir().join("thermometer-simulation-replay-export.csv");
+        {
+            let mut file = File::create(&path).unwrap();
+            export(&mut file, &readings, Format::Csv, Units::Fahrenheit).unwrap();
+        }
+
+        let mut generator = generator(&format!("simulated:replay:{}", path.display()));
+        fs::remove_file(&path).unwrap();
+
+        let values: Vec<Sample> = (0..2).map(|_| generator.next(Local::now())).collect();
+        assert_eq!(values, vec![Sample::Value(21_250), Sample::Value(-5_000)]);
+
+        // Header without the filtered column
+        let path = env::temp_dir().join("thermometer-simulation-replay-header.csv");
+        {
+            let mut file = File::create(&path).unwrap();
+            writeln!(file, "timestamp,celsius").unwrap();
+            writeln!(file, "2018-07-01T12:00:00+00:00,21.5").unwrap();
+        }
+
+        let result = format!("simulated:replay:{}", path.display()).parse::<Simulation>();
+        fs::remove_file(&path).unwrap();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,52 @@
+use super::{parse_daily, Forecast, Provider};
+use error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+/// Provider reading the forecast JSON from a file (offline development, tests).
+pub struct FileProvider {
+    path: PathBuf,
+}
+
+impl FileProvider {
+    /// Create new `FileProvider`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - forecast JSON file path
+    pub fn new(path: PathBuf) -> FileProvider {
+        FileProvider { path }
+    }
+}
+
+impl Provider for FileProvider {
+    fn daily(&self) -> Result<Vec<Forecast>, Error> {
+        let json = fs::read_to_string(&self.path)
+            .map_err(|e| Error::from(e).context(format!("Unable to read {}", self.path.display())))?;
+        parse_daily(&json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::Provider;
+    use super::FileProvider;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn test_file_provider() {
+        let path = env::temp_dir().join("thermometer-forecast-test.json");
+        fs::write(
+            &path,
+            r#"{"daily": {"time": ["2018-07-01"], "weather_code": [0], "temperature_2m_max": [25.0], "temperature_2m_min": [14.0]}}"#,
+        )
+        .unwrap();
+
+        let provider = FileProvider::new(path.clone());
+        assert_eq!(provider.daily().unwrap().len(), 1);
+
+        fs::remove_file(&path).unwrap();
+        assert_eq!(provider.daily().unwrap_err().kind(), "io");
+    }
+}
@@ -0,0 +1,31 @@
+use super::{parse_daily, Forecast, Provider};
+use error::Error;
+use http_client;
+use std::time::Duration;
+
+/// Connect, read & write timeout (s).
+const TIMEOUT: u64 = 10;
+
+/// Provider fetching the forecast JSON from an Open-Meteo compatible HTTP API.
+pub struct HttpProvider {
+    url: String,
+}
+
+impl HttpProvider {
+    /// Create new `HttpProvider`.
+    ///
+    /// # Arguments
+    ///
+    /// * `url` - forecast URL (`http://` only)
+    pub fn new(url: String) -> HttpProvider {
+        HttpProvider { url }
+    }
+}
+
+impl Provider for HttpProvider {
+    fn daily(&self) -> Result<Vec<Forecast>, Error> {
+        let json = http_client::get(&self.url, Duration::from_secs(TIMEOUT))
+            .map_err(|e| e.context(format!("Unable to fetch {}", self.url)))?;
+        parse_daily(&json)
+    }
+}
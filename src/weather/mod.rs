@@ -0,0 +1,297 @@
+//! Weather forecast.
+//!
+//! Forecast is fetched from a `Provider` in the background thread. Providers return
+//! daily forecasts in the [Open-Meteo](https://open-meteo.com/) format:
+//!
+//! ```json
+//! {
+//!   "daily": {
+//!     "time": ["2018-07-01", "2018-07-02"],
+//!     "weather_code": [3, 61],
+//!     "temperature_2m_max": [25.1, 21.3],
+//!     "temperature_2m_min": [14.2, 12.9]
+//!   }
+//! }
+//! ```
+mod file;
+mod http;
+
+pub use self::file::FileProvider;
+pub use self::http::HttpProvider;
+
+use chrono::NaiveDate;
+use config;
+use error::Error;
+use http_client;
+use serde_json;
+use slog::Logger;
+use state::SharedState;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::thread;
+use std::time::Duration;
+use w1::thermometer::{Temperature, Units};
+
+/// Weather condition.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Condition {
+    Clear,
+    PartlyCloudy,
+    Cloudy,
+    Fog,
+    Drizzle,
+    Rain,
+    Snow,
+    Thunderstorm,
+}
+
+impl Condition {
+    /// Condition from the WMO weather interpretation code.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - WMO weather code
+    pub fn from_wmo_code(code: u8) -> Result<Condition, Error> {
+        match code {
+            0 => Ok(Condition::Clear),
+            1 | 2 => Ok(Condition::PartlyCloudy),
+            3 => Ok(Condition::Cloudy),
+            45 | 48 => Ok(Condition::Fog),
+            51..=57 => Ok(Condition::Drizzle),
+            61..=67 | 80..=82 => Ok(Condition::Rain),
+            71..=77 | 85 | 86 => Ok(Condition::Snow),
+            95..=99 => Ok(Condition::Thunderstorm),
+            _ => Err(Error::Parse(format!("unknown weather code {}", code))),
+        }
+    }
+}
+
+impl AsRef<str> for Condition {
+    fn as_ref(&self) -> &str {
+        match self {
+            Condition::Clear => "clear",
+            Condition::PartlyCloudy => "partly-cloudy",
+            Condition::Cloudy => "cloudy",
+            Condition::Fog => "fog",
+            Condition::Drizzle => "drizzle",
+            Condition::Rain => "rain",
+            Condition::Snow => "snow",
+            Condition::Thunderstorm => "thunderstorm",
+        }
+    }
+}
+
+/// Daily forecast.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct Forecast {
+    /// Forecast day (local date).
+    pub date: NaiveDate,
+    /// Highest temperature.
+    pub high: Temperature,
+    /// Lowest temperature.
+    pub low: Temperature,
+    /// Prevailing condition.
+    pub condition: Condition,
+}
+
+/// Forecast data provider.
+pub trait Provider {
+    /// Fetch daily forecasts.
+    fn daily(&self) -> Result<Vec<Forecast>, Error>;
+}
+
+#[derive(Deserialize)]
+struct Daily {
+    time: Vec<String>,
+    #[serde(alias = "weathercode")]
+    weather_code: Vec<u8>,
+    temperature_2m_max: Vec<f64>,
+    temperature_2m_min: Vec<f64>,
+}
+
+#[derive(Deserialize)]
+struct Response {
+    daily: Daily,
+}
+
+/// Parse Open-Meteo daily forecast JSON (temperatures in celsius).
+///
+/// # Arguments
+///
+/// * `json` - Open-Meteo JSON response
+pub fn parse_daily(json: &str) -> Result<Vec<Forecast>, Error> {
+    let response: Response =
+        serde_json::from_str(json).map_err(|e| Error::Parse(format!("invalid forecast: {}", e)))?;
+    let daily = response.daily;
+
+    let len = daily.time.len();
+    if daily.weather_code.len() != len || daily.temperature_2m_max.len() != len || daily.temperature_2m_min.len() != len
+    {
+        return Err(Error::Parse(
+            "invalid forecast: daily arrays length mismatch".to_string(),
+        ));
+    }
+
+    (0..len)
+        .map(|i| {
+            Ok(Forecast {
+                date: NaiveDate::parse_from_str(&daily.time[i], "%Y-%m-%d")
+                    .map_err(|e| Error::Parse(format!("invalid forecast date {}: {}", daily.time[i], e)))?,
+                high: Temperature::from_units(daily.temperature_2m_max[i], Units::Celsius),
+                low: Temperature::from_units(daily.temperature_2m_min[i], Units::Celsius),
+                condition: Condition::from_wmo_code(daily.weather_code[i])?,
+            })
+        })
+        .collect()
+}
+
+/// Forecast for the given day.
+///
+/// # Arguments
+///
+/// * `daily` - daily forecasts
+/// * `date` - day
+pub fn forecast_for(daily: &[Forecast], date: NaiveDate) -> Option<Forecast> {
+    daily.iter().find(|f| f.date == date).cloned()
+}
+
+/// Forecast source (`http://...` or `file:/path/to/forecast.json`).
+#[derive(PartialEq, Debug, Clone)]
+pub enum Source {
+    Http(String),
+    File(PathBuf),
+}
+
+impl Source {
+    /// Create provider for this source.
+    pub fn provider(&self) -> Box<dyn Provider + Send> {
+        match self {
+            Source::Http(url) => Box::new(HttpProvider::new(url.clone())),
+            Source::File(path) => Box::new(FileProvider::new(path.clone())),
+        }
+    }
+}
+
+impl FromStr for Source {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("http://") {
+            http_client::parse_url(s)?;
+            Ok(Source::Http(s.to_string()))
+        } else if s.starts_with("file:") {
+            let path = &s["file:".len()..];
+            if path.is_empty() {
+                return Err(Error::from("Invalid weather source: missing file path"));
+            }
+            Ok(Source::File(PathBuf::from(path)))
+        } else if s.starts_with("https://") {
+            Err(Error::from(
+                "Invalid weather source: HTTPS is not supported, use http://",
+            ))
+        } else {
+            Err(Error::from(format!(
+                "Invalid weather source: {}, expected http://... or file:PATH",
+                s
+            )))
+        }
+    }
+}
+
+/// Fetch forecast periodically, last good response is cached and used
+/// if the provider fails.
+///
+/// # Arguments
+///
+/// * `provider` - forecast provider
+/// * `interval` - fetch interval
+/// * `state` - shared application state
+/// * `logger` - logger
+fn background_thread(provider: Box<dyn Provider + Send>, interval: Duration, state: SharedState, logger: Logger) {
+    let mut cached: Vec<Forecast> = vec![];
+
+    loop {
+        match provider.daily() {
+            Ok(daily) => {
+                debug!(logger, "Forecast updated"; "days" => daily.len());
+                cached = daily;
+            }
+            Err(e) => warn!(logger, "Failed to fetch forecast, using cached one"; "error" => %e, "kind" => e.kind()),
+        };
+
+        state.set_forecast(forecast_for(
+            &cached,
+            config::CONFIG.timezone().now().naive_local().date(),
+        ));
+        thread::sleep(interval);
+    }
+}
+
+/// Spawn new thread fetching weather forecast.
+///
+/// # Arguments
+///
+/// * `source` - forecast source
+/// * `interval` - fetch interval
+/// * `state` - shared application state
+/// * `logger` - logger
+pub fn spawn_background_thread(source: &Source, interval: Duration, state: SharedState, logger: Logger) {
+    let provider = source.provider();
+    thread::spawn(move || background_thread(provider, interval, state, logger));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{forecast_for, parse_daily, Condition, Source};
+    use chrono::NaiveDate;
+    use std::path::PathBuf;
+    use w1::thermometer::Temperature;
+
+    const JSON: &str = r#"{
+        "latitude": 50.08,
+        "daily": {
+            "time": ["2018-07-01", "2018-07-02"],
+            "weathercode": [3, 95],
+            "temperature_2m_max": [25.1, 21.3],
+            "temperature_2m_min": [14.2, -2.9]
+        }
+    }"#;
+
+    #[test]
+    fn test_parse_daily() {
+        let daily = parse_daily(JSON).unwrap();
+
+        assert_eq!(daily.len(), 2);
+        assert_eq!(daily[0].date, NaiveDate::from_ymd(2018, 7, 1));
+        assert_eq!(daily[0].high, Temperature::new(25_100));
+        assert_eq!(daily[0].low, Temperature::new(14_200));
+        assert_eq!(daily[0].condition, Condition::Cloudy);
+        assert_eq!(daily[1].low, Temperature::new(-2_900));
+        assert_eq!(daily[1].condition, Condition::Thunderstorm);
+
+        assert_eq!(forecast_for(&daily, NaiveDate::from_ymd(2018, 7, 2)), Some(daily[1]));
+        assert_eq!(forecast_for(&daily, NaiveDate::from_ymd(2018, 7, 3)), None);
+    }
+
+    #[test]
+    fn test_parse_daily_errors() {
+        assert!(parse_daily("{}").is_err());
+        assert!(parse_daily(&JSON.replace("95", "42")).is_err());
+        assert!(parse_daily(&JSON.replace("[25.1, 21.3]", "[25.1]")).is_err());
+    }
+
+    #[test]
+    fn test_source() {
+        assert_eq!(
+            "http://localhost:8080/v1/forecast".parse::<Source>().unwrap(),
+            Source::Http("http://localhost:8080/v1/forecast".to_string())
+        );
+        assert_eq!(
+            "file:/tmp/forecast.json".parse::<Source>().unwrap(),
+            Source::File(PathBuf::from("/tmp/forecast.json"))
+        );
+        assert!("https://api.open-meteo.com/v1/forecast".parse::<Source>().is_err());
+        assert!("file:".parse::<Source>().is_err());
+        assert!("forecast.json".parse::<Source>().is_err());
+    }
+}